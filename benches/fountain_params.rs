@@ -0,0 +1,107 @@
+//! Compares fountain tuning settings under bursty frame loss, as produced by
+//! cameras that lose focus for several frames in a row.
+//!
+//! Run with `cargo bench --bench fountain_params`. Every trial checks that the
+//! reassembled payload matches, so a setting that breaks decoding fails loudly
+//! instead of looking fast.
+
+use quantus_ur::{EncodeOptions, FountainParams, UrDecoder, UrEncoder};
+
+const PAYLOAD_LEN: usize = 2000;
+const TRIALS: usize = 200;
+const BURST_LOSS: [(f64, usize); 3] = [(0.05, 3), (0.10, 5), (0.20, 8)];
+const SETTINGS: [FountainParams; 4] = [
+    FountainParams {
+        seed: 0,
+        mixed_window: None,
+    },
+    FountainParams {
+        seed: 64,
+        mixed_window: None,
+    },
+    FountainParams {
+        seed: 0,
+        mixed_window: Some(5),
+    },
+    FountainParams {
+        seed: 0,
+        mixed_window: Some(20),
+    },
+];
+
+/// SplitMix64, so runs are reproducible without extra dependencies.
+struct Rng(u64);
+
+impl Rng {
+    fn next_f64(&mut self) -> f64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Frames displayed until completion when each frame starts a burst of
+/// `burst` lost frames with probability `start`.
+fn run_trial(
+    payload: &[u8],
+    options: &EncodeOptions,
+    (start, burst): (f64, usize),
+    rng: &mut Rng,
+) -> usize {
+    let mut encoder = UrEncoder::with_options(payload, options).expect("Encoder creation failed");
+    let mut decoder = UrDecoder::new();
+    let mut displayed = 0;
+    let mut lost = 0;
+    while !decoder.is_complete() {
+        let part = encoder.next_part().expect("Part generation failed");
+        displayed += 1;
+        if lost == 0 && rng.next_f64() < start {
+            lost = burst;
+        }
+        if lost > 0 {
+            lost -= 1;
+        } else {
+            decoder.receive(&part).expect("Receive failed");
+        }
+    }
+    assert_eq!(decoder.message().expect("Decoding failed"), payload);
+    displayed
+}
+
+fn main() {
+    let payload: Vec<u8> = (0..PAYLOAD_LEN).map(|i| (i % 251) as u8).collect();
+    let mut rng = Rng(0x5155_414e_5455_5321);
+
+    println!(
+        "{:>5} {:>7} {:>6} {:>6} {:>11} {:>9}",
+        "seed", "window", "burst", "start", "mean shown", "p95 shown"
+    );
+    for fountain in SETTINGS {
+        let options = EncodeOptions {
+            fountain,
+            ..EncodeOptions::default()
+        };
+        for loss in BURST_LOSS {
+            let mut shown: Vec<usize> = (0..TRIALS)
+                .map(|_| run_trial(&payload, &options, loss, &mut rng))
+                .collect();
+            shown.sort_unstable();
+            let mean = shown.iter().sum::<usize>() as f64 / TRIALS as f64;
+            let window = fountain
+                .mixed_window
+                .map_or_else(|| "-".to_string(), |w| w.to_string());
+            println!(
+                "{:>5} {:>7} {:>6} {:>5.0}% {:>11.1} {:>9}",
+                fountain.seed,
+                window,
+                loss.1,
+                loss.0 * 100.0,
+                mean,
+                shown[(TRIALS * 95) / 100]
+            );
+        }
+    }
+}
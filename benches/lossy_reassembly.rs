@@ -0,0 +1,77 @@
+//! Measures how many fountain parts a receiver needs to complete decoding when
+//! a fraction of the animated frames is lost, for several fragment sizes.
+//!
+//! Run with `cargo bench --bench lossy_reassembly`. The output backs the
+//! recommended animation settings: "extra" is the number of frames displayed
+//! beyond the minimal set before the message completed.
+
+use quantus_ur::{UrDecoder, UrEncoder};
+
+const PAYLOAD_LEN: usize = 2000;
+const TRIALS: usize = 200;
+const LOSS_RATES: [f64; 3] = [0.10, 0.25, 0.50];
+const FRAGMENT_LENGTHS: [usize; 4] = [50, 100, 200, 400];
+
+/// SplitMix64, so runs are reproducible without extra dependencies.
+struct Rng(u64);
+
+impl Rng {
+    fn next_f64(&mut self) -> f64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Returns (minimal set size, frames displayed until completion).
+fn run_trial(payload: &[u8], fragment_length: usize, loss: f64, rng: &mut Rng) -> (usize, usize) {
+    let mut encoder = UrEncoder::with_max_fragment_length(payload, fragment_length)
+        .expect("Encoder creation failed");
+    let mut decoder = UrDecoder::new();
+    let mut displayed = 0;
+    while !decoder.is_complete() {
+        let part = encoder.next_part().expect("Part generation failed");
+        displayed += 1;
+        if rng.next_f64() >= loss {
+            decoder.receive(&part).expect("Receive failed");
+        }
+    }
+    (encoder.fragment_count(), displayed)
+}
+
+fn main() {
+    let payload: Vec<u8> = (0..PAYLOAD_LEN).map(|i| (i % 251) as u8).collect();
+    let mut rng = Rng(0x5155_414e_5455_5321);
+
+    println!(
+        "{:>9} {:>6} {:>9} {:>11} {:>10} {:>9}",
+        "fragment", "loss", "minimal", "mean shown", "mean extra", "p95 extra"
+    );
+    for fragment_length in FRAGMENT_LENGTHS {
+        for loss in LOSS_RATES {
+            let mut minimal = 0;
+            let mut extras: Vec<usize> = (0..TRIALS)
+                .map(|_| {
+                    let (count, displayed) = run_trial(&payload, fragment_length, loss, &mut rng);
+                    minimal = count;
+                    displayed.saturating_sub(count)
+                })
+                .collect();
+            extras.sort_unstable();
+            let mean_extra = extras.iter().sum::<usize>() as f64 / TRIALS as f64;
+            let p95 = extras[(TRIALS * 95) / 100];
+            println!(
+                "{:>9} {:>5.0}% {:>9} {:>11.1} {:>10.1} {:>9}",
+                fragment_length,
+                loss * 100.0,
+                minimal,
+                minimal as f64 + mean_extra,
+                mean_extra,
+                p95
+            );
+        }
+    }
+}
@@ -0,0 +1,56 @@
+package com.quantus.ur
+
+class QuantusUrException(message: String) : Exception(message)
+
+/** Native entry points of libquantus_ur_android.so. */
+object QuantusUr {
+    init {
+        System.loadLibrary("quantus_ur_android")
+    }
+
+    @JvmStatic external fun encodeBytes(payload: ByteArray): Array<String>
+    @JvmStatic external fun decodeBytes(parts: Array<String>): ByteArray
+    @JvmStatic external fun isComplete(parts: Array<String>): Boolean
+
+    @JvmStatic external fun encoderNew(payload: ByteArray): Long
+    @JvmStatic external fun encoderNextPart(handle: Long): String
+    @JvmStatic external fun encoderFragmentCount(handle: Long): Int
+    @JvmStatic external fun encoderReset(handle: Long)
+    @JvmStatic external fun encoderFree(handle: Long)
+
+    @JvmStatic external fun decoderNew(): Long
+    @JvmStatic external fun decoderReceive(handle: Long, part: String): Boolean
+    @JvmStatic external fun decoderMessage(handle: Long): ByteArray
+    @JvmStatic external fun decoderFree(handle: Long)
+}
+
+/** Looping animation source. Call [close] when the QR screen is dismissed. */
+class UrEncoder(payload: ByteArray) : AutoCloseable {
+    private var handle = QuantusUr.encoderNew(payload)
+
+    val fragmentCount: Int get() = QuantusUr.encoderFragmentCount(handle)
+
+    fun nextPart(): String = QuantusUr.encoderNextPart(handle)
+
+    fun reset() = QuantusUr.encoderReset(handle)
+
+    override fun close() {
+        QuantusUr.encoderFree(handle)
+        handle = 0
+    }
+}
+
+/** Scanning session. Call [close] when scanning ends. */
+class UrDecoder : AutoCloseable {
+    private var handle = QuantusUr.decoderNew()
+
+    /** Returns true once the message is complete. */
+    fun receive(part: String): Boolean = QuantusUr.decoderReceive(handle, part)
+
+    fun message(): ByteArray = QuantusUr.decoderMessage(handle)
+
+    override fun close() {
+        QuantusUr.decoderFree(handle)
+        handle = 0
+    }
+}
@@ -0,0 +1,220 @@
+//! JNI bindings exposing `quantus_ur` to Kotlin as `com.quantus.ur.QuantusUr`.
+//!
+//! Stateful encoders and decoders are handed to Kotlin as opaque `Long`
+//! handles that must be released with the matching `*Free` call.
+
+use jni::objects::{JByteArray, JClass, JObject, JObjectArray, JString};
+use jni::sys::{jboolean, jbyteArray, jint, jlong, jobjectArray, jstring, JNI_FALSE, JNI_TRUE};
+use jni::JNIEnv;
+use quantus_ur::{QuantusUrError, UrDecoder, UrEncoder};
+
+const EXCEPTION_CLASS: &str = "com/quantus/ur/QuantusUrException";
+
+enum Error {
+    Ur(QuantusUrError),
+    Jni(jni::errors::Error),
+}
+
+impl From<QuantusUrError> for Error {
+    fn from(e: QuantusUrError) -> Self {
+        Error::Ur(e)
+    }
+}
+
+impl From<jni::errors::Error> for Error {
+    fn from(e: jni::errors::Error) -> Self {
+        Error::Jni(e)
+    }
+}
+
+/// Returns the value, or throws a `QuantusUrException` and returns `default`.
+fn or_throw<T>(env: &mut JNIEnv, result: Result<T, Error>, default: T) -> T {
+    match result {
+        Ok(value) => value,
+        Err(Error::Ur(e)) => {
+            let _ = env.throw_new(EXCEPTION_CLASS, e.to_string());
+            default
+        }
+        Err(Error::Jni(jni::errors::Error::JavaException)) => default,
+        Err(Error::Jni(e)) => {
+            let _ = env.throw_new(EXCEPTION_CLASS, e.to_string());
+            default
+        }
+    }
+}
+
+fn to_string_array(env: &mut JNIEnv, parts: &[String]) -> Result<jobjectArray, Error> {
+    let array = env.new_object_array(parts.len() as jint, "java/lang/String", JObject::null())?;
+    for (i, part) in parts.iter().enumerate() {
+        let s = env.new_string(part)?;
+        env.set_object_array_element(&array, i as jint, s)?;
+    }
+    Ok(array.into_raw())
+}
+
+fn from_string_array(env: &mut JNIEnv, array: &JObjectArray) -> Result<Vec<String>, Error> {
+    let len = env.get_array_length(array)?;
+    let mut parts = Vec::with_capacity(len as usize);
+    for i in 0..len {
+        let s = JString::from(env.get_object_array_element(array, i)?);
+        parts.push(env.get_string(&s)?.into());
+    }
+    Ok(parts)
+}
+
+unsafe fn encoder<'a>(handle: jlong) -> &'a mut UrEncoder {
+    &mut *(handle as *mut UrEncoder)
+}
+
+unsafe fn decoder<'a>(handle: jlong) -> &'a mut UrDecoder {
+    &mut *(handle as *mut UrDecoder)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_quantus_ur_QuantusUr_encodeBytes<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    payload: JByteArray<'local>,
+) -> jobjectArray {
+    let result = (|| -> Result<jobjectArray, Error> {
+        let payload = env.convert_byte_array(&payload)?;
+        let parts = quantus_ur::encode_bytes(&payload)?;
+        to_string_array(&mut env, &parts)
+    })();
+    or_throw(&mut env, result, std::ptr::null_mut())
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_quantus_ur_QuantusUr_decodeBytes<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    parts: JObjectArray<'local>,
+) -> jbyteArray {
+    let result = (|| -> Result<jbyteArray, Error> {
+        let parts = from_string_array(&mut env, &parts)?;
+        let payload = quantus_ur::decode_bytes(&parts)?;
+        Ok(env.byte_array_from_slice(&payload)?.into_raw())
+    })();
+    or_throw(&mut env, result, std::ptr::null_mut())
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_quantus_ur_QuantusUr_isComplete<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    parts: JObjectArray<'local>,
+) -> jboolean {
+    let result = from_string_array(&mut env, &parts)
+        .map(|parts| quantus_ur::is_complete(&parts) as jboolean);
+    or_throw(&mut env, result, JNI_FALSE)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_quantus_ur_QuantusUr_encoderNew<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    payload: JByteArray<'local>,
+) -> jlong {
+    let result = (|| -> Result<jlong, Error> {
+        let payload = env.convert_byte_array(&payload)?;
+        let encoder = UrEncoder::new(&payload)?;
+        Ok(Box::into_raw(Box::new(encoder)) as jlong)
+    })();
+    or_throw(&mut env, result, 0)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_quantus_ur_QuantusUr_encoderNextPart<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+) -> jstring {
+    let result = (|| -> Result<jstring, Error> {
+        let part = unsafe { encoder(handle) }.next_part()?;
+        Ok(env.new_string(part)?.into_raw())
+    })();
+    or_throw(&mut env, result, std::ptr::null_mut())
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_quantus_ur_QuantusUr_encoderFragmentCount<'local>(
+    _env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+) -> jint {
+    unsafe { encoder(handle) }.fragment_count() as jint
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_quantus_ur_QuantusUr_encoderReset<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+) {
+    let result = unsafe { encoder(handle) }.reset().map_err(Error::from);
+    or_throw(&mut env, result, ())
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_quantus_ur_QuantusUr_encoderFree<'local>(
+    _env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+) {
+    if handle != 0 {
+        drop(unsafe { Box::from_raw(handle as *mut UrEncoder) });
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_quantus_ur_QuantusUr_decoderNew<'local>(
+    _env: JNIEnv<'local>,
+    _class: JClass<'local>,
+) -> jlong {
+    Box::into_raw(Box::new(UrDecoder::new())) as jlong
+}
+
+/// Feeds one scanned part and returns whether the message is complete.
+#[no_mangle]
+pub extern "system" fn Java_com_quantus_ur_QuantusUr_decoderReceive<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+    part: JString<'local>,
+) -> jboolean {
+    let result = (|| -> Result<jboolean, Error> {
+        let part: String = env.get_string(&part)?.into();
+        let decoder = unsafe { decoder(handle) };
+        decoder.receive(&part)?;
+        Ok(if decoder.is_complete() {
+            JNI_TRUE
+        } else {
+            JNI_FALSE
+        })
+    })();
+    or_throw(&mut env, result, JNI_FALSE)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_quantus_ur_QuantusUr_decoderMessage<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+) -> jbyteArray {
+    let result = (|| -> Result<jbyteArray, Error> {
+        let payload = unsafe { decoder(handle) }.message()?;
+        Ok(env.byte_array_from_slice(&payload)?.into_raw())
+    })();
+    or_throw(&mut env, result, std::ptr::null_mut())
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_quantus_ur_QuantusUr_decoderFree<'local>(
+    _env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+) {
+    if handle != 0 {
+        drop(unsafe { Box::from_raw(handle as *mut UrDecoder) });
+    }
+}
@@ -0,0 +1,145 @@
+use anyhow::Result;
+use flutter_rust_bridge::frb;
+use quantus_ur::response::{SignResponse, RESPONSE_UR_TYPE};
+use quantus_ur::{config, UrDecoder, UrEncoder};
+
+use crate::frb_generated::StreamSink;
+
+/// A fully reassembled UR message, typed by its UR type.
+pub enum DecodedMessage {
+    /// A `quantus-sign-request` payload to be signed.
+    SignRequest { payload: Vec<u8> },
+    /// A `quantus-sign-response` answering the request whose payload hashes
+    /// to `request_hash`.
+    SignResponse {
+        request_hash: Vec<u8>,
+        signature: Vec<u8>,
+        public_key: Option<Vec<u8>>,
+    },
+    /// Any other UR type, with its payload undecoded.
+    Other { ur_type: String, payload: Vec<u8> },
+}
+
+impl DecodedMessage {
+    fn from_decoder(decoder: &UrDecoder) -> Result<Self> {
+        let ur_type = decoder.ur_type().unwrap_or_default();
+        Ok(if ur_type == config::sign_request_ur_type() {
+            DecodedMessage::SignRequest {
+                payload: decoder.message()?,
+            }
+        } else if ur_type == RESPONSE_UR_TYPE {
+            let response = SignResponse::from_decoder(decoder)?;
+            DecodedMessage::SignResponse {
+                request_hash: response.request_hash.to_vec(),
+                signature: response.signature,
+                public_key: response.public_key,
+            }
+        } else {
+            DecodedMessage::Other {
+                ur_type: ur_type.to_string(),
+                payload: decoder.message()?,
+            }
+        })
+    }
+}
+
+/// Events pushed to Dart while a scanning session is fed.
+pub enum DecodeEvent {
+    /// A part was accepted; the message is not complete yet.
+    PartAccepted,
+    /// A part was rejected; the session continues.
+    PartRejected { message: String },
+    /// The message is complete.
+    Complete(DecodedMessage),
+}
+
+#[frb(sync)]
+pub fn encode_bytes(payload: Vec<u8>) -> Result<Vec<String>> {
+    Ok(quantus_ur::encode_bytes(&payload)?)
+}
+
+#[frb(sync)]
+pub fn encode_hex(hex_payload: String) -> Result<Vec<String>> {
+    Ok(quantus_ur::encode_hex(&hex_payload)?)
+}
+
+#[frb(sync)]
+pub fn decode_bytes(parts: Vec<String>) -> Result<Vec<u8>> {
+    Ok(quantus_ur::decode_bytes(&parts)?)
+}
+
+#[frb(sync)]
+pub fn is_complete(parts: Vec<String>) -> bool {
+    quantus_ur::is_complete(&parts)
+}
+
+/// Looping encoder for animated QR display.
+#[frb(opaque)]
+pub struct FlutterUrEncoder {
+    inner: UrEncoder,
+}
+
+impl FlutterUrEncoder {
+    #[frb(sync)]
+    pub fn new(payload: Vec<u8>) -> Result<Self> {
+        Ok(FlutterUrEncoder {
+            inner: UrEncoder::new(&payload)?,
+        })
+    }
+
+    #[frb(sync, getter)]
+    pub fn fragment_count(&self) -> usize {
+        self.inner.fragment_count()
+    }
+
+    #[frb(sync)]
+    pub fn next_part(&mut self) -> Result<String> {
+        Ok(self.inner.next_part()?)
+    }
+
+    #[frb(sync)]
+    pub fn reset(&mut self) -> Result<()> {
+        Ok(self.inner.reset()?)
+    }
+}
+
+/// Streaming scanning session. Every received part results in one event on
+/// the sink passed to [`FlutterUrDecoder::new`], until the message is
+/// complete; parts scanned after that are ignored.
+#[frb(opaque)]
+pub struct FlutterUrDecoder {
+    inner: UrDecoder,
+    sink: StreamSink<DecodeEvent>,
+}
+
+impl FlutterUrDecoder {
+    #[frb(sync)]
+    pub fn new(sink: StreamSink<DecodeEvent>) -> Self {
+        FlutterUrDecoder {
+            inner: UrDecoder::new(),
+            sink,
+        }
+    }
+
+    #[frb(sync)]
+    pub fn receive(&mut self, part: String) -> Result<()> {
+        if self.inner.is_complete() {
+            return Ok(());
+        }
+        let event = match self.inner.receive(&part) {
+            Err(e) => DecodeEvent::PartRejected {
+                message: e.to_string(),
+            },
+            Ok(()) if self.inner.is_complete() => {
+                DecodeEvent::Complete(DecodedMessage::from_decoder(&self.inner)?)
+            }
+            Ok(()) => DecodeEvent::PartAccepted,
+        };
+        self.sink.add(event).map_err(|e| anyhow::anyhow!("{:?}", e))
+    }
+
+    #[frb(sync, getter)]
+    pub fn is_complete(&self) -> bool {
+        self.inner.is_complete()
+    }
+}
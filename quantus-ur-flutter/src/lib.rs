@@ -0,0 +1,9 @@
+//! flutter_rust_bridge bindings for the Flutter companion app.
+//!
+//! `frb_generated.rs` is produced by `flutter_rust_bridge_codegen generate`
+//! (run from this directory) together with the Dart side and is not checked
+//! in, so this crate is not part of the Cargo workspace. CI generates it into a
+//! scratch Dart package before linting and testing the crate.
+
+pub mod api;
+mod frb_generated;
@@ -0,0 +1,121 @@
+//! UniFFI exports for the React Native wallet.
+//!
+//! `uniffi-bindgen-react-native` turns these into a JSI turbo module, so
+//! encoding and fountain decoding run natively instead of in JavaScript.
+
+use std::sync::Mutex;
+
+use quantus_ur::{QuantusUrError, UrDecoder, UrEncoder};
+
+uniffi::setup_scaffolding!();
+
+#[derive(Debug, uniffi::Error)]
+pub enum UrError {
+    Failed { kind: String, message: String },
+}
+
+impl From<QuantusUrError> for UrError {
+    fn from(e: QuantusUrError) -> Self {
+        UrError::Failed {
+            kind: e.kind().to_string(),
+            message: e.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for UrError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UrError::Failed { message, .. } => f.write_str(message),
+        }
+    }
+}
+
+/// Scan progress of an [`RnUrDecoder`].
+#[derive(uniffi::Record)]
+pub struct DecodeProgress {
+    pub received: u32,
+    /// Zero until the first part has been received.
+    pub expected: u32,
+    pub complete: bool,
+}
+
+#[uniffi::export]
+pub fn encode_bytes(payload: Vec<u8>) -> Result<Vec<String>, UrError> {
+    Ok(quantus_ur::encode_bytes(&payload)?)
+}
+
+#[uniffi::export]
+pub fn decode_bytes(parts: Vec<String>) -> Result<Vec<u8>, UrError> {
+    Ok(quantus_ur::decode_bytes(&parts)?)
+}
+
+#[uniffi::export]
+pub fn is_complete(parts: Vec<String>) -> bool {
+    quantus_ur::is_complete(&parts)
+}
+
+#[derive(uniffi::Object)]
+pub struct RnUrEncoder {
+    inner: Mutex<UrEncoder>,
+}
+
+#[uniffi::export]
+impl RnUrEncoder {
+    #[uniffi::constructor]
+    pub fn new(payload: Vec<u8>) -> Result<Self, UrError> {
+        Ok(RnUrEncoder {
+            inner: Mutex::new(UrEncoder::new(&payload)?),
+        })
+    }
+
+    pub fn fragment_count(&self) -> u32 {
+        self.inner.lock().unwrap().fragment_count() as u32
+    }
+
+    pub fn next_part(&self) -> Result<String, UrError> {
+        Ok(self.inner.lock().unwrap().next_part()?)
+    }
+
+    pub fn reset(&self) -> Result<(), UrError> {
+        Ok(self.inner.lock().unwrap().reset()?)
+    }
+}
+
+#[derive(uniffi::Object)]
+pub struct RnUrDecoder {
+    inner: Mutex<UrDecoder>,
+}
+
+#[uniffi::export]
+impl RnUrDecoder {
+    #[uniffi::constructor]
+    pub fn new() -> Self {
+        RnUrDecoder {
+            inner: Mutex::new(UrDecoder::new()),
+        }
+    }
+
+    /// Feeds one scanned part and returns the updated progress.
+    pub fn receive(&self, part: String) -> Result<DecodeProgress, UrError> {
+        let mut decoder = self.inner.lock().unwrap();
+        decoder.receive(&part)?;
+        Ok(progress(&decoder))
+    }
+
+    pub fn progress(&self) -> DecodeProgress {
+        progress(&self.inner.lock().unwrap())
+    }
+
+    pub fn message(&self) -> Result<Vec<u8>, UrError> {
+        Ok(self.inner.lock().unwrap().message()?)
+    }
+}
+
+fn progress(decoder: &UrDecoder) -> DecodeProgress {
+    DecodeProgress {
+        received: decoder.received_parts() as u32,
+        expected: decoder.expected_parts().unwrap_or(0) as u32,
+        complete: decoder.is_complete(),
+    }
+}
@@ -0,0 +1,186 @@
+//! Post-quantum public keys exchanged for account import, and their SS58
+//! addresses.
+//!
+//! A `quantus-pubkey` message is a CBOR byte string holding the encoded
+//! public key. A `quantus-account` message is `[public_key, label]`, with the
+//! label a text string naming the account on the signer.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use blake2::{Blake2b512, Digest};
+use minicbor::{Decoder, Encoder};
+
+use crate::cbor::cbor_error;
+use crate::transfer::{blake2_256, Hash};
+use crate::{decode_message, encode_message, QuantusUrError};
+
+pub const PUBKEY_UR_TYPE: &str = "quantus-pubkey";
+pub const ACCOUNT_UR_TYPE: &str = "quantus-account";
+
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// A public key scanned from a `quantus-pubkey` or `quantus-account` message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AccountKey {
+    pub public_key: Vec<u8>,
+    /// Account name, only carried by `quantus-account` messages.
+    pub label: Option<String>,
+}
+
+impl AccountKey {
+    pub fn from_parts(ur_parts: &[String]) -> Result<Self, QuantusUrError> {
+        let (ur_type, cbor) = decode_message(ur_parts)?;
+        let mut d = Decoder::new(&cbor);
+        let key = match ur_type.as_str() {
+            PUBKEY_UR_TYPE => AccountKey {
+                public_key: d.bytes().map_err(cbor_error)?.to_vec(),
+                label: None,
+            },
+            ACCOUNT_UR_TYPE => {
+                if d.array().map_err(cbor_error)? != Some(2) {
+                    return Err(QuantusUrError::CborError(
+                        "Malformed account message".to_string(),
+                    ));
+                }
+                AccountKey {
+                    public_key: d.bytes().map_err(cbor_error)?.to_vec(),
+                    label: Some(d.str().map_err(cbor_error)?.to_string()),
+                }
+            }
+            other => {
+                return Err(QuantusUrError::UrError(format!(
+                    "Expected a {} or {} message, got {}",
+                    PUBKEY_UR_TYPE, ACCOUNT_UR_TYPE, other
+                )))
+            }
+        };
+        if key.public_key.is_empty() {
+            return Err(QuantusUrError::CborError("Empty public key".to_string()));
+        }
+        Ok(key)
+    }
+
+    /// Encodes the key as a `quantus-account` message when it has a label and
+    /// as a `quantus-pubkey` message otherwise.
+    pub fn to_parts(&self) -> Result<Vec<String>, QuantusUrError> {
+        let mut e = Encoder::new(Vec::new());
+        match &self.label {
+            Some(label) => {
+                e.array(2)
+                    .and_then(|e| e.bytes(&self.public_key))
+                    .and_then(|e| e.str(label))
+                    .map_err(cbor_error)?;
+                encode_message(e.into_writer(), ACCOUNT_UR_TYPE)
+            }
+            None => {
+                e.bytes(&self.public_key).map_err(cbor_error)?;
+                encode_message(e.into_writer(), PUBKEY_UR_TYPE)
+            }
+        }
+    }
+
+    /// 32-byte account id: the key itself if it is 32 bytes long, otherwise
+    /// its BLAKE2b-256 hash, as Substrate does for larger keys.
+    pub fn account_id(&self) -> Hash {
+        match self.public_key.as_slice().try_into() {
+            Ok(id) => id,
+            Err(_) => blake2_256(&self.public_key),
+        }
+    }
+
+    /// SS58 address of the account under the network's registered `prefix`.
+    pub fn ss58_address(&self, prefix: u16) -> Result<String, QuantusUrError> {
+        ss58_encode(&self.account_id(), prefix)
+    }
+}
+
+/// Encodes a 32-byte account id as an SS58 address. Prefixes above 16383
+/// are not representable and rejected.
+pub fn ss58_encode(account_id: &Hash, prefix: u16) -> Result<String, QuantusUrError> {
+    let mut data = Vec::with_capacity(2 + 32 + 2);
+    match prefix {
+        0..=63 => data.push(prefix as u8),
+        64..=16383 => {
+            data.push((((prefix & 0b1111_1100) >> 2) as u8) | 0b0100_0000);
+            data.push(((prefix >> 8) as u8) | (((prefix & 0b11) as u8) << 6));
+        }
+        _ => {
+            return Err(QuantusUrError::InvalidConfiguration(format!(
+                "SS58 prefix {} out of range",
+                prefix
+            )))
+        }
+    }
+    data.extend_from_slice(account_id);
+    let checksum = Blake2b512::new()
+        .chain_update(b"SS58PRE")
+        .chain_update(&data)
+        .finalize();
+    data.extend_from_slice(&checksum[..2]);
+    Ok(base58(&data))
+}
+
+fn base58(data: &[u8]) -> String {
+    // Little-endian base-58 digits of the big-endian input
+    let mut digits: Vec<u8> = Vec::with_capacity(data.len() * 138 / 100 + 1);
+    for &byte in data {
+        let mut carry = byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let zeros = data.iter().take_while(|&&b| b == 0).count();
+    core::iter::repeat('1')
+        .take(zeros)
+        .chain(
+            digits
+                .iter()
+                .rev()
+                .map(|&d| BASE58_ALPHABET[d as usize] as char),
+        )
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_ss58_known_address() {
+        // Well-known development account "Alice" on the generic Substrate prefix
+        let alice: Hash = [
+            0xd4, 0x35, 0x93, 0xc7, 0x15, 0xfd, 0xd3, 0x1c, 0x61, 0x14, 0x1a, 0xbd, 0x04, 0xa9,
+            0x9f, 0xd6, 0x82, 0x2c, 0x85, 0x58, 0x85, 0x4c, 0xcd, 0xe3, 0x9a, 0x56, 0x84, 0xe7,
+            0xa5, 0x6d, 0xa2, 0x7d,
+        ];
+        assert_eq!(
+            ss58_encode(&alice, 42).expect("Encoding failed"),
+            "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"
+        );
+        assert!(ss58_encode(&alice, 16384).is_err());
+    }
+
+    #[test]
+    fn test_account_message_roundtrip() {
+        for label in [None, Some("Savings".to_string())] {
+            let key = AccountKey {
+                public_key: vec![0x42; 2592],
+                label,
+            };
+            let parsed = AccountKey::from_parts(&key.to_parts().unwrap()).expect("Parsing failed");
+            assert_eq!(parsed, key);
+            assert_eq!(parsed.account_id(), blake2_256(&key.public_key));
+        }
+        let parts = crate::encode_bytes(b"not a key").unwrap();
+        assert!(AccountKey::from_parts(&parts).is_err());
+    }
+}
@@ -0,0 +1,319 @@
+//! AirGap Vault compatibility for substrate chains, so Quantus payloads can be
+//! signed with an AirGap Vault instead of a Quantus signer.
+//!
+//! AirGap exchanges IACP messages inside a `ur:bytes` UR: the CBOR byte string
+//! holds `[version, [message, ...]]`, where each message is
+//! `[type, protocol, id, payload]` and the payload is a CBOR map with text
+//! keys. Sign requests carry `transaction`, `publicKey` and optionally
+//! `callbackURL`; sign responses carry `transaction` (the signed payload) and
+//! `signature`. Messages of other types in a bundle are skipped.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use minicbor::{Decoder, Encoder};
+
+use crate::cbor::{cbor_error, expect_ur_type};
+use crate::response::SignResponse;
+use crate::{decode_message, encode_message, QuantusUrError};
+
+pub const AIRGAP_UR_TYPE: &str = "bytes";
+
+/// Serializer version written into, and required of, every bundle.
+pub const IACP_VERSION: u8 = 3;
+
+const SIGN_REQUEST: u8 = 5;
+const SIGN_RESPONSE: u8 = 6;
+
+fn airgap_error(msg: &str) -> QuantusUrError {
+    QuantusUrError::CborError(format!("AirGap: {}", msg))
+}
+
+/// Encodes one message as a single-message bundle in `ur:bytes` parts.
+fn encode_bundle(
+    message_type: u8,
+    protocol: &str,
+    id: u32,
+    fields: &[(&str, Option<&[u8]>)],
+    text_fields: &[(&str, Option<&str>)],
+) -> Result<Vec<String>, QuantusUrError> {
+    let present = fields.iter().filter(|(_, v)| v.is_some()).count()
+        + text_fields.iter().filter(|(_, v)| v.is_some()).count();
+    let mut e = Encoder::new(Vec::new());
+    e.array(2)
+        .and_then(|e| e.u8(IACP_VERSION))
+        .and_then(|e| e.array(1))
+        .and_then(|e| e.array(4))
+        .and_then(|e| e.u8(message_type))
+        .and_then(|e| e.str(protocol))
+        .and_then(|e| e.u32(id))
+        .and_then(|e| e.map(present as u64))
+        .map_err(cbor_error)?;
+    for (key, value) in fields {
+        if let Some(value) = value {
+            e.str(key)
+                .and_then(|e| e.bytes(value))
+                .map_err(cbor_error)?;
+        }
+    }
+    for (key, value) in text_fields {
+        if let Some(value) = value {
+            e.str(key).and_then(|e| e.str(value)).map_err(cbor_error)?;
+        }
+    }
+    let bundle = e.into_writer();
+
+    let mut outer = Encoder::new(Vec::new());
+    outer.bytes(&bundle).map_err(cbor_error)?;
+    encode_message(outer.into_writer(), AIRGAP_UR_TYPE)
+}
+
+/// A decoded message: protocol, id and payload fields.
+struct Message {
+    protocol: String,
+    id: u32,
+    bytes: Vec<(String, Vec<u8>)>,
+    text: Vec<(String, String)>,
+}
+
+impl Message {
+    fn bytes(&self, key: &str) -> Option<Vec<u8>> {
+        self.bytes
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.clone())
+    }
+
+    fn text(&self, key: &str) -> Option<String> {
+        self.text
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.clone())
+    }
+
+    fn require(&self, key: &str) -> Result<Vec<u8>, QuantusUrError> {
+        self.bytes(key)
+            .ok_or_else(|| airgap_error(&format!("missing {}", key)))
+    }
+}
+
+/// Reassembles a `ur:bytes` bundle and returns its first message of `wanted`
+/// type.
+fn decode_bundle(ur_parts: &[String], wanted: u8) -> Result<Message, QuantusUrError> {
+    let (ur_type, cbor) = decode_message(ur_parts)?;
+    expect_ur_type(&ur_type, AIRGAP_UR_TYPE)?;
+    let bundle = Decoder::new(&cbor).bytes().map_err(cbor_error)?;
+    let mut d = Decoder::new(bundle);
+    if d.array().map_err(cbor_error)? != Some(2) {
+        return Err(airgap_error("malformed bundle"));
+    }
+    let version = d.u8().map_err(cbor_error)?;
+    if version != IACP_VERSION {
+        return Err(airgap_error(&format!("unsupported version {}", version)));
+    }
+    let count = d
+        .array()
+        .map_err(cbor_error)?
+        .ok_or_else(|| airgap_error("indefinite-length bundle"))?;
+    for _ in 0..count {
+        if d.array().map_err(cbor_error)? != Some(4) {
+            return Err(airgap_error("malformed message"));
+        }
+        let message_type = d.u8().map_err(cbor_error)?;
+        if message_type != wanted {
+            // Protocol, id and payload of a message we do not handle
+            for _ in 0..3 {
+                d.skip().map_err(cbor_error)?;
+            }
+            continue;
+        }
+        let protocol = d.str().map_err(cbor_error)?.to_string();
+        let id = d.u32().map_err(cbor_error)?;
+        let fields = d
+            .map()
+            .map_err(cbor_error)?
+            .ok_or_else(|| airgap_error("indefinite-length payload"))?;
+        let mut message = Message {
+            protocol,
+            id,
+            bytes: Vec::new(),
+            text: Vec::new(),
+        };
+        for _ in 0..fields {
+            let key = d.str().map_err(cbor_error)?.to_string();
+            match d.datatype().map_err(cbor_error)? {
+                minicbor::data::Type::Bytes => message
+                    .bytes
+                    .push((key, d.bytes().map_err(cbor_error)?.to_vec())),
+                minicbor::data::Type::String => message
+                    .text
+                    .push((key, d.str().map_err(cbor_error)?.to_string())),
+                _ => d.skip().map_err(cbor_error)?,
+            }
+        }
+        return Ok(message);
+    }
+    Err(airgap_error("no message of the expected type"))
+}
+
+/// Substrate transaction sign request as sent to AirGap Vault.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AirGapSignRequest {
+    /// AirGap protocol identifier of the chain, e.g. `"polkadot"`.
+    pub protocol: String,
+    /// Correlates the response; AirGap echoes it back.
+    pub id: u32,
+    pub transaction: Vec<u8>,
+    pub public_key: Vec<u8>,
+    pub callback_url: Option<String>,
+}
+
+impl AirGapSignRequest {
+    pub fn to_parts(&self) -> Result<Vec<String>, QuantusUrError> {
+        encode_bundle(
+            SIGN_REQUEST,
+            &self.protocol,
+            self.id,
+            &[
+                ("transaction", Some(self.transaction.as_slice())),
+                ("publicKey", Some(self.public_key.as_slice())),
+            ],
+            &[("callbackURL", self.callback_url.as_deref())],
+        )
+    }
+
+    pub fn from_parts(ur_parts: &[String]) -> Result<Self, QuantusUrError> {
+        let message = decode_bundle(ur_parts, SIGN_REQUEST)?;
+        Ok(AirGapSignRequest {
+            transaction: message.require("transaction")?,
+            public_key: message.require("publicKey")?,
+            callback_url: message.text("callbackURL"),
+            protocol: message.protocol,
+            id: message.id,
+        })
+    }
+}
+
+/// Signed transaction returned by AirGap Vault.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AirGapSignResponse {
+    pub protocol: String,
+    pub id: u32,
+    pub transaction: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+impl AirGapSignResponse {
+    pub fn to_parts(&self) -> Result<Vec<String>, QuantusUrError> {
+        encode_bundle(
+            SIGN_RESPONSE,
+            &self.protocol,
+            self.id,
+            &[
+                ("transaction", Some(self.transaction.as_slice())),
+                ("signature", Some(self.signature.as_slice())),
+            ],
+            &[],
+        )
+    }
+
+    pub fn from_parts(ur_parts: &[String]) -> Result<Self, QuantusUrError> {
+        let message = decode_bundle(ur_parts, SIGN_RESPONSE)?;
+        Ok(AirGapSignResponse {
+            transaction: message.require("transaction")?,
+            signature: message.require("signature")?,
+            protocol: message.protocol,
+            id: message.id,
+        })
+    }
+
+    /// Whether this response answers `request`: AirGap echoes the request
+    /// id and protocol and returns the transaction it signed, which must be
+    /// the one that was sent.
+    pub fn answers(&self, request: &AirGapSignRequest) -> bool {
+        self.id == request.id
+            && self.protocol == request.protocol
+            && self.transaction == request.transaction
+    }
+
+    /// Converts AirGap's answer into a [`SignResponse`] carrying the
+    /// request's public key, so sessions built for Quantus signers accept
+    /// AirGap Vault too. Fails with [`QuantusUrError::ResponseMismatch`]
+    /// unless the response [answers](Self::answers) `request`.
+    pub fn into_response(
+        self,
+        request: &AirGapSignRequest,
+    ) -> Result<SignResponse, QuantusUrError> {
+        if !self.answers(request) {
+            return Err(QuantusUrError::ResponseMismatch);
+        }
+        Ok(
+            SignResponse::for_request(&request.transaction, self.signature)
+                .with_public_key(request.public_key.clone()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> AirGapSignRequest {
+        AirGapSignRequest {
+            protocol: "polkadot".to_string(),
+            id: 42,
+            transaction: (0..700u32).map(|i| (i % 251) as u8).collect(),
+            public_key: alloc::vec![0xd4; 32],
+            callback_url: Some("airgap-wallet://?d=".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_request_roundtrip() {
+        let request = request();
+        let parts = request.to_parts().expect("Encoding failed");
+        assert!(parts.len() > 1);
+        assert!(parts[0].starts_with("UR:BYTES/"));
+        assert_eq!(
+            AirGapSignRequest::from_parts(&parts).expect("Decoding failed"),
+            request
+        );
+        assert!(AirGapSignResponse::from_parts(&parts).is_err());
+    }
+
+    #[test]
+    fn test_response_into_sign_response() {
+        let request = request();
+        let response = AirGapSignResponse {
+            protocol: request.protocol.clone(),
+            id: request.id,
+            transaction: request.transaction.clone(),
+            signature: alloc::vec![0x11; 64],
+        };
+        let parts = response.to_parts().expect("Encoding failed");
+        let decoded = AirGapSignResponse::from_parts(&parts).expect("Decoding failed");
+        assert!(decoded.answers(&request));
+        let response = decoded.into_response(&request).expect("Response mismatch");
+        assert!(response.matches_request(&request.transaction));
+    }
+
+    #[test]
+    fn test_response_for_other_transaction() {
+        let request = request();
+        let mut transaction = request.transaction.clone();
+        transaction[0] ^= 1;
+        let response = AirGapSignResponse {
+            protocol: request.protocol.clone(),
+            id: request.id,
+            transaction,
+            signature: alloc::vec![0x11; 64],
+        };
+        assert!(!response.answers(&request));
+        assert!(matches!(
+            response.into_response(&request),
+            Err(QuantusUrError::ResponseMismatch)
+        ));
+    }
+}
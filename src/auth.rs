@@ -0,0 +1,199 @@
+//! Per-part authentication for the visual channel.
+//!
+//! Anyone who can hold a screen in front of the camera can insert frames into
+//! a scan session, and a forged fountain part silently corrupts the message.
+//! When both sides share a session key (e.g. from pairing), each part can
+//! carry a truncated keyed BLAKE2b MAC as an extra path segment,
+//! `UR:TYPE/SEQ/BODY/<MAC>`, and [`AuthenticatedDecoder`] drops every part
+//! whose MAC does not verify before it reaches the fountain decoder.
+//!
+//! The MAC covers the lowercased part, so it survives scanners changing case.
+//! It does not prevent replaying genuine parts of an earlier message sent
+//! under the same key; derive a fresh key per session for that.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use blake2::digest::consts::{U32, U8};
+use blake2::digest::Mac;
+use blake2::Blake2bMac;
+
+use crate::part::sanitize_part;
+use crate::{constant_time_eq, DecodeEvent, QuantusUrError, UrDecoder};
+
+/// Length of the truncated MAC in bytes.
+pub const PART_MAC_LENGTH: usize = 8;
+
+const MAC_KEY_CONTEXT: &[u8] = b"quantus-ur part mac";
+
+fn key_error() -> QuantusUrError {
+    QuantusUrError::InvalidConfiguration("session key must be 1 to 64 bytes".to_string())
+}
+
+/// Key tagging and verifying parts, derived from a shared session key.
+#[derive(Clone)]
+pub struct PartKey(Blake2bMac<U8>);
+
+impl PartKey {
+    /// Derives the MAC key from a session key of 1 to 64 bytes.
+    pub fn from_session_key(session_key: &[u8]) -> Result<Self, QuantusUrError> {
+        if session_key.is_empty() {
+            return Err(key_error());
+        }
+        let derived = <Blake2bMac<U32> as Mac>::new_from_slice(session_key)
+            .map_err(|_| key_error())?
+            .chain_update(MAC_KEY_CONTEXT)
+            .finalize()
+            .into_bytes();
+        <Blake2bMac<U8> as Mac>::new_from_slice(&derived)
+            .map(PartKey)
+            .map_err(|_| key_error())
+    }
+
+    fn mac(&self, part: &str) -> [u8; PART_MAC_LENGTH] {
+        self.0
+            .clone()
+            .chain_update(part.to_ascii_lowercase())
+            .finalize()
+            .into_bytes()
+            .into()
+    }
+
+    /// Appends the MAC segment to `part`, in the part's case.
+    pub fn tag_part(&self, part: &str) -> String {
+        let part = sanitize_part(part);
+        let mut tag = String::with_capacity(PART_MAC_LENGTH * 2);
+        for byte in self.mac(part) {
+            tag.push_str(&format!("{:02x}", byte));
+        }
+        if part.bytes().any(|b| b.is_ascii_uppercase()) {
+            tag.make_ascii_uppercase();
+        }
+        format!("{}/{}", part, tag)
+    }
+
+    /// Checks the MAC segment and returns the part without it.
+    pub fn verify_part<'a>(&self, part: &'a str) -> Result<&'a str, QuantusUrError> {
+        let part = sanitize_part(part);
+        let (untagged, tag) = part
+            .rsplit_once('/')
+            .ok_or(QuantusUrError::PartAuthenticationFailed)?;
+        if tag.len() != PART_MAC_LENGTH * 2 || !tag.is_ascii() {
+            return Err(QuantusUrError::PartAuthenticationFailed);
+        }
+        let mut received = [0u8; PART_MAC_LENGTH];
+        for (byte, digits) in received.iter_mut().zip(tag.as_bytes().chunks(2)) {
+            let digits = core::str::from_utf8(digits)
+                .map_err(|_| QuantusUrError::PartAuthenticationFailed)?;
+            *byte = u8::from_str_radix(digits, 16)
+                .map_err(|_| QuantusUrError::PartAuthenticationFailed)?;
+        }
+        if !constant_time_eq(&received, &self.mac(untagged)) {
+            return Err(QuantusUrError::PartAuthenticationFailed);
+        }
+        Ok(untagged)
+    }
+}
+
+impl core::fmt::Debug for PartKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("PartKey(..)")
+    }
+}
+
+/// [`UrDecoder`] that only accepts parts carrying a valid MAC.
+pub struct AuthenticatedDecoder {
+    key: PartKey,
+    decoder: UrDecoder,
+    rejected: usize,
+}
+
+impl AuthenticatedDecoder {
+    pub fn new(key: PartKey) -> Self {
+        Self::with_decoder(key, UrDecoder::new())
+    }
+
+    pub fn with_decoder(key: PartKey, decoder: UrDecoder) -> Self {
+        AuthenticatedDecoder {
+            key,
+            decoder,
+            rejected: 0,
+        }
+    }
+
+    /// The session, for progress reporting and reading the message.
+    pub fn decoder(&self) -> &UrDecoder {
+        &self.decoder
+    }
+
+    /// Parts dropped because their MAC did not verify.
+    pub fn rejected_parts(&self) -> usize {
+        self.rejected
+    }
+
+    /// Verifies the part and feeds it to the session. Parts failing
+    /// verification return [`QuantusUrError::PartAuthenticationFailed`] and
+    /// leave the session untouched.
+    pub fn receive_event(&mut self, part: &str) -> Result<DecodeEvent, QuantusUrError> {
+        match self.key.verify_part(part) {
+            Ok(untagged) => self.decoder.receive_event(untagged),
+            Err(e) => {
+                self.rejected += 1;
+                trace_event!(rejected = self.rejected, "unauthenticated part rejected");
+                Err(e)
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.decoder.reset();
+        self.rejected = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encode_bytes;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_forged_parts_rejected() {
+        let key = PartKey::from_session_key(b"paired session key").expect("Invalid key");
+        let payload: Vec<u8> = (0..1000u32).map(|i| (i % 251) as u8).collect();
+        let parts = encode_bytes(&payload).expect("Encoding failed");
+        let forged = encode_bytes(&[0xee; 1000]).expect("Encoding failed");
+
+        let mut decoder = AuthenticatedDecoder::new(key.clone());
+        for (part, forged) in parts.iter().zip(&forged) {
+            let attacker = PartKey::from_session_key(b"guessed key").expect("Invalid key");
+            assert!(decoder.receive_event(&attacker.tag_part(forged)).is_err());
+            assert!(decoder.receive_event(forged).is_err());
+            decoder
+                .receive_event(&key.tag_part(part))
+                .expect("Receive failed");
+        }
+        assert_eq!(decoder.rejected_parts(), 2 * parts.len());
+        assert_eq!(
+            decoder.decoder().message().expect("Decoding failed"),
+            payload
+        );
+    }
+
+    #[test]
+    fn test_tag_survives_case_changes() {
+        let key = PartKey::from_session_key(&[7; 32]).expect("Invalid key");
+        let parts = encode_bytes(b"Hello, Quantus!").expect("Encoding failed");
+        let tagged = key.tag_part(&parts[0]);
+        assert!(tagged.starts_with(&parts[0]));
+        assert_eq!(
+            key.verify_part(&tagged.to_lowercase())
+                .expect("Verification failed"),
+            parts[0].to_lowercase()
+        );
+        assert!(PartKey::from_session_key(&[]).is_err());
+        assert_eq!(
+            key.verify_part(&parts[0]).unwrap_err().kind(),
+            "part_authentication_failed"
+        );
+    }
+}
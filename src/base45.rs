@@ -0,0 +1,175 @@
+//! Base45 part bodies ([RFC 9285](https://www.rfc-editor.org/rfc/rfc9285)),
+//! selected with [`crate::EncodingMode::Base45`].
+//!
+//! Base45 packs two bytes into three characters of the QR alphanumeric
+//! alphabet, against four for minimal bytewords, so the same fragment fits a
+//! smaller code. Parts read `UR45:<TYPE>/<SEQ>-<COUNT>/<BODY>`: the scheme
+//! keeps them apart from real URs, and since the alphabet contains `/` the
+//! sequence is always present, `1-1` marking a single-part message. Bodies
+//! carry the same CBOR as the matching UR part followed by its big-endian
+//! CRC32, so fragments and sessions behave exactly as for UR parts.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use ur::bytewords::{self, Style};
+
+use crate::checksum::crc32;
+use crate::QuantusUrError;
+
+/// Scheme of base45 parts, in the uppercase form they are emitted in.
+pub const BASE45_SCHEME: &str = "UR45:";
+
+const ALPHABET: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+fn base45_error(msg: &str) -> QuantusUrError {
+    QuantusUrError::UrError(format!("Base45: {}", msg))
+}
+
+/// Encodes `data` as base45 text.
+pub fn encode(data: &[u8]) -> String {
+    let mut text = String::with_capacity((data.len() + 1) / 2 * 3);
+    for pair in data.chunks(2) {
+        let (mut n, digits) = match pair {
+            [a, b] => (u32::from(*a) << 8 | u32::from(*b), 3),
+            _ => (u32::from(pair[0]), 2),
+        };
+        for _ in 0..digits {
+            text.push(char::from(ALPHABET[(n % 45) as usize]));
+            n /= 45;
+        }
+    }
+    text
+}
+
+/// Decodes base45 text. Lowercase letters are accepted, since the alphabet
+/// has none and scanners or normalization may lowercase the text.
+pub fn decode(text: &str) -> Result<Vec<u8>, QuantusUrError> {
+    let values = text
+        .bytes()
+        .map(|c| {
+            ALPHABET
+                .iter()
+                .position(|&a| a == c.to_ascii_uppercase())
+                .map(|v| v as u32)
+                .ok_or_else(|| base45_error("invalid character"))
+        })
+        .collect::<Result<Vec<u32>, _>>()?;
+    if values.len() % 3 == 1 {
+        return Err(base45_error("invalid length"));
+    }
+    let mut data = Vec::with_capacity(values.len() / 3 * 2 + 1);
+    for group in values.chunks(3) {
+        match *group {
+            [c, d, e] => {
+                let n = c + d * 45 + e * 45 * 45;
+                let n = u16::try_from(n).map_err(|_| base45_error("value out of range"))?;
+                data.extend_from_slice(&n.to_be_bytes());
+            }
+            [c, d] => {
+                let n = u8::try_from(c + d * 45).map_err(|_| base45_error("value out of range"))?;
+                data.push(n);
+            }
+            _ => return Err(base45_error("invalid length")),
+        }
+    }
+    Ok(data)
+}
+
+/// Whether `part` uses the base45 scheme rather than `ur:`.
+pub(crate) fn is_base45_part(part: &str) -> bool {
+    part.get(..BASE45_SCHEME.len())
+        .map_or(false, |scheme| scheme.eq_ignore_ascii_case(BASE45_SCHEME))
+}
+
+/// Base45 part for a UR part body; `sequence` is `None` for single-part
+/// messages.
+pub(crate) fn encode_part(ur_type: &str, sequence: Option<(u32, u32)>, body: &[u8]) -> String {
+    let (index, count) = sequence.unwrap_or((1, 1));
+    let mut data = body.to_vec();
+    data.extend_from_slice(&crc32(body).to_be_bytes());
+    format!(
+        "{}{}/{}-{}/{}",
+        BASE45_SCHEME,
+        ur_type.to_ascii_uppercase(),
+        index,
+        count,
+        encode(&data)
+    )
+}
+
+/// Converts a base45 part into the equivalent lowercase UR part text.
+pub(crate) fn to_ur_text(part: &str) -> Result<String, QuantusUrError> {
+    let rest = part
+        .get(BASE45_SCHEME.len()..)
+        .filter(|_| is_base45_part(part))
+        .ok_or_else(|| base45_error("invalid scheme"))?;
+    let mut segments = rest.splitn(3, '/');
+    let (ur_type, sequence, body) = match (segments.next(), segments.next(), segments.next()) {
+        (Some(ur_type), Some(sequence), Some(body)) => (ur_type, sequence, body),
+        _ => return Err(base45_error("invalid path")),
+    };
+    let (index, count) = sequence
+        .split_once('-')
+        .and_then(|(index, count)| Some((index.parse::<u32>().ok()?, count.parse::<u32>().ok()?)))
+        .ok_or_else(|| base45_error("invalid sequence"))?;
+
+    let data = decode(body)?;
+    if data.len() < 4 {
+        return Err(base45_error("body too short"));
+    }
+    let (body, trailer) = data.split_at(data.len() - 4);
+    let expected = u32::from_be_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    let actual = crc32(body);
+    if expected != actual {
+        return Err(QuantusUrError::PartChecksumMismatch { expected, actual });
+    }
+
+    let ur_type = ur_type.to_ascii_lowercase();
+    let words = bytewords::encode(body, Style::Minimal);
+    Ok(if (index, count) == (1, 1) {
+        format!("ur:{}/{}", ur_type, words)
+    } else {
+        format!("ur:{}/{}-{}/{}", ur_type, index, count, words)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        decode_bytes, encode_bytes, encode_bytes_with_options, EncodeOptions, EncodingMode,
+        UrDecoder,
+    };
+
+    #[test]
+    fn test_rfc_vectors() {
+        assert_eq!(encode(b"AB"), "BB8");
+        assert_eq!(encode(b"Hello!!"), "%69 VD92EX0");
+        assert_eq!(encode(b"ietf!"), "QED8WEX0");
+        assert_eq!(decode("QED8WEX0").expect("Decoding failed"), b"ietf!");
+        assert!(decode("GGW").is_err());
+        assert!(decode("ZZZ").is_err());
+    }
+
+    #[test]
+    fn test_base45_parts_roundtrip() {
+        let payload: Vec<u8> = (0..1200u32).map(|i| (i % 251) as u8).collect();
+        let options = EncodeOptions {
+            mode: EncodingMode::Base45,
+            ..EncodeOptions::default()
+        };
+        let parts = encode_bytes_with_options(&payload, &options).expect("Encoding failed");
+        let ur_parts = encode_bytes(&payload).expect("Encoding failed");
+        assert_eq!(parts.len(), ur_parts.len());
+        assert!(parts[0].starts_with("UR45:QUANTUS-SIGN-REQUEST/1-"));
+        assert!(parts[0].len() < ur_parts[0].len());
+        assert_eq!(decode_bytes(&parts).expect("Decoding failed"), payload);
+
+        let single = encode_bytes_with_options(b"short", &options).expect("Encoding failed");
+        assert!(single[0].starts_with("UR45:QUANTUS-SIGN-REQUEST/1-1/"));
+        let mut decoder = UrDecoder::new();
+        decoder.receive(&single[0]).expect("Receive failed");
+        assert_eq!(decoder.message().expect("Decoding failed"), b"short");
+    }
+}
@@ -0,0 +1,694 @@
+//! Command-line tool for encoding and decoding Quantus UR parts.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use blake2::digest::consts::U32;
+use blake2::{Blake2b, Digest};
+use clap::{Args, Parser, Subcommand};
+use quantus_ur::{
+    classify_payload, config, constant_time_eq, encode_hex, hexdump::HexDump, io::encode_reader,
+    parts_from_text, EncodeOptions, QuantusUrError, UrDecoder, UrEncoder,
+};
+use serde::Serialize;
+
+#[derive(Parser)]
+#[command(name = "quantus-ur", version, about)]
+struct Cli {
+    /// Print structured JSON instead of text.
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Encode a hex payload into UR parts, one per line.
+    Encode {
+        #[arg(required_unless_present = "input")]
+        payload: Option<String>,
+        /// Read the binary payload from a file instead.
+        #[arg(long, conflicts_with = "payload")]
+        input: Option<PathBuf>,
+    },
+    /// Decode UR parts and print the payload as hex.
+    Decode {
+        #[command(flatten)]
+        input: PartsInput,
+        /// Feed text and QR image files dropped into a directory until the
+        /// message is complete.
+        #[arg(long, conflicts_with_all = ["parts", "file"])]
+        watch: Option<PathBuf>,
+        /// Print the payload as a hex dump with offsets and ASCII.
+        #[arg(long)]
+        dump: bool,
+    },
+    /// Show the UR type, scan progress and payload kind of a part set.
+    Inspect(PartsInput),
+    /// Decode UR parts and check the payload's BLAKE2b-256 hash.
+    Verify {
+        #[command(flatten)]
+        input: PartsInput,
+        /// Expected hash as hex, with or without a `0x` prefix.
+        #[arg(long)]
+        expect_hash: String,
+    },
+    /// Write regression test vectors for bindings in other languages.
+    GenVectors {
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        #[arg(long)]
+        out: PathBuf,
+    },
+}
+
+/// Parts given as arguments, in a file (one per line) or on stdin.
+#[derive(Args)]
+struct PartsInput {
+    parts: Vec<String>,
+    /// Read parts from a file, one per line; `-` reads stdin.
+    #[arg(long, conflicts_with = "parts")]
+    file: Option<PathBuf>,
+}
+
+impl PartsInput {
+    /// Falls back to stdin when neither parts nor a file are given.
+    fn read(&self) -> Result<Vec<String>, CliError> {
+        let text = match &self.file {
+            Some(path) if path.as_os_str() != "-" => std::fs::read_to_string(path)?,
+            None if !self.parts.is_empty() => return Ok(self.parts.clone()),
+            _ => {
+                let mut text = String::new();
+                io::stdin().read_to_string(&mut text)?;
+                text
+            }
+        };
+        Ok(parts_from_text(&text))
+    }
+}
+
+enum CliError {
+    Ur(QuantusUrError),
+    Io(io::Error),
+}
+
+impl CliError {
+    fn kind(&self) -> &'static str {
+        match self {
+            CliError::Ur(e) => e.kind(),
+            CliError::Io(_) => "io",
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Ur(e) => write!(f, "{}", e),
+            CliError::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+impl From<QuantusUrError> for CliError {
+    fn from(e: QuantusUrError) -> Self {
+        CliError::Ur(e)
+    }
+}
+
+impl From<io::Error> for CliError {
+    fn from(e: io::Error) -> Self {
+        CliError::Io(e)
+    }
+}
+
+/// Result of a subcommand; fields that do not apply are left out of the JSON.
+#[derive(Serialize, Default)]
+struct Report {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ur_type: Option<String>,
+    part_count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parts: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expected_parts: Option<usize>,
+    complete: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    payload_hex: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    payload_kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vector_count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    payload_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    verified: Option<bool>,
+    elapsed_us: u128,
+}
+
+impl Report {
+    /// Whether the process exits successfully: false only for a failed
+    /// `verify`.
+    fn passed(&self) -> bool {
+        self.verified != Some(false)
+    }
+}
+
+/// `{"error": {"kind", "message"}}`, printed for errors in `--json` mode.
+fn error_json(e: &CliError) -> serde_json::Value {
+    let error = serde_json::json!({ "kind": e.kind(), "message": e.to_string() });
+    serde_json::json!({ "error": error })
+}
+
+const WATCH_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Extracts parts from a dropped file: QR codes from images, lines otherwise.
+fn scan_file(path: &Path) -> Result<Vec<String>, String> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("png" | "jpg" | "jpeg" | "bmp" | "gif") => {
+            let image = image::open(path).map_err(|e| e.to_string())?.to_luma8();
+            let (width, height) = image.dimensions();
+            let mut prepared = rqrr::PreparedImage::prepare_from_greyscale(
+                width as usize,
+                height as usize,
+                |x, y| image.get_pixel(x as u32, y as u32).0[0],
+            );
+            Ok(prepared
+                .detect_grids()
+                .iter()
+                .filter_map(|grid| grid.decode().ok())
+                .map(|(_, content)| content)
+                .collect())
+        }
+        _ => std::fs::read_to_string(path)
+            .map(|text| parts_from_text(&text))
+            .map_err(|e| e.to_string()),
+    }
+}
+
+/// Size and modification time of a dropped file.
+type FileStamp = (u64, Option<SystemTime>);
+
+fn file_stamp(path: &Path) -> Option<FileStamp> {
+    let metadata = std::fs::metadata(path).ok()?;
+    metadata
+        .is_file()
+        .then(|| (metadata.len(), metadata.modified().ok()))
+}
+
+/// Polls `dir` for new files and feeds their parts to a decoder until the
+/// message is complete. A file is read once its size and modification time
+/// are unchanged between two polls, so files still being written are not
+/// read half-way, and read again if it changes afterwards. Unreadable files
+/// and invalid parts are reported and skipped.
+fn watch(dir: &Path) -> Result<UrDecoder, CliError> {
+    let mut decoder = UrDecoder::new();
+    // Stamps of each file when it was last read and when it was last polled
+    let mut read = HashMap::new();
+    let mut polled = HashMap::new();
+    while !decoder.is_complete() {
+        let mut stable_files = Vec::new();
+        for entry in std::fs::read_dir(dir)?.filter_map(Result::ok) {
+            let path = entry.path();
+            let Some(stamp) = file_stamp(&path) else {
+                continue;
+            };
+            if read.get(&path) != Some(&stamp) && polled.insert(path.clone(), stamp) == Some(stamp)
+            {
+                stable_files.push((path, stamp));
+            }
+        }
+        if stable_files.is_empty() {
+            thread::sleep(WATCH_POLL_INTERVAL);
+            continue;
+        }
+        stable_files.sort();
+        for (path, stamp) in stable_files {
+            match scan_file(&path) {
+                Ok(parts) => {
+                    for part in parts {
+                        if let Err(e) = decoder.receive(&part) {
+                            eprintln!("{}: {}", path.display(), e);
+                        }
+                    }
+                }
+                Err(e) => eprintln!("{}: {}", path.display(), e),
+            }
+            read.insert(path, stamp);
+        }
+    }
+    Ok(decoder)
+}
+
+fn decode_report(parts: &[String], require_complete: bool) -> Result<Report, QuantusUrError> {
+    let mut decoder = UrDecoder::new();
+    for part in parts {
+        decoder.receive(part)?;
+    }
+    if require_complete && !decoder.is_complete() {
+        return Err(QuantusUrError::Incomplete);
+    }
+    report(&decoder)
+}
+
+fn report(decoder: &UrDecoder) -> Result<Report, QuantusUrError> {
+    let payload = if decoder.is_complete() {
+        Some(decoder.message()?)
+    } else {
+        None
+    };
+    Ok(Report {
+        ur_type: decoder.ur_type().map(str::to_string),
+        part_count: decoder.received_parts(),
+        expected_parts: decoder.expected_parts(),
+        complete: decoder.is_complete(),
+        payload_kind: payload.as_deref().map(|p| classify_payload(p).to_string()),
+        payload_hex: payload.map(hex::encode),
+        ..Report::default()
+    })
+}
+
+/// Payload sizes covered by the test vectors: single-part, around the
+/// default fragment length and well into multi-part territory.
+const VECTOR_PAYLOAD_LENGTHS: [usize; 9] = [1, 16, 100, 198, 199, 250, 500, 1000, 5000];
+/// Fountain parts past the minimal set included in every vector.
+const VECTOR_EXTRA_PARTS: usize = 5;
+
+#[derive(Serialize)]
+struct TestVector {
+    payload_hex: String,
+    blake2_256: String,
+    fragment_count: usize,
+    /// Minimal set followed by `VECTOR_EXTRA_PARTS` fountain parts.
+    parts: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct VectorFile {
+    generator: String,
+    seed: u64,
+    ur_type: &'static str,
+    vectors: Vec<TestVector>,
+}
+
+/// SplitMix64, so vectors are reproducible from the seed alone.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+fn gen_vectors(seed: u64) -> Result<VectorFile, QuantusUrError> {
+    let mut state = seed;
+    let mut vectors = Vec::with_capacity(VECTOR_PAYLOAD_LENGTHS.len());
+    for len in VECTOR_PAYLOAD_LENGTHS {
+        let payload: Vec<u8> = (0..len).map(|_| splitmix64(&mut state) as u8).collect();
+        let mut encoder = UrEncoder::new(&payload)?;
+        let fragment_count = encoder.fragment_count();
+        let extra = if encoder.is_single_part() {
+            0
+        } else {
+            VECTOR_EXTRA_PARTS
+        };
+        let parts = (0..fragment_count + extra)
+            .map(|_| encoder.next_part())
+            .collect::<Result<_, _>>()?;
+        vectors.push(TestVector {
+            payload_hex: hex::encode(&payload),
+            blake2_256: hex::encode(Blake2b::<U32>::digest(&payload)),
+            fragment_count,
+            parts,
+        });
+    }
+    Ok(VectorFile {
+        generator: format!("quantus-ur {}", env!("CARGO_PKG_VERSION")),
+        seed,
+        ur_type: config::sign_request_ur_type(),
+        vectors,
+    })
+}
+
+fn run(command: &Command) -> Result<Report, CliError> {
+    let start = Instant::now();
+    let mut report = match command {
+        Command::Encode { payload, input } => {
+            let parts = match (payload, input) {
+                (_, Some(path)) => {
+                    let file = std::fs::File::open(path)?;
+                    encode_reader(file, &EncodeOptions::default())?
+                        .into_minimal_parts()
+                        .collect::<Result<_, _>>()?
+                }
+                (Some(payload), None) => encode_hex(payload)?,
+                (None, None) => unreachable!("enforced by clap"),
+            };
+            Report {
+                ur_type: Some(config::sign_request_ur_type().to_string()),
+                part_count: parts.len(),
+                parts: Some(parts),
+                complete: true,
+                ..Report::default()
+            }
+        }
+        Command::Decode {
+            watch: Some(dir), ..
+        } => report(&watch(dir)?)?,
+        Command::Decode { input, .. } => decode_report(&input.read()?, true)?,
+        Command::Inspect(input) => decode_report(&input.read()?, false)?,
+        Command::Verify { input, expect_hash } => {
+            let mut report = decode_report(&input.read()?, true)?;
+            let payload = hex::decode(report.payload_hex.as_deref().unwrap_or_default())
+                .map_err(QuantusUrError::HexError)?;
+            let hash = Blake2b::<U32>::digest(&payload);
+            let expected = match expect_hash.get(..2) {
+                Some(prefix) if prefix.eq_ignore_ascii_case("0x") => &expect_hash[2..],
+                _ => expect_hash.as_str(),
+            };
+            let expected = hex::decode(expected).map_err(QuantusUrError::HexError)?;
+            report.verified = Some(constant_time_eq(&hash, &expected));
+            report.payload_hash = Some(hex::encode(hash));
+            report
+        }
+        Command::GenVectors { seed, out } => {
+            let file = gen_vectors(*seed)?;
+            let json = serde_json::to_string_pretty(&file).map_err(io::Error::from)?;
+            std::fs::write(out, json)?;
+            Report {
+                ur_type: Some(file.ur_type.to_string()),
+                part_count: file.vectors.iter().map(|v| v.parts.len()).sum(),
+                vector_count: Some(file.vectors.len()),
+                complete: true,
+                ..Report::default()
+            }
+        }
+    };
+    report.elapsed_us = start.elapsed().as_micros();
+    Ok(report)
+}
+
+fn print_text(command: &Command, report: &Report) {
+    match command {
+        Command::Verify { expect_hash, .. } => {
+            let hash = report.payload_hash.as_deref().unwrap_or_default();
+            if report.verified == Some(true) {
+                println!("PASS: payload hash {}", hash);
+            } else {
+                println!("FAIL: payload hash {}", hash);
+                println!("      expected     {}", expect_hash);
+            }
+        }
+        Command::GenVectors { out, .. } => println!(
+            "wrote {} vectors ({} parts) to {}",
+            report.vector_count.unwrap_or(0),
+            report.part_count,
+            out.display()
+        ),
+        Command::Encode { .. } => {
+            for part in report.parts.iter().flatten() {
+                println!("{}", part);
+            }
+        }
+        Command::Decode { dump, .. } => {
+            if let Some(kind) = &report.payload_kind {
+                eprintln!("payload: {}", kind);
+            }
+            let payload_hex = report.payload_hex.as_deref().unwrap_or_default();
+            match hex::decode(payload_hex) {
+                Ok(payload) if *dump => println!("{}", HexDump(&payload)),
+                _ => println!("{}", payload_hex),
+            }
+        }
+        Command::Inspect(_) => {
+            println!("type:     {}", report.ur_type.as_deref().unwrap_or("-"));
+            println!(
+                "parts:    {}/{}",
+                report.part_count,
+                report.expected_parts.unwrap_or(0)
+            );
+            println!("complete: {}", report.complete);
+            if let (Some(hex), Some(kind)) = (&report.payload_hex, &report.payload_kind) {
+                println!("payload:  {} bytes, {}", hex.len() / 2, kind);
+            }
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(&cli.command) {
+        Ok(report) => {
+            if cli.json {
+                println!("{}", serde_json::to_string(&report).unwrap());
+            } else {
+                print_text(&cli.command, &report);
+            }
+            if report.passed() {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+        Err(e) if cli.json => {
+            println!("{}", error_json(&e));
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn payload() -> Vec<u8> {
+        (0..500u32).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn parts() -> Vec<String> {
+        encode_hex(&hex::encode(payload())).expect("Encoding failed")
+    }
+
+    fn parse(args: &[&str]) -> Cli {
+        Cli::try_parse_from(["quantus-ur"].iter().chain(args)).expect("Parsing failed")
+    }
+
+    fn run_args(args: &[String]) -> Result<Report, CliError> {
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        run(&parse(&args).command)
+    }
+
+    /// The keys of the `--json` output for `report`.
+    fn json_keys(report: &Report) -> Vec<String> {
+        let Value::Object(json) = serde_json::to_value(report).expect("Serialization failed")
+        else {
+            panic!("Report is not a JSON object");
+        };
+        let mut keys: Vec<String> = json.keys().cloned().collect();
+        keys.sort();
+        keys
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("quantus-ur-cli-{}-{}", std::process::id(), name))
+    }
+
+    fn verify_args(expect_hash: &str) -> Vec<String> {
+        let mut args = vec![
+            "verify".to_string(),
+            "--expect-hash".to_string(),
+            expect_hash.to_string(),
+        ];
+        args.extend(parts());
+        args
+    }
+
+    #[test]
+    fn test_verify_pass_and_fail() {
+        let hash = hex::encode(Blake2b::<U32>::digest(payload()));
+        let report = run_args(&verify_args(&format!("0x{}", hash))).expect("Verify failed");
+        assert!(report.passed());
+        assert_eq!(report.payload_hash.as_deref(), Some(hash.as_str()));
+        assert_eq!(
+            json_keys(&report),
+            [
+                "complete",
+                "elapsed_us",
+                "expected_parts",
+                "part_count",
+                "payload_hash",
+                "payload_hex",
+                "payload_kind",
+                "ur_type",
+                "verified"
+            ]
+        );
+
+        let report = run_args(&verify_args(&"00".repeat(32))).expect("Verify failed");
+        assert!(!report.passed());
+        assert_eq!(report.verified, Some(false));
+        assert_eq!(report.payload_hash.as_deref(), Some(hash.as_str()));
+    }
+
+    #[test]
+    fn test_verify_prefix_case_insensitive() {
+        let hash = hex::encode(Blake2b::<U32>::digest(payload()));
+        for expect_hash in [
+            format!("0X{}", hash),
+            format!("0x{}", hash.to_uppercase()),
+            hash.to_uppercase(),
+        ] {
+            let report = run_args(&verify_args(&expect_hash)).expect("Verify failed");
+            assert!(report.passed(), "{} did not match", expect_hash);
+        }
+
+        assert!(run_args(&verify_args(&format!("0x0x{}", hash))).is_err());
+    }
+
+    #[test]
+    fn test_verify_incomplete_is_an_error() {
+        let mut args = verify_args(&"00".repeat(32));
+        args.truncate(4);
+        let Err(e) = run_args(&args) else {
+            panic!("Verify of a partial set succeeded");
+        };
+        let json = error_json(&e);
+        assert_eq!(json["error"]["kind"], "incomplete");
+        assert_eq!(json["error"]["message"], e.to_string());
+    }
+
+    #[test]
+    fn test_encode_decode_inspect_json() {
+        let report =
+            run_args(&["encode".to_string(), hex::encode(payload())]).expect("Encode failed");
+        assert!(report.passed());
+        assert_eq!(report.parts.as_deref(), Some(parts().as_slice()));
+        assert_eq!(
+            json_keys(&report),
+            ["complete", "elapsed_us", "part_count", "parts", "ur_type"]
+        );
+
+        let mut args = vec!["decode".to_string(), "--dump".to_string()];
+        args.extend(parts());
+        let report = run_args(&args).expect("Decode failed");
+        assert_eq!(report.payload_hex, Some(hex::encode(payload())));
+        assert_eq!(
+            report.payload_kind,
+            Some(classify_payload(&payload()).to_string())
+        );
+
+        let report =
+            run_args(&["inspect".to_string(), parts()[0].clone()]).expect("Inspect failed");
+        assert!(!report.complete);
+        assert_eq!(report.part_count, 1);
+        assert_eq!(report.expected_parts, Some(parts().len()));
+        assert_eq!(
+            json_keys(&report),
+            [
+                "complete",
+                "elapsed_us",
+                "expected_parts",
+                "part_count",
+                "ur_type"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_file_input() {
+        let payload_file = temp_path("payload.bin");
+        std::fs::write(&payload_file, payload()).expect("Write failed");
+        let encoded = run_args(&[
+            "encode".to_string(),
+            "--input".to_string(),
+            payload_file.display().to_string(),
+        ]);
+        std::fs::remove_file(&payload_file).expect("Remove failed");
+        let parts = encoded.expect("Encode failed").parts.expect("No parts");
+
+        let parts_file = temp_path("parts.txt");
+        std::fs::write(&parts_file, parts.join("\n")).expect("Write failed");
+        let decoded = run_args(&[
+            "decode".to_string(),
+            "--file".to_string(),
+            parts_file.display().to_string(),
+        ]);
+        std::fs::remove_file(&parts_file).expect("Remove failed");
+        assert_eq!(
+            decoded.expect("Decode failed").payload_hex,
+            Some(hex::encode(payload()))
+        );
+    }
+
+    #[test]
+    fn test_watch_directory() {
+        let dir = temp_path("watch");
+        std::fs::create_dir_all(&dir).expect("Create failed");
+        let parts = parts();
+        let (first, rest) = parts.split_at(1);
+        std::fs::write(dir.join("a.txt"), first.join("\n")).expect("Write failed");
+        std::fs::write(dir.join("b.txt"), rest.join("\n")).expect("Write failed");
+        std::fs::write(dir.join("c.txt"), "not a part").expect("Write failed");
+        let report = run_args(&[
+            "decode".to_string(),
+            "--watch".to_string(),
+            dir.display().to_string(),
+        ]);
+        std::fs::remove_dir_all(&dir).expect("Remove failed");
+        let report = report.expect("Watch failed");
+        assert!(report.complete);
+        assert_eq!(report.payload_hex, Some(hex::encode(payload())));
+    }
+
+    #[test]
+    fn test_gen_vectors() {
+        let out = temp_path("vectors.json");
+        let report = run_args(&[
+            "gen-vectors".to_string(),
+            "--seed".to_string(),
+            "7".to_string(),
+            "--out".to_string(),
+            out.display().to_string(),
+        ]);
+        let written = std::fs::read_to_string(&out);
+        std::fs::remove_file(&out).expect("Remove failed");
+        let report = report.expect("Generation failed");
+        assert_eq!(report.vector_count, Some(VECTOR_PAYLOAD_LENGTHS.len()));
+        assert_eq!(
+            json_keys(&report),
+            [
+                "complete",
+                "elapsed_us",
+                "part_count",
+                "ur_type",
+                "vector_count"
+            ]
+        );
+
+        let file: Value =
+            serde_json::from_str(&written.expect("Read failed")).expect("Invalid vector file");
+        assert_eq!(file["seed"], 7);
+        let vectors = file["vectors"].as_array().expect("No vectors");
+        assert_eq!(vectors.len(), VECTOR_PAYLOAD_LENGTHS.len());
+        let parts: usize = vectors
+            .iter()
+            .map(|v| v["parts"].as_array().map_or(0, Vec::len))
+            .sum();
+        assert_eq!(parts, report.part_count);
+    }
+}
@@ -0,0 +1,194 @@
+//! Several independent payloads in one fountain stream, e.g. a sign request
+//! together with the chain metadata snippets the signer needs to display it,
+//! so the user completes a single scan session.
+//!
+//! A bundle is a `quantus-bundle` UR whose CBOR body is an array of entries,
+//! each `[id, content type, payload]`. Ids are chosen by the sender and must
+//! be unique within the bundle; the content type tells the receiver how to
+//! interpret the payload.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use minicbor::{Decoder, Encoder};
+
+use crate::cbor::{cbor_error, expect_ur_type};
+use crate::{decode_message, EncodeOptions, QuantusUrError, UrDecoder, UrEncoder};
+
+pub const BUNDLE_UR_TYPE: &str = "quantus-bundle";
+
+/// Content type of a Quantus signing payload.
+pub const CONTENT_SIGN_REQUEST: &str = "sign-request";
+/// Content type of a runtime metadata snippet, e.g. a metadata proof.
+pub const CONTENT_METADATA: &str = "metadata";
+
+fn bundle_error(msg: &str) -> QuantusUrError {
+    QuantusUrError::CborError(format!("Bundle: {}", msg))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BundleEntry {
+    pub id: u32,
+    pub content_type: String,
+    pub payload: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bundle {
+    pub entries: Vec<BundleEntry>,
+}
+
+impl Bundle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an entry.
+    pub fn with_entry(mut self, id: u32, content_type: &str, payload: &[u8]) -> Self {
+        self.entries.push(BundleEntry {
+            id,
+            content_type: content_type.to_string(),
+            payload: payload.to_vec(),
+        });
+        self
+    }
+
+    pub fn get(&self, id: u32) -> Option<&BundleEntry> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+
+    /// Entries of the given content type, in bundle order.
+    pub fn of_type<'a>(&'a self, content_type: &'a str) -> impl Iterator<Item = &'a BundleEntry> {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.content_type == content_type)
+    }
+
+    /// Checks that the bundle has entries, none of them empty, with unique ids.
+    pub fn validate(&self) -> Result<(), QuantusUrError> {
+        if self.entries.is_empty() {
+            return Err(bundle_error("no entries"));
+        }
+        for (index, entry) in self.entries.iter().enumerate() {
+            if entry.payload.is_empty() {
+                return Err(QuantusUrError::EmptyPayload);
+            }
+            if self.entries[..index]
+                .iter()
+                .any(|other| other.id == entry.id)
+            {
+                return Err(bundle_error(&format!("duplicate id {}", entry.id)));
+            }
+        }
+        Ok(())
+    }
+
+    fn to_cbor(&self) -> Result<Vec<u8>, QuantusUrError> {
+        self.validate()?;
+        let mut e = Encoder::new(Vec::new());
+        e.array(self.entries.len() as u64).map_err(cbor_error)?;
+        for entry in &self.entries {
+            e.array(3)
+                .and_then(|e| e.u32(entry.id))
+                .and_then(|e| e.str(&entry.content_type))
+                .and_then(|e| e.bytes(&entry.payload))
+                .map_err(cbor_error)?;
+        }
+        Ok(e.into_writer())
+    }
+
+    fn from_cbor(cbor: &[u8]) -> Result<Self, QuantusUrError> {
+        let mut d = Decoder::new(cbor);
+        let count = d
+            .array()
+            .map_err(cbor_error)?
+            .ok_or_else(|| bundle_error("indefinite-length bundle"))?;
+        let mut bundle = Bundle::new();
+        for _ in 0..count {
+            if d.array().map_err(cbor_error)? != Some(3) {
+                return Err(bundle_error("malformed entry"));
+            }
+            bundle.entries.push(BundleEntry {
+                id: d.u32().map_err(cbor_error)?,
+                content_type: d.str().map_err(cbor_error)?.to_string(),
+                payload: d.bytes().map_err(cbor_error)?.to_vec(),
+            });
+        }
+        bundle.validate()?;
+        Ok(bundle)
+    }
+
+    /// Encoder for animating the bundle, e.g. with a larger fragment length.
+    pub fn encoder(&self, options: &EncodeOptions) -> Result<UrEncoder, QuantusUrError> {
+        UrEncoder::from_cbor_with_options(self.to_cbor()?, BUNDLE_UR_TYPE, options)
+    }
+
+    /// Minimal set of parts.
+    pub fn to_parts(&self) -> Result<Vec<String>, QuantusUrError> {
+        self.encoder(&EncodeOptions::default())?
+            .into_minimal_parts()
+            .collect()
+    }
+
+    pub fn from_parts(ur_parts: &[String]) -> Result<Self, QuantusUrError> {
+        let (ur_type, cbor) = decode_message(ur_parts)?;
+        expect_ur_type(&ur_type, BUNDLE_UR_TYPE)?;
+        Self::from_cbor(&cbor)
+    }
+
+    /// Reads the bundle from a completed streaming session.
+    pub fn from_decoder(decoder: &UrDecoder) -> Result<Self, QuantusUrError> {
+        let cbor = decoder.cbor_message()?;
+        expect_ur_type(decoder.ur_type().unwrap_or_default(), BUNDLE_UR_TYPE)?;
+        Self::from_cbor(&cbor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundle() -> Bundle {
+        let request: Vec<u8> = (0..400u32).map(|i| (i % 251) as u8).collect();
+        Bundle::new()
+            .with_entry(1, CONTENT_SIGN_REQUEST, &request)
+            .with_entry(2, CONTENT_METADATA, &[0x6d; 300])
+            .with_entry(3, CONTENT_METADATA, &[0x6e; 120])
+    }
+
+    #[test]
+    fn test_bundle_roundtrip() {
+        let bundle = bundle();
+        let mut encoder = bundle
+            .encoder(&EncodeOptions::default())
+            .expect("Encoding failed");
+        assert!(!encoder.is_single_part());
+
+        let mut decoder = UrDecoder::new();
+        while !decoder.is_complete() {
+            decoder
+                .receive(&encoder.next_part().expect("Encoding failed"))
+                .expect("Receive failed");
+        }
+        let decoded = Bundle::from_decoder(&decoder).expect("Decoding failed");
+        assert_eq!(decoded, bundle);
+        assert_eq!(decoded.of_type(CONTENT_METADATA).count(), 2);
+        assert_eq!(decoded.get(1).expect("Missing entry").payload.len(), 400);
+    }
+
+    #[test]
+    fn test_invalid_bundles_rejected() {
+        assert!(Bundle::new().to_parts().is_err());
+        assert!(bundle().with_entry(2, "other", b"x").to_parts().is_err());
+        assert!(matches!(
+            Bundle::new()
+                .with_entry(1, CONTENT_METADATA, &[])
+                .to_parts(),
+            Err(QuantusUrError::EmptyPayload)
+        ));
+        let parts = crate::encode_bytes(b"payload").expect("Encoding failed");
+        assert!(Bundle::from_parts(&parts).is_err());
+    }
+}
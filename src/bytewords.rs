@@ -0,0 +1,173 @@
+//! Bytewords encoding helpers for inspecting and debugging UR part bodies.
+//!
+//! Encoded text always carries the 4-byte CRC32 checksum defined by
+//! BCR-2020-012, and decoding verifies it.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use ur::bytewords::{self, Style};
+
+use crate::checksum::crc32;
+use crate::fixed::MINIMAL_BYTEWORDS;
+use crate::part::parse_part;
+use crate::QuantusUrError;
+
+/// Bytewords text style.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BytewordsStyle {
+    /// Full words separated by spaces.
+    Standard,
+    /// Full words separated by dashes.
+    Uri,
+    /// First and last letter of each word, no separators. Used in UR bodies.
+    Minimal,
+}
+
+impl From<BytewordsStyle> for Style {
+    fn from(style: BytewordsStyle) -> Self {
+        match style {
+            BytewordsStyle::Standard => Style::Standard,
+            BytewordsStyle::Uri => Style::Uri,
+            BytewordsStyle::Minimal => Style::Minimal,
+        }
+    }
+}
+
+/// Encodes `data` as bytewords with an appended checksum.
+pub fn encode(data: &[u8], style: BytewordsStyle) -> String {
+    bytewords::encode(data, style.into())
+}
+
+/// Decodes bytewords text, verifying and stripping the checksum.
+pub fn decode(text: &str, style: BytewordsStyle) -> Result<Vec<u8>, QuantusUrError> {
+    bytewords::decode(&text.to_lowercase(), style.into())
+        .map_err(|e| QuantusUrError::BytewordsError(e.to_string()))
+}
+
+/// Decodes the body of a UR part string. For single-part URs this is the CBOR
+/// message; for multi-part URs it is the CBOR-encoded fountain part.
+pub fn decode_part_body(part: &str) -> Result<Vec<u8>, QuantusUrError> {
+    Ok(parse_part(part)?.body)
+}
+
+/// Checks the bytewords CRC32 of a part body without parsing anything else,
+/// returning the checksum.
+///
+/// Fails with [`QuantusUrError::PartChecksumMismatch`] when the body was
+/// corrupted in transport, and with other errors when the text is not a
+/// bytewords part at all, so logs can tell the two apart.
+pub fn verify_part_checksum(part: &str) -> Result<u32, QuantusUrError> {
+    let lower = part.trim().to_lowercase();
+    if !lower.starts_with("ur:") {
+        return Err(QuantusUrError::UrError("Invalid scheme".to_string()));
+    }
+    let (_, body) = lower
+        .rsplit_once('/')
+        .ok_or_else(|| QuantusUrError::UrError("Invalid UR path".to_string()))?;
+    let bytes = minimal_letters(body)?;
+    if bytes.len() % 2 != 0 || bytes.len() < 8 {
+        return Err(QuantusUrError::BytewordsError(
+            "Invalid body length".to_string(),
+        ));
+    }
+    let data: Vec<u8> = bytes
+        .chunks(2)
+        .map(|pair| {
+            MINIMAL_BYTEWORDS
+                .chunks(2)
+                .position(|word| word == pair)
+                .map(|index| index as u8)
+                .ok_or_else(|| QuantusUrError::BytewordsError("Invalid word".to_string()))
+        })
+        .collect::<Result<_, _>>()?;
+    let (data, trailer) = data.split_at(data.len() - 4);
+    let expected = u32::from_be_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    let actual = crc32(data);
+    if expected != actual {
+        return Err(QuantusUrError::PartChecksumMismatch { expected, actual });
+    }
+    Ok(actual)
+}
+
+/// Letters of a minimal or dash-separated (URI style) body in minimal form.
+fn minimal_letters(body: &str) -> Result<Vec<u8>, QuantusUrError> {
+    if !body.contains('-') {
+        return Ok(body.as_bytes().to_vec());
+    }
+    let mut letters = Vec::with_capacity(body.len() / 2);
+    for word in body.split('-') {
+        let word = word.as_bytes();
+        if word.len() != 4 {
+            return Err(QuantusUrError::BytewordsError("Invalid word".to_string()));
+        }
+        letters.extend_from_slice(&[word[0], word[3]]);
+    }
+    Ok(letters)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encode_bytes;
+    use alloc::format;
+
+    #[test]
+    fn test_roundtrip_all_styles() {
+        let data = b"Hello, Quantus!";
+        for style in [
+            BytewordsStyle::Standard,
+            BytewordsStyle::Uri,
+            BytewordsStyle::Minimal,
+        ] {
+            let text = encode(data, style);
+            assert_eq!(decode(&text, style).expect("Decoding failed"), data);
+        }
+    }
+
+    #[test]
+    fn test_checksum_mismatch_rejected() {
+        let mut text = encode(b"Hello, Quantus!", BytewordsStyle::Minimal);
+        // "ae" and "ad" are both valid minimal bytewords, so only the checksum catches this
+        text.replace_range(..2, if text.starts_with("ae") { "ad" } else { "ae" });
+        assert!(matches!(
+            decode(&text, BytewordsStyle::Minimal),
+            Err(QuantusUrError::BytewordsError(_))
+        ));
+    }
+
+    #[test]
+    fn test_verify_part_checksum() {
+        let payload: Vec<u8> = (0..250).map(|i| i as u8).collect();
+        let parts = encode_bytes(&payload).expect("Encoding failed");
+        let part: crate::UrPart = parts[1].parse().expect("Parsing failed");
+        assert_eq!(
+            verify_part_checksum(&parts[1]).expect("Checksum failed"),
+            part.part_checksum()
+        );
+
+        let (prefix, body) = parts[1].rsplit_once('/').unwrap();
+        let flipped = if body.starts_with("LP") { "LS" } else { "LP" };
+        let corrupted = format!("{}/{}{}", prefix, flipped, &body[2..]);
+        assert_eq!(
+            verify_part_checksum(&corrupted).unwrap_err().kind(),
+            "part_checksum_mismatch"
+        );
+        assert!(matches!(
+            corrupted.parse::<crate::UrPart>(),
+            Err(QuantusUrError::PartChecksumMismatch { .. })
+        ));
+        assert_eq!(verify_part_checksum("hello").unwrap_err().kind(), "ur");
+    }
+
+    #[test]
+    fn test_decode_part_body() {
+        let parts = encode_bytes(b"Hello, Quantus!").expect("Encoding failed");
+        let body = decode_part_body(&parts[0]).expect("Decoding failed");
+        let (_, minimal) = parts[0].rsplit_once('/').expect("Missing body");
+        assert_eq!(
+            encode(&body, BytewordsStyle::Minimal),
+            minimal.to_lowercase()
+        );
+    }
+}
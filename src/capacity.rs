@@ -0,0 +1,181 @@
+//! Alphanumeric-mode QR capacity for versions 1–40, used to size fragments so
+//! every part fits the code a renderer will produce.
+
+use crate::{MAX_QR_FRAGMENT_LENGTH, MIN_FRAGMENT_LENGTH};
+
+/// QR error correction level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ErrorCorrection {
+    /// Recovers ~7% of damaged modules. Densest frames, best for animated URs.
+    #[default]
+    Low,
+    Medium,
+    Quartile,
+    High,
+}
+
+impl ErrorCorrection {
+    fn index(self) -> usize {
+        match self {
+            ErrorCorrection::Low => 0,
+            ErrorCorrection::Medium => 1,
+            ErrorCorrection::Quartile => 2,
+            ErrorCorrection::High => 3,
+        }
+    }
+}
+
+/// Maximum alphanumeric characters per QR version (row 0 is version 1), by
+/// error correction level in `L, M, Q, H` order. Per ISO/IEC 18004.
+pub const ALPHANUMERIC_CAPACITY: [[u16; 4]; 40] = [
+    [25, 20, 16, 10],
+    [47, 38, 29, 20],
+    [77, 61, 47, 35],
+    [114, 90, 67, 50],
+    [154, 122, 87, 64],
+    [195, 154, 108, 84],
+    [224, 178, 125, 93],
+    [279, 221, 157, 122],
+    [335, 262, 189, 143],
+    [395, 311, 221, 174],
+    [468, 366, 259, 200],
+    [535, 419, 296, 227],
+    [619, 483, 352, 259],
+    [667, 528, 376, 283],
+    [758, 600, 426, 321],
+    [854, 656, 470, 365],
+    [938, 734, 531, 408],
+    [1046, 816, 574, 452],
+    [1153, 909, 644, 493],
+    [1249, 970, 702, 557],
+    [1352, 1035, 742, 587],
+    [1460, 1134, 823, 640],
+    [1588, 1248, 890, 672],
+    [1704, 1326, 963, 744],
+    [1853, 1451, 1041, 779],
+    [1990, 1542, 1094, 864],
+    [2132, 1637, 1172, 910],
+    [2223, 1732, 1263, 958],
+    [2369, 1839, 1322, 1016],
+    [2520, 1994, 1429, 1080],
+    [2677, 2113, 1499, 1150],
+    [2840, 2238, 1618, 1226],
+    [3009, 2369, 1700, 1307],
+    [3183, 2506, 1787, 1394],
+    [3351, 2632, 1867, 1431],
+    [3537, 2780, 1966, 1530],
+    [3729, 2894, 2071, 1591],
+    [3927, 3054, 2181, 1658],
+    [4087, 3220, 2298, 1774],
+    [4296, 3391, 2420, 1852],
+];
+
+/// Worst-case bytes a fountain part adds around its fragment: the CBOR array
+/// header, four `u32` fields, the byte string head and the bytewords CRC32.
+const PART_OVERHEAD: usize = 1 + 4 * 5 + 3 + 4;
+
+/// Worst-case characters of `UR:<type>//` with a `<seq>-<count>` of two `u32`s.
+fn prefix_length(ur_type: &str) -> usize {
+    "UR:".len() + ur_type.len() + 1 + 21 + 1
+}
+
+/// Alphanumeric characters that fit a QR code of `version` (1–40).
+pub fn alphanumeric_capacity(version: u8, ec: ErrorCorrection) -> Option<usize> {
+    let row = ALPHANUMERIC_CAPACITY.get(usize::from(version).checked_sub(1)?)?;
+    Some(row[ec.index()] as usize)
+}
+
+/// Smallest QR version holding `length` alphanumeric characters.
+pub fn min_version(length: usize, ec: ErrorCorrection) -> Option<u8> {
+    (1..=40).find(|&version| alphanumeric_capacity(version, ec) >= Some(length))
+}
+
+/// Upper bound on the length of a multi-part part with the given fragment
+/// length.
+pub fn part_length(ur_type: &str, fragment_length: usize) -> usize {
+    prefix_length(ur_type) + (fragment_length + PART_OVERHEAD) * 2
+}
+
+/// Largest fragment length whose parts are guaranteed to fit a QR code of
+/// `version`, capped at [`MAX_QR_FRAGMENT_LENGTH`]. `None` when the version
+/// cannot hold a fragment of [`MIN_FRAGMENT_LENGTH`].
+pub fn max_fragment_length(ur_type: &str, version: u8, ec: ErrorCorrection) -> Option<usize> {
+    let capacity = alphanumeric_capacity(version, ec)?;
+    let fragment = capacity.checked_sub(prefix_length(ur_type))? / 2;
+    fragment
+        .checked_sub(PART_OVERHEAD)
+        .filter(|&fragment| fragment >= MIN_FRAGMENT_LENGTH)
+        .map(|fragment| fragment.min(MAX_QR_FRAGMENT_LENGTH))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{encode_bytes_with_options, EncodeOptions, UR_TYPE};
+
+    #[test]
+    fn test_capacity_lookup() {
+        assert_eq!(alphanumeric_capacity(1, ErrorCorrection::Low), Some(25));
+        assert_eq!(alphanumeric_capacity(40, ErrorCorrection::High), Some(1852));
+        assert_eq!(alphanumeric_capacity(0, ErrorCorrection::Low), None);
+        assert_eq!(alphanumeric_capacity(41, ErrorCorrection::Low), None);
+        assert_eq!(min_version(25, ErrorCorrection::Low), Some(1));
+        assert_eq!(min_version(26, ErrorCorrection::Low), Some(2));
+        assert_eq!(min_version(4297, ErrorCorrection::Low), None);
+    }
+
+    #[test]
+    fn test_parts_fit_their_version() {
+        assert_eq!(
+            max_fragment_length(UR_TYPE, 40, ErrorCorrection::Low),
+            Some(MAX_QR_FRAGMENT_LENGTH)
+        );
+        assert_eq!(max_fragment_length(UR_TYPE, 1, ErrorCorrection::High), None);
+
+        let payload: alloc::vec::Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+        for version in [10, 20, 30] {
+            let ec = ErrorCorrection::Medium;
+            let options = EncodeOptions {
+                max_fragment_length: max_fragment_length(UR_TYPE, version, ec).unwrap(),
+                ..EncodeOptions::default()
+            };
+            let parts = encode_bytes_with_options(&payload, &options).expect("Encoding failed");
+            for part in &parts {
+                assert!(part.len() <= alphanumeric_capacity(version, ec).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn test_every_length_encodes() {
+        assert_eq!(max_fragment_length(UR_TYPE, 4, ErrorCorrection::Low), None);
+        assert_eq!(
+            max_fragment_length(UR_TYPE, 6, ErrorCorrection::Quartile),
+            None
+        );
+
+        let payload: alloc::vec::Vec<u8> = (0..500u32).map(|i| (i % 251) as u8).collect();
+        let levels = [
+            ErrorCorrection::Low,
+            ErrorCorrection::Medium,
+            ErrorCorrection::Quartile,
+            ErrorCorrection::High,
+        ];
+        for version in 1..=40 {
+            for ec in levels {
+                let Some(fragment_length) = max_fragment_length(UR_TYPE, version, ec) else {
+                    continue;
+                };
+                let options = EncodeOptions {
+                    max_fragment_length: fragment_length,
+                    ..EncodeOptions::default()
+                };
+                let parts = encode_bytes_with_options(&payload, &options).expect("Encoding failed");
+                for part in &parts {
+                    assert!(part.len() <= alphanumeric_capacity(version, ec).unwrap());
+                }
+            }
+        }
+    }
+}
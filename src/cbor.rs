@@ -0,0 +1,138 @@
+//! Validation of the CBOR envelope around sign-request payloads, and the
+//! error and UR type helpers shared by the message modules.
+
+use alloc::format;
+use alloc::string::ToString;
+
+use crate::QuantusUrError;
+
+const MAJOR_BYTES: u8 = 2;
+const MAJOR_TAG: u8 = 6;
+
+/// Tag 24, "encoded CBOR data item", which is always accepted.
+pub(crate) const EMBEDDED_CBOR_TAG: u64 = 24;
+
+pub(crate) fn cbor_error<E: core::fmt::Display>(e: E) -> QuantusUrError {
+    QuantusUrError::CborError(e.to_string())
+}
+
+/// Fails with [`QuantusUrError::UrError`] unless a reassembled message is of
+/// the `expected` UR type.
+pub(crate) fn expect_ur_type(ur_type: &str, expected: &str) -> Result<(), QuantusUrError> {
+    if ur_type != expected {
+        return Err(QuantusUrError::UrError(format!(
+            "Expected a {} message, got {}",
+            expected, ur_type
+        )));
+    }
+    Ok(())
+}
+
+/// Reads the major type and argument of a data item, rejecting arguments not
+/// in their shortest encoding. Returns the argument and the header length.
+fn read_head(message: &[u8]) -> Result<(u8, u64, usize), QuantusUrError> {
+    let initial = *message.first().ok_or_else(|| cbor_error("Empty message"))?;
+    let (value, header, minimum) = match initial & 0x1f {
+        info @ 0..=23 => (info as u64, 1, 0),
+        24 => (read_uint(message, 1)?, 2, 24),
+        25 => (read_uint(message, 2)?, 3, 1 << 8),
+        26 => (read_uint(message, 4)?, 5, 1 << 16),
+        27 => (read_uint(message, 8)?, 9, 1 << 32),
+        31 => return Err(cbor_error("Indefinite-length item in strict mode")),
+        _ => return Err(cbor_error("Reserved additional information value")),
+    };
+    if value < minimum {
+        return Err(QuantusUrError::CborError(format!(
+            "Non-minimal argument encoding: {} encoded in {} bytes",
+            value,
+            header - 1
+        )));
+    }
+    Ok((initial >> 5, value, header))
+}
+
+/// Reads an optionally tagged byte string that must make up the whole
+/// message. Tags other than 24 must be in `allowed_tags`.
+pub(crate) fn strict_payload<'a>(
+    message: &'a [u8],
+    allowed_tags: &[u64],
+) -> Result<(Option<u64>, &'a [u8]), QuantusUrError> {
+    let (major, value, header) = read_head(message)?;
+    if major != MAJOR_TAG {
+        return Ok((None, strict_byte_string(message)?));
+    }
+    if value != EMBEDDED_CBOR_TAG && !allowed_tags.contains(&value) {
+        return Err(QuantusUrError::CborError(format!(
+            "Tag {} is not in the allow-list",
+            value
+        )));
+    }
+    Ok((Some(value), strict_byte_string(&message[header..])?))
+}
+
+/// Reads a definite-length byte string that must make up the whole message,
+/// with its length in the shortest possible encoding.
+pub(crate) fn strict_byte_string(message: &[u8]) -> Result<&[u8], QuantusUrError> {
+    let (major, len, header) = read_head(message)?;
+    if major != MAJOR_BYTES {
+        return Err(QuantusUrError::CborError(format!(
+            "Expected a byte string (major type 2), found major type {}",
+            major
+        )));
+    }
+    let body = &message[header..];
+    if (body.len() as u64) < len {
+        return Err(cbor_error("Byte string is truncated"));
+    }
+    if body.len() as u64 > len {
+        return Err(QuantusUrError::CborError(format!(
+            "{} trailing bytes after the CBOR item",
+            body.len() as u64 - len
+        )));
+    }
+    Ok(body)
+}
+
+fn read_uint(message: &[u8], size: usize) -> Result<u64, QuantusUrError> {
+    let bytes = message
+        .get(1..1 + size)
+        .ok_or_else(|| cbor_error("Truncated length"))?;
+    Ok(bytes.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strict_accepts_canonical() {
+        assert_eq!(strict_byte_string(&[0x43, 1, 2, 3]).unwrap(), &[1, 2, 3]);
+        let mut long = alloc::vec![0x58, 30];
+        long.extend_from_slice(&[7; 30]);
+        assert_eq!(strict_byte_string(&long).unwrap(), &[7; 30]);
+    }
+
+    #[test]
+    fn test_strict_rejects_sloppy_cbor() {
+        // Trailing byte
+        assert!(strict_byte_string(&[0x42, 1, 2, 3]).is_err());
+        // Length 3 encoded in one extra byte
+        assert!(strict_byte_string(&[0x58, 3, 1, 2, 3]).is_err());
+        // Text string instead of byte string
+        assert!(strict_byte_string(&[0x63, b'a', b'b', b'c']).is_err());
+        // Truncated
+        assert!(strict_byte_string(&[0x44, 1, 2]).is_err());
+    }
+
+    #[test]
+    fn test_strict_tags() {
+        let tagged = [0xd8, 0x18, 0x41, 9];
+        assert_eq!(strict_payload(&tagged, &[]).unwrap(), (Some(24), &[9][..]));
+        let registry = [0xd9, 0x01, 0x30, 0x41, 9];
+        assert!(strict_payload(&registry, &[]).is_err());
+        assert_eq!(
+            strict_payload(&registry, &[304]).unwrap(),
+            (Some(304), &[9][..])
+        );
+    }
+}
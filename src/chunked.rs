@@ -0,0 +1,249 @@
+//! Plain hex chunks for scanners that cannot read UR, selected with
+//! [`crate::EncodingMode::HexChunks`].
+//!
+//! Each chunk reads `QSR <index>/<total> <HEX>`, with a 1-based index and the
+//! payload bytes in uppercase hex, so the whole text fits a QR code's
+//! alphanumeric mode. Chunks are a plain split of the payload: there is no
+//! fountain coding, so every chunk has to be scanned.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::part::MAX_SEQUENCE_COUNT;
+use crate::QuantusUrError;
+
+/// Prefix identifying a chunk, short for Quantus sign request.
+pub const CHUNK_PREFIX: &str = "QSR";
+
+fn chunk_error(msg: &str) -> QuantusUrError {
+    QuantusUrError::UrError(format!("Hex chunk: {}", msg))
+}
+
+/// One parsed chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HexChunk {
+    /// 1-based position of the chunk.
+    pub index: u32,
+    pub total: u32,
+    pub data: Vec<u8>,
+}
+
+impl HexChunk {
+    /// Whether `text` looks like a chunk rather than a UR part.
+    pub fn is_chunk(text: &str) -> bool {
+        let bytes = text.as_bytes();
+        bytes.len() > CHUNK_PREFIX.len()
+            && bytes[..CHUNK_PREFIX.len()].eq_ignore_ascii_case(CHUNK_PREFIX.as_bytes())
+            && bytes[CHUNK_PREFIX.len()] == b' '
+    }
+
+    /// Parses a scanned chunk, in either case.
+    pub fn parse(text: &str) -> Result<Self, QuantusUrError> {
+        let mut fields = text.trim().split(' ').filter(|f| !f.is_empty());
+        if !fields
+            .next()
+            .map_or(false, |prefix| prefix.eq_ignore_ascii_case(CHUNK_PREFIX))
+        {
+            return Err(chunk_error("missing prefix"));
+        }
+        let (index, total) = fields
+            .next()
+            .and_then(|position| position.split_once('/'))
+            .ok_or_else(|| chunk_error("missing position"))?;
+        let index: u32 = index.parse().map_err(|_| chunk_error("invalid index"))?;
+        let total: u32 = total.parse().map_err(|_| chunk_error("invalid total"))?;
+        if index == 0 || index > total || total > MAX_SEQUENCE_COUNT {
+            return Err(chunk_error("position out of range"));
+        }
+        let hex = fields.next().ok_or_else(|| chunk_error("missing data"))?;
+        if fields.next().is_some() {
+            return Err(chunk_error("trailing text"));
+        }
+        Ok(HexChunk {
+            index,
+            total,
+            data: decode_hex(hex)?,
+        })
+    }
+}
+
+impl core::fmt::Display for HexChunk {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{} {}/{} ", CHUNK_PREFIX, self.index, self.total)?;
+        self.data
+            .iter()
+            .try_for_each(|byte| write!(f, "{:02X}", byte))
+    }
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>, QuantusUrError> {
+    let digit = |c: u8| {
+        (c as char)
+            .to_digit(16)
+            .map(|d| d as u8)
+            .ok_or_else(|| chunk_error("invalid hex"))
+    };
+    let hex = hex.as_bytes();
+    if hex.is_empty() || hex.len() % 2 != 0 {
+        return Err(chunk_error("invalid hex length"));
+    }
+    hex.chunks(2)
+        .map(|pair| Ok(digit(pair[0])? << 4 | digit(pair[1])?))
+        .collect()
+}
+
+/// Splits `payload` into chunks of at most `chunk_length` bytes.
+pub fn encode_chunks(payload: &[u8], chunk_length: usize) -> Result<Vec<String>, QuantusUrError> {
+    if payload.is_empty() {
+        return Err(QuantusUrError::EmptyPayload);
+    }
+    let chunk_length = chunk_length.max(1);
+    let total = (payload.len() + chunk_length - 1) / chunk_length;
+    if total > MAX_SEQUENCE_COUNT as usize {
+        return Err(chunk_error("payload needs too many chunks"));
+    }
+    Ok(payload
+        .chunks(chunk_length)
+        .zip(1u32..)
+        .map(|(data, index)| {
+            HexChunk {
+                index,
+                total: total as u32,
+                data: data.to_vec(),
+            }
+            .to_string()
+        })
+        .collect())
+}
+
+/// Chunks received so far in a decode session.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ChunkSet {
+    chunks: Vec<Option<Vec<u8>>>,
+}
+
+impl ChunkSet {
+    /// Stores the chunk, returning whether it was new.
+    pub(crate) fn insert(&mut self, chunk: HexChunk) -> Result<bool, QuantusUrError> {
+        if self.chunks.is_empty() {
+            self.chunks = alloc::vec![None; chunk.total as usize];
+        } else if self.chunks.len() != chunk.total as usize {
+            return Err(QuantusUrError::InconsistentSequence {
+                expected: self.chunks.len() as u32,
+                actual: chunk.total,
+            });
+        }
+        let slot = &mut self.chunks[chunk.index as usize - 1];
+        match slot {
+            Some(data) if *data != chunk.data => Err(chunk_error("conflicting data")),
+            Some(_) => Ok(false),
+            None => {
+                *slot = Some(chunk.data);
+                Ok(true)
+            }
+        }
+    }
+
+    pub(crate) fn total(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Bytes held by the received chunks.
+    pub(crate) fn memory_usage(&self) -> usize {
+        self.chunks.iter().flatten().map(Vec::len).sum()
+    }
+
+    /// 1-based indices of the chunks not received yet.
+    pub(crate) fn missing(&self) -> Vec<u32> {
+        (1u32..)
+            .zip(&self.chunks)
+            .filter(|(_, chunk)| chunk.is_none())
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    pub(crate) fn is_complete(&self) -> bool {
+        !self.chunks.is_empty() && self.chunks.iter().all(Option::is_some)
+    }
+
+    pub(crate) fn payload(&self) -> Option<Vec<u8>> {
+        if !self.is_complete() {
+            return None;
+        }
+        Some(self.chunks.iter().flatten().flatten().copied().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        decode_bytes, encode_bytes_with_options, DecodeEvent, EncodeOptions, EncodingMode,
+        UrDecoder,
+    };
+
+    #[test]
+    fn test_chunk_format() {
+        let parts = encode_chunks(&[0xde, 0xad, 0xbe, 0xef, 0x01], 2).expect("Encoding failed");
+        assert_eq!(parts, ["QSR 1/3 DEAD", "QSR 2/3 BEEF", "QSR 3/3 01"]);
+        assert_eq!(
+            HexChunk::parse("qsr 2/3 beef")
+                .expect("Parsing failed")
+                .data,
+            [0xbe, 0xef]
+        );
+        assert!(HexChunk::parse("QSR 4/3 BEEF").is_err());
+        assert!(HexChunk::parse("QSR 1/3 BEE").is_err());
+    }
+
+    #[test]
+    fn test_hex_chunks_roundtrip() {
+        let payload: Vec<u8> = (0..1000u32).map(|i| (i % 251) as u8).collect();
+        let options = EncodeOptions {
+            mode: EncodingMode::HexChunks,
+            ..EncodeOptions::default()
+        };
+        let mut parts = encode_bytes_with_options(&payload, &options).expect("Encoding failed");
+        assert!(parts.len() > 1);
+        assert!(parts.iter().all(|part| part.starts_with("QSR ")));
+        parts.reverse();
+        assert_eq!(decode_bytes(&parts).expect("Decoding failed"), payload);
+
+        let mut decoder = UrDecoder::new();
+        assert_eq!(
+            decoder.receive_event(&parts[0]).expect("Receive failed"),
+            DecodeEvent::Progress {
+                received: 1,
+                expected: parts.len()
+            }
+        );
+        assert!(matches!(
+            decoder.receive_event(&parts[0]),
+            Ok(DecodeEvent::Duplicate { .. })
+        ));
+        assert_eq!(decoder.missing_parts().len(), parts.len() - 1);
+    }
+
+    #[test]
+    fn test_hex_chunks_validated() {
+        let payload: Vec<u8> = (0..1000u32).map(|i| (i % 251) as u8).collect();
+        let options = EncodeOptions {
+            mode: EncodingMode::HexChunks,
+            max_fragment_length: 1,
+            ..EncodeOptions::default()
+        };
+        assert!(matches!(
+            encode_bytes_with_options(&payload, &options),
+            Err(QuantusUrError::InvalidConfiguration(_))
+        ));
+
+        let options = EncodeOptions {
+            mode: EncodingMode::HexChunks,
+            ..EncodeOptions::with_max_parts(4)
+        };
+        let parts = encode_bytes_with_options(&payload, &options).expect("Encoding failed");
+        assert_eq!(parts.len(), 4);
+    }
+}
@@ -0,0 +1,149 @@
+//! Best-effort sniffing of decoded payloads, for triage during interop debugging.
+
+use core::fmt;
+use minicbor::Decoder;
+
+/// What a decoded payload most likely contains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PayloadKind {
+    /// Length-prefixed SCALE extrinsic with the given version byte.
+    ScaleExtrinsic {
+        version: u8,
+        signed: bool,
+    },
+    /// 32 raw bytes, e.g. a hash of an oversized signing payload.
+    Hash,
+    /// A single well-formed CBOR item.
+    Cbor,
+    /// Printable UTF-8 text.
+    Utf8Text,
+    Unknown,
+}
+
+impl fmt::Display for PayloadKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PayloadKind::ScaleExtrinsic { version, signed } => write!(
+                f,
+                "SCALE extrinsic v{} ({})",
+                version & 0x3f,
+                if *signed { "signed" } else { "unsigned" }
+            ),
+            PayloadKind::Hash => write!(f, "32-byte hash"),
+            PayloadKind::Cbor => write!(f, "CBOR"),
+            PayloadKind::Utf8Text => write!(f, "UTF-8 text"),
+            PayloadKind::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// Decodes a SCALE compact integer, returning the value and its encoded length.
+pub(crate) fn scale_compact(data: &[u8]) -> Option<(u128, usize)> {
+    let first = *data.first()?;
+    match first & 0b11 {
+        0 => Some(((first >> 2) as u128, 1)),
+        1 => {
+            let bytes = data.get(..2)?;
+            Some(((u16::from_le_bytes([bytes[0], bytes[1]]) >> 2) as u128, 2))
+        }
+        2 => {
+            let bytes: [u8; 4] = data.get(..4)?.try_into().ok()?;
+            Some(((u32::from_le_bytes(bytes) >> 2) as u128, 4))
+        }
+        _ => {
+            let len = (first >> 2) as usize + 4;
+            if len > 16 {
+                return None;
+            }
+            let mut bytes = [0u8; 16];
+            bytes[..len].copy_from_slice(data.get(1..1 + len)?);
+            Some((u128::from_le_bytes(bytes), 1 + len))
+        }
+    }
+}
+
+pub(crate) fn scale_extrinsic(payload: &[u8]) -> Option<PayloadKind> {
+    let (len, prefix) = scale_compact(payload)?;
+    if len != (payload.len() - prefix) as u128 || len == 0 {
+        return None;
+    }
+    let version = payload[prefix];
+    // Version 4 and 5 extrinsics; the top bits flag signed (0x80) and general (0x40) ones.
+    if !matches!(version & 0x3f, 4 | 5) {
+        return None;
+    }
+    Some(PayloadKind::ScaleExtrinsic {
+        version,
+        signed: version & 0x80 != 0,
+    })
+}
+
+fn is_cbor(payload: &[u8]) -> bool {
+    let mut d = Decoder::new(payload);
+    d.skip().is_ok() && d.position() == payload.len()
+}
+
+fn is_text(payload: &[u8]) -> bool {
+    match core::str::from_utf8(payload) {
+        Ok(text) => text.chars().all(|c| !c.is_control() || c.is_whitespace()),
+        Err(_) => false,
+    }
+}
+
+/// Guesses what a decoded payload contains. A length-prefixed extrinsic wins
+/// over the other formats; random 32-byte hashes are practically never
+/// printable text, so text is checked first.
+pub fn classify_payload(payload: &[u8]) -> PayloadKind {
+    if payload.is_empty() {
+        return PayloadKind::Unknown;
+    }
+    if let Some(kind) = scale_extrinsic(payload) {
+        return kind;
+    }
+    if is_text(payload) {
+        return PayloadKind::Utf8Text;
+    }
+    if payload.len() == 32 {
+        return PayloadKind::Hash;
+    }
+    if is_cbor(payload) {
+        return PayloadKind::Cbor;
+    }
+    PayloadKind::Unknown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_scale_extrinsic() {
+        let mut extrinsic = vec![0x84];
+        extrinsic.extend_from_slice(&[0xaa; 99]);
+        let mut payload = vec![(100 << 2) as u8 | 0b01, 0x01];
+        payload.extend_from_slice(&extrinsic);
+        assert_eq!(
+            classify_payload(&payload),
+            PayloadKind::ScaleExtrinsic {
+                version: 0x84,
+                signed: true
+            }
+        );
+    }
+
+    #[test]
+    fn test_other_kinds() {
+        assert_eq!(classify_payload(&[0x11; 32]), PayloadKind::Hash);
+        assert_eq!(
+            classify_payload(b"Hello, Quantus!\n"),
+            PayloadKind::Utf8Text
+        );
+        assert_eq!(
+            classify_payload(&[0x82, 0x01, 0x43, 0xff, 0xfe, 0xfd]),
+            PayloadKind::Cbor
+        );
+        assert_eq!(classify_payload(&[0xff, 0x00, 0x13]), PayloadKind::Unknown);
+    }
+}
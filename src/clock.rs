@@ -0,0 +1,59 @@
+//! Time source for expiry, TTLs and session deadlines.
+//!
+//! The `now` arguments taken elsewhere in the crate are in whatever unit the
+//! caller picks; the `*_with_clock` variants read a [`Clock`] instead, which
+//! counts milliseconds. Only differences between readings matter, so firmware
+//! without an RTC can count from boot with [`ManualClock`] or its own timer.
+
+use core::cell::Cell;
+
+/// Millisecond time source.
+pub trait Clock {
+    /// Current time in milliseconds. Must not go backwards.
+    fn now(&self) -> u64;
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> u64 {
+        (**self).now()
+    }
+}
+
+/// Wall-clock time, in milliseconds since the Unix epoch. The default clock.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis() as u64)
+    }
+}
+
+/// Clock advanced by hand, for deterministic tests and for firmware that
+/// feeds it from a tick counter.
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock(Cell<u64>);
+
+impl ManualClock {
+    pub fn new(now: u64) -> Self {
+        ManualClock(Cell::new(now))
+    }
+
+    pub fn set(&self, now: u64) {
+        self.0.set(now);
+    }
+
+    pub fn advance(&self, millis: u64) {
+        self.0.set(self.0.get().saturating_add(millis));
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> u64 {
+        self.0.get()
+    }
+}
@@ -0,0 +1,150 @@
+//! Protocol parameters this build of the crate runs with, for display and
+//! logging by downstream apps instead of hard-coded copies.
+
+use core::ops::RangeInclusive;
+
+use crate::options::{MAX_QR_FRAGMENT_LENGTH, MIN_FRAGMENT_LENGTH};
+use crate::response::{RESPONSE_UR_TYPE, RESPONSE_VERSION};
+use crate::split::{SPLIT_UR_TYPE, SPLIT_VERSION};
+use crate::transfer::{
+    CHUNK_UR_TYPE, DEFAULT_CHUNK_SIZE, MANIFEST_UR_TYPE, MANIFEST_VERSION, MAX_CHUNK_COUNT,
+    MISSING_UR_TYPE,
+};
+use crate::{MAX_FRAGMENT_LENGTH, UR_TYPE};
+
+/// UR type of sign requests produced by [`crate::encode_bytes`].
+pub fn sign_request_ur_type() -> &'static str {
+    UR_TYPE
+}
+
+/// UR types used by the chunked [`crate::transfer`] protocol: manifest,
+/// chunk and missing-chunks messages.
+pub fn transfer_ur_types() -> [&'static str; 3] {
+    [MANIFEST_UR_TYPE, CHUNK_UR_TYPE, MISSING_UR_TYPE]
+}
+
+/// Fragment length used when none is configured.
+pub fn default_fragment_length() -> usize {
+    MAX_FRAGMENT_LENGTH
+}
+
+/// Fragment lengths accepted by [`crate::EncodeOptions::validate`].
+pub fn fragment_length_range() -> RangeInclusive<usize> {
+    MIN_FRAGMENT_LENGTH..=MAX_QR_FRAGMENT_LENGTH
+}
+
+/// Transfer manifest versions this build reads and writes.
+pub fn supported_manifest_versions() -> &'static [u8] {
+    &[MANIFEST_VERSION]
+}
+
+/// A versioned CBOR envelope and the version this build writes and accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct EnvelopeVersion {
+    pub ur_type: &'static str,
+    pub version: u8,
+}
+
+static ENVELOPE_VERSIONS: &[EnvelopeVersion] = &[
+    EnvelopeVersion {
+        ur_type: RESPONSE_UR_TYPE,
+        version: RESPONSE_VERSION,
+    },
+    EnvelopeVersion {
+        ur_type: SPLIT_UR_TYPE,
+        version: SPLIT_VERSION,
+    },
+    #[cfg(feature = "passphrase")]
+    EnvelopeVersion {
+        ur_type: crate::passphrase::ENCRYPTED_UR_TYPE,
+        version: crate::passphrase::VERSION,
+    },
+    #[cfg(feature = "ml-dsa")]
+    EnvelopeVersion {
+        ur_type: crate::envelope::ENVELOPE_UR_TYPE,
+        version: crate::envelope::ENVELOPE_VERSION,
+    },
+    #[cfg(feature = "substrate")]
+    EnvelopeVersion {
+        ur_type: crate::extrinsic::STRUCTURED_UR_TYPE,
+        version: crate::extrinsic::STRUCTURED_VERSION,
+    },
+    #[cfg(feature = "airgap")]
+    EnvelopeVersion {
+        ur_type: crate::airgap::AIRGAP_UR_TYPE,
+        version: crate::airgap::IACP_VERSION,
+    },
+];
+
+/// Envelope versions of the message types enabled in this build; a
+/// message with any other version is rejected when decoded.
+pub fn supported_envelope_versions() -> &'static [EnvelopeVersion] {
+    ENVELOPE_VERSIONS
+}
+
+pub fn default_chunk_size() -> usize {
+    DEFAULT_CHUNK_SIZE
+}
+
+pub fn max_chunk_count() -> usize {
+    MAX_CHUNK_COUNT
+}
+
+/// All parameters at once, e.g. for a diagnostics screen or a log line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ProtocolInfo {
+    pub crate_version: &'static str,
+    pub sign_request_ur_type: &'static str,
+    pub transfer_ur_types: [&'static str; 3],
+    pub default_fragment_length: usize,
+    pub min_fragment_length: usize,
+    pub max_fragment_length: usize,
+    pub manifest_versions: &'static [u8],
+    pub envelope_versions: &'static [EnvelopeVersion],
+    pub default_chunk_size: usize,
+    pub max_chunk_count: usize,
+}
+
+pub fn protocol_info() -> ProtocolInfo {
+    ProtocolInfo {
+        crate_version: env!("CARGO_PKG_VERSION"),
+        sign_request_ur_type: sign_request_ur_type(),
+        transfer_ur_types: transfer_ur_types(),
+        default_fragment_length: default_fragment_length(),
+        min_fragment_length: *fragment_length_range().start(),
+        max_fragment_length: *fragment_length_range().end(),
+        manifest_versions: supported_manifest_versions(),
+        envelope_versions: supported_envelope_versions(),
+        default_chunk_size: default_chunk_size(),
+        max_chunk_count: max_chunk_count(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{encode_bytes, UrPart};
+
+    #[test]
+    fn test_ur_type_matches_encoder() {
+        let parts = encode_bytes(b"Hello, Quantus!").expect("Encoding failed");
+        let part: UrPart = parts[0].parse().expect("Parsing failed");
+        assert_eq!(part.ur_type(), sign_request_ur_type());
+        assert!(fragment_length_range().contains(&default_fragment_length()));
+    }
+
+    #[test]
+    fn test_envelope_versions_listed() {
+        let info = protocol_info();
+        assert!(info.envelope_versions.contains(&EnvelopeVersion {
+            ur_type: RESPONSE_UR_TYPE,
+            version: RESPONSE_VERSION,
+        }));
+        assert!(info
+            .envelope_versions
+            .iter()
+            .any(|v| v.ur_type == SPLIT_UR_TYPE));
+    }
+}
@@ -0,0 +1,221 @@
+//! One symmetric API for both sides of the air gap.
+//!
+//! A [`Conversation`] pairs an outgoing encoder with an incoming decoder. The
+//! requester sends a `quantus-sign-request` and accepts only the
+//! `quantus-sign-response` answering it; the signer accepts a request, then
+//! sends the response built by [`Conversation::respond`].
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::response::{ResponseCollector, SignResponse};
+use crate::{QuantusUrError, UrDecoder, UrEncoder, UrPart, UR_TYPE};
+
+/// Which side of the exchange a [`Conversation`] plays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Role {
+    /// Sends the request, receives the response.
+    Requester,
+    /// Receives the request, sends the response.
+    Signer,
+}
+
+/// A complete message received by a [`Conversation`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inbound {
+    Request(Vec<u8>),
+    Response(SignResponse),
+}
+
+enum Incoming {
+    Request(UrDecoder),
+    Response(ResponseCollector),
+}
+
+pub struct Conversation {
+    role: Role,
+    request: Option<Vec<u8>>,
+    response: Option<SignResponse>,
+    outgoing: Option<UrEncoder>,
+    incoming: Incoming,
+}
+
+impl Conversation {
+    /// Requester side: sends `payload` and waits for its response.
+    pub fn requester(payload: &[u8]) -> Result<Self, QuantusUrError> {
+        Ok(Conversation {
+            role: Role::Requester,
+            request: Some(payload.to_vec()),
+            response: None,
+            outgoing: Some(UrEncoder::new(payload)?),
+            incoming: Incoming::Response(ResponseCollector::for_request(payload)),
+        })
+    }
+
+    /// Signer side: waits for a request before anything can be sent.
+    pub fn signer() -> Self {
+        Conversation {
+            role: Role::Signer,
+            request: None,
+            response: None,
+            outgoing: None,
+            incoming: Incoming::Request(UrDecoder::new()),
+        }
+    }
+
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    /// The request payload: known from the start on the requester, once
+    /// received on the signer.
+    pub fn request(&self) -> Option<&[u8]> {
+        self.request.as_deref()
+    }
+
+    /// The response: once received on the requester, once built on the signer.
+    pub fn response(&self) -> Option<&SignResponse> {
+        self.response.as_ref()
+    }
+
+    /// Whether the expected inbound message has arrived.
+    pub fn received(&self) -> bool {
+        match self.role {
+            Role::Requester => self.response.is_some(),
+            Role::Signer => self.request.is_some(),
+        }
+    }
+
+    /// Next outgoing part to display.
+    pub fn next_part(&mut self) -> Result<String, QuantusUrError> {
+        match &mut self.outgoing {
+            Some(encoder) => encoder.next_part(),
+            None => Err(QuantusUrError::UrError(
+                "Nothing to send before a request is received".to_string(),
+            )),
+        }
+    }
+
+    /// Feeds a scanned part, returning the inbound message once complete.
+    ///
+    /// Parts of the wrong type for this side are rejected, as are corrupt
+    /// parts and parts of another message, without losing the parts scanned
+    /// so far. Once the inbound message has arrived further parts are
+    /// ignored.
+    pub fn receive(&mut self, part: &str) -> Result<Option<Inbound>, QuantusUrError> {
+        if self.received() {
+            return Ok(None);
+        }
+        match &mut self.incoming {
+            Incoming::Response(collector) => {
+                let response = collector.receive(part)?;
+                self.response = response.clone();
+                Ok(response.map(Inbound::Response))
+            }
+            Incoming::Request(decoder) => {
+                let parsed: UrPart = part.parse()?;
+                if parsed.ur_type() != UR_TYPE {
+                    return Err(QuantusUrError::UrError(
+                        "Scanned part is not a sign request".to_string(),
+                    ));
+                }
+                decoder.receive_part(&parsed)?;
+                if !decoder.is_complete() {
+                    return Ok(None);
+                }
+                let payload = decoder.message()?;
+                self.request = Some(payload.clone());
+                Ok(Some(Inbound::Request(payload)))
+            }
+        }
+    }
+
+    /// Signer side: answers the received request with `signature` and
+    /// starts sending the response.
+    pub fn respond(&mut self, signature: Vec<u8>) -> Result<&SignResponse, QuantusUrError> {
+        let request = match (self.role, &self.request) {
+            (Role::Signer, Some(request)) => request,
+            _ => {
+                return Err(QuantusUrError::UrError(
+                    "Only a signer holding a request can respond".to_string(),
+                ))
+            }
+        };
+        let response = SignResponse::for_request(request, signature);
+        self.outgoing = Some(response.encoder()?);
+        Ok(self.response.insert(response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn send(from: &mut Conversation, to: &mut Conversation) -> Option<Inbound> {
+        for _ in 0..100 {
+            let part = from.next_part().expect("Part generation failed");
+            if let Some(inbound) = to.receive(&part).expect("Receive failed") {
+                return Some(inbound);
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn test_both_sides() {
+        let payload: Vec<u8> = (0..600u32).map(|i| (i % 251) as u8).collect();
+        let mut wallet = Conversation::requester(&payload).expect("Creation failed");
+        let mut signer = Conversation::signer();
+        assert!(signer.next_part().is_err());
+
+        assert_eq!(
+            send(&mut wallet, &mut signer),
+            Some(Inbound::Request(payload.clone()))
+        );
+        signer
+            .respond(alloc::vec![9; 4627])
+            .expect("Respond failed");
+
+        match send(&mut signer, &mut wallet) {
+            Some(Inbound::Response(response)) => assert!(response.matches_request(&payload)),
+            other => panic!("Unexpected inbound message: {:?}", other),
+        }
+        assert_eq!(wallet.response(), signer.response());
+    }
+
+    #[test]
+    fn test_bad_frames_keep_scan() {
+        let payload: Vec<u8> = (0..600u32).map(|i| (i % 251) as u8).collect();
+        let mut wallet = Conversation::requester(&payload).expect("Creation failed");
+        let mut stale = Conversation::requester(&payload[1..]).expect("Creation failed");
+        let mut signer = Conversation::signer();
+
+        let first = wallet.next_part().expect("Part generation failed");
+        assert_eq!(signer.receive(&first).expect("Receive failed"), None);
+        let mut corrupt = wallet.next_part().expect("Part generation failed");
+        let last = corrupt.pop().expect("Empty part");
+        corrupt.push(if last == 'A' { 'B' } else { 'A' });
+        assert!(signer.receive(&corrupt).is_err());
+        let foreign = stale.next_part().expect("Part generation failed");
+        assert!(signer.receive(&foreign).is_err());
+
+        assert_eq!(
+            send(&mut wallet, &mut signer),
+            Some(Inbound::Request(payload))
+        );
+    }
+
+    #[test]
+    fn test_wrong_type_rejected() {
+        let mut wallet = Conversation::requester(b"request").expect("Creation failed");
+        let mut other = Conversation::requester(b"request").expect("Creation failed");
+        let request_part = other.next_part().expect("Part generation failed");
+        assert!(wallet.receive(&request_part).is_err());
+
+        let mut signer = Conversation::signer();
+        let response = SignResponse::for_request(b"request", alloc::vec![1; 64]);
+        let parts = response.to_parts().expect("Encoding failed");
+        assert!(signer.receive(&parts[0]).is_err());
+    }
+}
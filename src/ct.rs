@@ -0,0 +1,23 @@
+//! Constant-time equality for secrets and authenticators.
+
+use subtle::ConstantTimeEq;
+
+/// Compares two byte strings in time independent of where they differ, for
+/// signatures, public keys and hashes checked on signer firmware. Only the
+/// lengths are compared in variable time.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && bool::from(a.ct_eq(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"signature", b"signature"));
+        assert!(!constant_time_eq(b"signature", b"signaturf"));
+        assert!(!constant_time_eq(b"signature", b"sig"));
+        assert!(constant_time_eq(b"", b""));
+    }
+}
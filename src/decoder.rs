@@ -0,0 +1,1231 @@
+use alloc::collections::{BTreeSet, VecDeque};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use crate::base45::{is_base45_part, to_ur_text};
+use crate::cbor::cbor_error;
+use crate::chunked::{ChunkSet, HexChunk};
+use crate::clock::Clock;
+#[cfg(feature = "metrics")]
+use crate::metrics::SharedMetrics;
+use crate::part::{parse_part, sanitize_part, ParsedPart, PartHeader, UrPart};
+#[cfg(feature = "raptorq")]
+use crate::raptor::{is_raptor_part, parse_raptor_part, RaptorSession};
+use crate::{
+    payload_from_cbor, tagged_payload_from_cbor, DecodeOptions, QuantusUrError, TaggedPayload,
+    UrFlavor, UR_TYPE,
+};
+
+/// Outcome of feeding one part to a [`UrDecoder`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DecodeEvent {
+    /// A new part was accepted; the message is not complete yet.
+    Progress { received: usize, expected: usize },
+    /// The part had already been received and added nothing.
+    Duplicate { sequence: u32 },
+    /// The message is complete and can be read with [`UrDecoder::message`].
+    Complete,
+    /// The scan was invalid and skipped, see
+    /// [`DecodeOptions::skip_invalid_parts`].
+    Skipped,
+}
+
+/// Consecutive duplicate parts after which a session is considered stalled,
+/// unless [`DecodeOptions::stall_threshold`] says otherwise.
+pub const DEFAULT_STALL_THRESHOLD: usize = 10;
+
+/// Why a session stopped making progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StallReason {
+    /// The same part keeps arriving: the sender shows a static frame.
+    StaticFrame,
+    /// Parts keep arriving but all were received before.
+    RepeatingParts,
+}
+
+impl StallReason {
+    /// Suggested prompt for the user.
+    pub fn remediation(&self) -> &'static str {
+        match self {
+            StallReason::StaticFrame => {
+                "The code is not animating. Restart the animation on the sending device."
+            }
+            StallReason::RepeatingParts => {
+                "Only already scanned frames are being read. Hold the camera steady \
+                 closer to the screen, or lower the animation density."
+            }
+        }
+    }
+}
+
+/// Overall state of a decode session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DecodeStatus {
+    /// No part received yet.
+    Waiting,
+    Receiving,
+    Stalled(StallReason),
+    /// The deadline passed before the message completed.
+    TimedOut,
+    Complete,
+}
+
+/// Animation pace the scanner asks of the display, for setups with a
+/// back-channel such as BLE alongside the QR code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PaceHint {
+    /// Keep the current frame rate.
+    Hold,
+    /// Frames are being missed: sequence numbers jump between scans.
+    SlowDown,
+    /// The same frame is read several times in a row.
+    SpeedUp,
+    /// Only parts received earlier arrive; restart the loop, e.g. with
+    /// [`crate::UrEncoder::restart_from_minimal_set`].
+    Reloop,
+}
+
+/// Frames considered by [`UrDecoder::pace_hint`].
+const PACE_WINDOW: usize = 16;
+/// Frames needed before a hint other than [`PaceHint::Hold`] is given.
+const PACE_MIN_FRAMES: usize = 8;
+
+#[derive(Debug, Clone, Copy)]
+enum PaceSample {
+    /// New part, after this many unseen sequence numbers.
+    New { skipped: u32 },
+    /// Same part as the previous scan.
+    Repeat,
+    /// A part received earlier in the session.
+    Old,
+}
+
+/// Summary of a decode session, e.g. as reported when it times out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DecodeStats {
+    pub received_parts: usize,
+    pub expected_parts: Option<usize>,
+    pub skipped_parts: usize,
+}
+
+/// Scans recorded in [`SessionReport::arrival_order`]; later ones are only
+/// counted.
+pub const MAX_REPORTED_ARRIVALS: usize = 4096;
+
+/// What a decode session saw, for analyzing real-world scan performance,
+/// e.g. from user-submitted diagnostics.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SessionReport {
+    pub complete: bool,
+    pub expected_parts: Option<usize>,
+    /// Sequence numbers of the distinct parts received, ascending.
+    pub used_sequences: Vec<u32>,
+    /// Sequence number of every accepted scan in arrival order, repeats
+    /// included, up to [`MAX_REPORTED_ARRIVALS`] entries.
+    pub arrival_order: Vec<u32>,
+    /// Scans of parts received before.
+    pub duplicate_frames: usize,
+    /// Invalid scans skipped, see [`DecodeOptions::skip_invalid_parts`].
+    pub skipped_frames: usize,
+    /// See [`UrDecoder::scan_duration`].
+    pub scan_duration: Option<u64>,
+}
+
+impl SessionReport {
+    /// Scans that added nothing to the message.
+    pub fn wasted_frames(&self) -> usize {
+        self.duplicate_frames + self.skipped_frames
+    }
+}
+
+/// Frame rate and loss of a session, measured from scan times.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScanTiming {
+    /// Time from the first to the latest scan.
+    pub elapsed: Duration,
+    /// Scans delivered, including duplicates and skipped ones.
+    pub scans: usize,
+    /// Scans per second delivered by the camera pipeline.
+    pub scan_rate: f32,
+    /// Estimated frames per second shown by the sender: new parts plus the
+    /// sequence numbers skipped between them.
+    pub display_rate: f32,
+    /// Estimated fraction of shown frames the camera missed.
+    pub loss_rate: f32,
+}
+
+/// Callbacks for callback-driven camera APIs. All methods default to no-ops.
+pub trait DecodeObserver {
+    fn on_progress(&mut self, _received: usize, _expected: usize) {}
+    fn on_duplicate(&mut self, _sequence: u32) {}
+    fn on_complete(&mut self) {}
+    fn on_skipped(&mut self) {}
+}
+
+impl DecodeEvent {
+    /// Calls the observer method matching this event.
+    pub fn notify(self, observer: &mut dyn DecodeObserver) {
+        match self {
+            DecodeEvent::Progress { received, expected } => {
+                observer.on_progress(received, expected)
+            }
+            DecodeEvent::Duplicate { sequence } => observer.on_duplicate(sequence),
+            DecodeEvent::Complete => observer.on_complete(),
+            DecodeEvent::Skipped => observer.on_skipped(),
+        }
+    }
+}
+
+/// Stateful decoder that accumulates scanned parts of a single UR message.
+///
+/// Parts are fed one at a time as they are scanned, so the fountain work is
+/// done incrementally instead of replaying the whole part list on every scan.
+/// Prefer it over calling [`crate::is_complete`] after each scan, which is
+/// quadratic over a session.
+#[derive(Default)]
+pub struct UrDecoder {
+    inner: ur::ur::Decoder,
+    ur_type: Option<String>,
+    single: Option<Vec<u8>>,
+    header: Option<PartHeader>,
+    seen: BTreeSet<u32>,
+    skipped: usize,
+    last_sequence: Option<u32>,
+    duplicate_streak: usize,
+    same_part_streak: usize,
+    recent: VecDeque<PaceSample>,
+    deadline: Option<u64>,
+    timed_out: bool,
+    /// Plain hex chunks, when the sender uses [`crate::EncodingMode::HexChunks`].
+    chunks: ChunkSet,
+    /// RaptorQ session, when the sender uses [`crate::EncodingMode::RaptorQ`].
+    #[cfg(feature = "raptorq")]
+    raptor: Option<RaptorSession>,
+    /// Sequence numbers in arrival order, for [`SessionReport`].
+    arrivals: Vec<u32>,
+    duplicates: usize,
+    /// Sequence numbers jumped over between consecutive new parts.
+    missed_frames: usize,
+    /// Instant of the first scan passed to [`UrDecoder::receive_scan`].
+    #[cfg(feature = "std")]
+    scan_epoch: Option<std::time::Instant>,
+    /// Times of the first and latest scan passed to [`UrDecoder::receive_at`].
+    first_scan_at: Option<u64>,
+    last_scan_at: Option<u64>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<SharedMetrics>,
+    options: DecodeOptions,
+}
+
+impl UrDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a decoder that aborts the session with
+    /// [`QuantusUrError::MemoryLimitExceeded`] once the estimated memory held by
+    /// the in-flight message would exceed `limit` bytes.
+    pub fn with_memory_limit(limit: usize) -> Self {
+        Self::with_options(DecodeOptions {
+            memory_limit: Some(limit),
+            ..DecodeOptions::default()
+        })
+    }
+
+    pub fn with_options(options: DecodeOptions) -> Self {
+        UrDecoder {
+            options,
+            ..Self::default()
+        }
+    }
+
+    /// Abandons the current message and starts a fresh session with the same
+    /// options, keeping the buffers already allocated where possible.
+    pub fn reset(&mut self) {
+        trace_event!(unique_parts = self.seen.len(), "decoder reset");
+        self.inner = ur::ur::Decoder::default();
+        self.ur_type = None;
+        self.single = None;
+        self.header = None;
+        self.seen.clear();
+        self.skipped = 0;
+        self.last_sequence = None;
+        self.duplicate_streak = 0;
+        self.same_part_streak = 0;
+        self.recent.clear();
+        self.deadline = None;
+        self.timed_out = false;
+        self.chunks = ChunkSet::default();
+        #[cfg(feature = "raptorq")]
+        {
+            self.raptor = None;
+        }
+        self.arrivals.clear();
+        self.duplicates = 0;
+        self.missed_frames = 0;
+        #[cfg(feature = "std")]
+        {
+            self.scan_epoch = None;
+        }
+        self.first_scan_at = None;
+        self.last_scan_at = None;
+    }
+
+    /// Reports parts and session outcomes to `metrics` from now on.
+    #[cfg(feature = "metrics")]
+    pub fn set_metrics(&mut self, metrics: SharedMetrics) {
+        self.metrics = Some(metrics);
+    }
+
+    /// Time between the first and latest scan passed to
+    /// [`UrDecoder::receive_at`], in its unit.
+    pub fn scan_duration(&self) -> Option<u64> {
+        Some(self.last_scan_at?.saturating_sub(self.first_scan_at?))
+    }
+
+    #[cfg(feature = "metrics")]
+    fn report_metrics(&self, result: &Result<DecodeEvent, QuantusUrError>) {
+        let Some(metrics) = &self.metrics else {
+            return;
+        };
+        match result {
+            Ok(event) => {
+                metrics.part_received(*event);
+                if *event == DecodeEvent::Complete {
+                    metrics.session_completed(self.scan_duration());
+                }
+            }
+            Err(e) => metrics.part_rejected(e.kind()),
+        }
+    }
+
+    #[cfg(not(feature = "metrics"))]
+    fn report_metrics(&self, _result: &Result<DecodeEvent, QuantusUrError>) {}
+
+    /// Bounds the session: once [`UrDecoder::check_deadline`] or
+    /// [`UrDecoder::receive_at`] sees `now >= deadline` before the message
+    /// completed, the session fails with [`QuantusUrError::TimedOut`]. Time is
+    /// in whatever unit the caller passes as `now`.
+    pub fn set_deadline(&mut self, deadline: Option<u64>) {
+        self.deadline = deadline;
+    }
+
+    pub fn deadline(&self) -> Option<u64> {
+        self.deadline
+    }
+
+    /// Times the session out if `now` reached the deadline.
+    pub fn check_deadline(&mut self, now: u64) -> Result<(), QuantusUrError> {
+        if !self.is_complete() && self.deadline.map_or(false, |at| now >= at) {
+            if !self.timed_out {
+                trace_event!(unique_parts = self.seen.len(), "decode session timed out");
+                #[cfg(feature = "metrics")]
+                if let Some(metrics) = &self.metrics {
+                    metrics.session_failed("timed_out", self.scan_duration());
+                }
+            }
+            self.timed_out = true;
+        }
+        self.ensure_open()
+    }
+
+    /// Like [`UrDecoder::receive_event`], first checking the deadline. The
+    /// first scan starts the [`DecodeOptions::max_scan_duration`] clock.
+    pub fn receive_at(&mut self, part: &str, now: u64) -> Result<DecodeEvent, QuantusUrError> {
+        self.first_scan_at.get_or_insert(now);
+        self.last_scan_at = Some(now);
+        if self.deadline.is_none() {
+            self.deadline = self
+                .options
+                .max_scan_duration
+                .map(|duration| now.saturating_add(duration));
+        }
+        self.check_deadline(now)?;
+        self.receive_event(part)
+    }
+
+    /// Like [`UrDecoder::receive_at`], reading the time from `clock`, so
+    /// deadlines and [`DecodeOptions::max_scan_duration`] are in milliseconds.
+    pub fn receive_with_clock(
+        &mut self,
+        part: &str,
+        clock: &impl Clock,
+    ) -> Result<DecodeEvent, QuantusUrError> {
+        self.receive_at(part, clock.now())
+    }
+
+    /// Like [`UrDecoder::receive_at`] for a timestamped scan event, e.g. from
+    /// a camera callback. Times count in milliseconds from the first scan, so
+    /// deadlines, [`UrDecoder::scan_timing`] and the [`SessionReport`] see
+    /// when each frame was actually read.
+    #[cfg(feature = "std")]
+    pub fn receive_scan(
+        &mut self,
+        at: std::time::Instant,
+        part: &str,
+    ) -> Result<DecodeEvent, QuantusUrError> {
+        let epoch = *self.scan_epoch.get_or_insert(at);
+        let now = at.saturating_duration_since(epoch).as_millis();
+        self.receive_at(part, u64::try_from(now).unwrap_or(u64::MAX))
+    }
+
+    /// Effective frame rate and loss, once scans with different times were
+    /// received. Assumes the times given to [`UrDecoder::receive_at`] are in
+    /// milliseconds, as with [`UrDecoder::receive_scan`] and
+    /// [`UrDecoder::receive_with_clock`].
+    pub fn scan_timing(&self) -> Option<ScanTiming> {
+        let elapsed = Duration::from_millis(self.scan_duration().filter(|&ms| ms > 0)?);
+        let seconds = elapsed.as_secs_f32();
+        let scans = self.seen.len() + self.duplicates + self.skipped;
+        let shown = self.seen.len() + self.missed_frames;
+        Some(ScanTiming {
+            elapsed,
+            scans,
+            scan_rate: scans as f32 / seconds,
+            display_rate: shown as f32 / seconds,
+            loss_rate: if shown == 0 {
+                0.0
+            } else {
+                self.missed_frames as f32 / shown as f32
+            },
+        })
+    }
+
+    /// Parts received, expected and skipped so far.
+    pub fn stats(&self) -> DecodeStats {
+        DecodeStats {
+            received_parts: self.received_parts(),
+            expected_parts: self.expected_parts(),
+            skipped_parts: self.skipped,
+        }
+    }
+
+    /// Which parts were used, in what order they arrived and how many scans
+    /// were wasted so far.
+    pub fn session_report(&self) -> SessionReport {
+        SessionReport {
+            complete: self.is_complete(),
+            expected_parts: self.expected_parts(),
+            used_sequences: self.seen.iter().copied().collect(),
+            arrival_order: self.arrivals.clone(),
+            duplicate_frames: self.duplicates,
+            skipped_frames: self.skipped,
+            scan_duration: self.scan_duration(),
+        }
+    }
+
+    fn ensure_open(&self) -> Result<(), QuantusUrError> {
+        if self.timed_out {
+            return Err(QuantusUrError::TimedOut(self.stats()));
+        }
+        Ok(())
+    }
+
+    pub fn memory_limit(&self) -> Option<usize> {
+        self.options.memory_limit
+    }
+
+    /// Estimated bytes held by the session: the reassembly buffer for the
+    /// message plus one fragment for every distinct part received.
+    pub fn memory_usage(&self) -> usize {
+        if let Some(single) = &self.single {
+            return single.len();
+        }
+        match &self.header {
+            Some(header) => header
+                .message_length
+                .saturating_add(self.seen.len().saturating_mul(header.fragment_length)),
+            None => {
+                #[cfg(feature = "raptorq")]
+                if let Some(session) = &self.raptor {
+                    return session.memory_usage(self.seen.len());
+                }
+                self.chunks.memory_usage()
+            }
+        }
+    }
+
+    /// UR type of the message, known once the first part has been received.
+    pub fn ur_type(&self) -> Option<&str> {
+        self.ur_type.as_deref()
+    }
+
+    /// Number of distinct fountain parts received so far.
+    pub fn received_parts(&self) -> usize {
+        if self.single.is_some() {
+            1
+        } else {
+            self.seen.len()
+        }
+    }
+
+    /// Number of fragments the message was split into, known once the first
+    /// part has been received. Lossy channels need more parts than this.
+    pub fn expected_parts(&self) -> Option<usize> {
+        if self.single.is_some() {
+            return Some(1);
+        }
+        if self.chunks.total() > 0 {
+            return Some(self.chunks.total());
+        }
+        #[cfg(feature = "raptorq")]
+        if let Some(session) = &self.raptor {
+            return Some(session.source_count() as usize);
+        }
+        self.header.map(|h| h.sequence_count as usize)
+    }
+
+    /// Waiting, receiving, stalled or complete. A session is stalled once the
+    /// stall threshold of consecutive parts added nothing new.
+    pub fn status(&self) -> DecodeStatus {
+        let threshold = self
+            .options
+            .stall_threshold
+            .unwrap_or(DEFAULT_STALL_THRESHOLD);
+        if self.is_complete() {
+            DecodeStatus::Complete
+        } else if self.timed_out {
+            DecodeStatus::TimedOut
+        } else if self.duplicate_streak >= threshold {
+            DecodeStatus::Stalled(if self.same_part_streak >= threshold {
+                StallReason::StaticFrame
+            } else {
+                StallReason::RepeatingParts
+            })
+        } else if self.received_parts() > 0 {
+            DecodeStatus::Receiving
+        } else {
+            DecodeStatus::Waiting
+        }
+    }
+
+    /// Recommended display pace from the ratio of useful to duplicate frames
+    /// among the last scans. [`PaceHint::Hold`] until enough frames were seen.
+    pub fn pace_hint(&self) -> PaceHint {
+        if self.is_complete() || self.recent.len() < PACE_MIN_FRAMES {
+            return PaceHint::Hold;
+        }
+        let (mut new, mut skipped, mut repeats, mut old) = (0u32, 0u32, 0usize, 0usize);
+        for sample in &self.recent {
+            match *sample {
+                PaceSample::New { skipped: gap } => {
+                    new += 1;
+                    skipped = skipped.saturating_add(gap);
+                }
+                PaceSample::Repeat => repeats += 1,
+                PaceSample::Old => old += 1,
+            }
+        }
+        let frames = self.recent.len();
+        if old * 2 >= frames {
+            PaceHint::Reloop
+        } else if repeats * 2 >= frames {
+            PaceHint::SpeedUp
+        } else if u64::from(skipped) * 10 >= u64::from(new.saturating_add(skipped)) * 3 {
+            // More than ~30% of the frames shown were never scanned
+            PaceHint::SlowDown
+        } else {
+            PaceHint::Hold
+        }
+    }
+
+    /// Number of scans skipped as invalid in lenient mode.
+    pub fn skipped_parts(&self) -> usize {
+        self.skipped
+    }
+
+    /// Sequence numbers of the pure fragments (`1..=expected`) not received yet.
+    /// Mixed fountain parts can make up for them, so this is a hint for the
+    /// sender rather than a hard requirement.
+    pub fn missing_parts(&self) -> Vec<u32> {
+        match (&self.single, &self.header) {
+            (None, Some(header)) => (1..=header.sequence_count)
+                .filter(|sequence| !self.seen.contains(sequence))
+                .collect(),
+            _ => self.chunks.missing(),
+        }
+    }
+
+    pub fn receive(&mut self, part: &str) -> Result<(), QuantusUrError> {
+        self.receive_event(part).map(|_| ())
+    }
+
+    /// Like [`UrDecoder::receive`], reporting what the part contributed.
+    pub fn receive_event(&mut self, part: &str) -> Result<DecodeEvent, QuantusUrError> {
+        if self.is_complete() {
+            return Ok(DecodeEvent::Complete);
+        }
+        self.ensure_open()?;
+        let result = self.receive_sanitized(sanitize_part(part));
+        self.skip_invalid(result)
+    }
+
+    /// Dispatches a part on its scheme: UR, base45, RaptorQ or hex chunk.
+    fn receive_sanitized(&mut self, part: &str) -> Result<DecodeEvent, QuantusUrError> {
+        if HexChunk::is_chunk(part) {
+            return self.receive_chunk(HexChunk::parse(part)?);
+        }
+        if is_base45_part(part) {
+            let text = to_ur_text(part)?;
+            return self.receive_parsed(parse_part(&text)?, &text);
+        }
+        #[cfg(feature = "raptorq")]
+        if is_raptor_part(part) {
+            return self.receive_raptor(part);
+        }
+        self.check_casing(part)?;
+        self.receive_parsed(parse_part(part)?, part)
+    }
+
+    #[cfg(feature = "raptorq")]
+    fn receive_raptor(&mut self, part: &str) -> Result<DecodeEvent, QuantusUrError> {
+        let part = parse_raptor_part(part)?;
+        if let Some(ur_type) = &self.ur_type {
+            if *ur_type != part.ur_type {
+                return Err(QuantusUrError::UrError(
+                    "Part belongs to a different UR type".to_string(),
+                ));
+            }
+        }
+        let opened = self.raptor.is_none();
+        let mut session = match self.raptor.take() {
+            Some(session) => session,
+            None if self.received_parts() > 0 => {
+                return Err(QuantusUrError::UrError(
+                    "RaptorQ part received during another session".to_string(),
+                ))
+            }
+            None => RaptorSession::open(&part, self.options.memory_limit)?,
+        };
+        let (sequence, ur_type) = (part.sequence, part.ur_type.clone());
+        let result = if self.seen.contains(&sequence) {
+            Ok(())
+        } else {
+            self.check_memory(session.memory_usage(self.seen.len() + 1))
+        }
+        .and_then(|()| session.receive(part));
+        let expected = session.source_count() as usize;
+        // A session opened by a malformed packet is dropped with it
+        if result.is_ok() || !opened {
+            self.raptor = Some(session);
+        }
+        let complete = result?;
+        self.ur_type = Some(ur_type);
+        let is_new = self.seen.insert(sequence);
+        self.record_scan(sequence, is_new);
+        trace_event!(sequence, duplicate = !is_new, "RaptorQ packet received");
+        if complete {
+            Ok(DecodeEvent::Complete)
+        } else if !is_new {
+            Ok(DecodeEvent::Duplicate { sequence })
+        } else {
+            Ok(DecodeEvent::Progress {
+                received: self.seen.len(),
+                expected,
+            })
+        }
+    }
+
+    #[cfg(feature = "raptorq")]
+    fn raptor_active(&self) -> bool {
+        self.raptor.is_some()
+    }
+
+    #[cfg(not(feature = "raptorq"))]
+    fn raptor_active(&self) -> bool {
+        false
+    }
+
+    #[cfg(feature = "raptorq")]
+    fn raptor_message(&self) -> Option<&[u8]> {
+        self.raptor.as_ref().and_then(RaptorSession::message)
+    }
+
+    #[cfg(not(feature = "raptorq"))]
+    fn raptor_message(&self) -> Option<&[u8]> {
+        None
+    }
+
+    /// Receives a part and reports the resulting event to `observer`.
+    pub fn receive_observed(
+        &mut self,
+        part: &str,
+        observer: &mut dyn DecodeObserver,
+    ) -> Result<(), QuantusUrError> {
+        self.receive_event(part)?.notify(observer);
+        Ok(())
+    }
+
+    /// Like [`UrDecoder::receive`] for a part the caller already parsed.
+    pub(crate) fn receive_text(
+        &mut self,
+        parsed: ParsedPart,
+        part: &str,
+    ) -> Result<(), QuantusUrError> {
+        if self.is_complete() {
+            return Ok(());
+        }
+        self.ensure_open()?;
+        let result = self
+            .check_casing(part)
+            .and_then(|()| self.receive_parsed(parsed, part));
+        self.skip_invalid(result).map(|_| ())
+    }
+
+    /// Receives an already validated part, skipping the parsing step.
+    pub fn receive_part(&mut self, part: &UrPart) -> Result<(), QuantusUrError> {
+        if self.is_complete() {
+            return Ok(());
+        }
+        self.ensure_open()?;
+        let result = self.receive_parsed(part.parsed().clone(), part.as_str());
+        self.skip_invalid(result).map(|_| ())
+    }
+
+    /// Applies [`DecodeOptions::skip_invalid_parts`] to the outcome of a
+    /// scan and reports it to the metrics.
+    fn skip_invalid(
+        &mut self,
+        result: Result<DecodeEvent, QuantusUrError>,
+    ) -> Result<DecodeEvent, QuantusUrError> {
+        let result = match result {
+            Err(e @ QuantusUrError::MemoryLimitExceeded { .. }) => Err(e),
+            Err(_e) if self.options.skip_invalid_parts => {
+                self.skipped += 1;
+                trace_event!(error = %_e, skipped = self.skipped, "invalid part skipped");
+                Ok(DecodeEvent::Skipped)
+            }
+            result => result,
+        };
+        self.report_metrics(&result);
+        result
+    }
+
+    fn receive_chunk(&mut self, chunk: HexChunk) -> Result<DecodeEvent, QuantusUrError> {
+        if self.single.is_some() || self.header.is_some() || self.raptor_active() {
+            return Err(QuantusUrError::UrError(
+                "Hex chunk received during a UR session".to_string(),
+            ));
+        }
+        self.check_memory(self.chunks.memory_usage().saturating_add(chunk.data.len()))?;
+        let (index, total) = (chunk.index, chunk.total as usize);
+        let is_new = self.chunks.insert(chunk)?;
+        self.ur_type = Some(UR_TYPE.to_string());
+        self.seen.insert(index);
+        self.record_scan(index, is_new);
+        trace_event!(index, total, duplicate = !is_new, "hex chunk received");
+        if self.chunks.is_complete() {
+            Ok(DecodeEvent::Complete)
+        } else if !is_new {
+            Ok(DecodeEvent::Duplicate { sequence: index })
+        } else {
+            Ok(DecodeEvent::Progress {
+                received: self.seen.len(),
+                expected: total,
+            })
+        }
+    }
+
+    /// Updates the stall streaks and pace samples for a scan of `sequence`.
+    fn record_scan(&mut self, sequence: u32, is_new: bool) {
+        if self.arrivals.len() < MAX_REPORTED_ARRIVALS {
+            self.arrivals.push(sequence);
+        }
+        if is_new {
+            self.duplicate_streak = 0;
+            self.same_part_streak = 0;
+        } else {
+            self.duplicates += 1;
+            self.duplicate_streak += 1;
+            if self.last_sequence == Some(sequence) {
+                self.same_part_streak += 1;
+            } else {
+                self.same_part_streak = 1;
+            }
+        }
+        let sample = match self.last_sequence {
+            Some(last) if last == sequence => PaceSample::Repeat,
+            _ if !is_new => PaceSample::Old,
+            Some(last) if sequence > last => PaceSample::New {
+                skipped: sequence - last - 1,
+            },
+            _ => PaceSample::New { skipped: 0 },
+        };
+        if let PaceSample::New { skipped } = sample {
+            self.missed_frames = self.missed_frames.saturating_add(skipped as usize);
+        }
+        if self.recent.len() == PACE_WINDOW {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(sample);
+        self.last_sequence = Some(sequence);
+    }
+
+    fn receive_parsed(
+        &mut self,
+        parsed: ParsedPart,
+        part: &str,
+    ) -> Result<DecodeEvent, QuantusUrError> {
+        if self.chunks.total() > 0 || self.raptor_active() {
+            return Err(QuantusUrError::UrError(
+                "UR part received during a hex chunk or RaptorQ session".to_string(),
+            ));
+        }
+        if let Some(ur_type) = &self.ur_type {
+            if *ur_type != parsed.ur_type {
+                return Err(QuantusUrError::UrError(
+                    "Part belongs to a different UR type".to_string(),
+                ));
+            }
+        }
+
+        let header = match parsed.header {
+            Some(header) => header,
+            None => {
+                if self.header.is_some() {
+                    return Err(QuantusUrError::UrError(
+                        "Single-part UR received during a multi-part session".to_string(),
+                    ));
+                }
+                self.check_memory(parsed.body.len())?;
+                trace_event!(ur_type = %parsed.ur_type, "single-part message complete");
+                self.ur_type = Some(parsed.ur_type);
+                self.single = Some(parsed.body);
+                return Ok(DecodeEvent::Complete);
+            }
+        };
+        if let Some(session) = &self.header {
+            if session.sequence_count != header.sequence_count {
+                return Err(QuantusUrError::InconsistentSequence {
+                    expected: session.sequence_count,
+                    actual: header.sequence_count,
+                });
+            }
+            if session.message_length != header.message_length
+                || session.checksum != header.checksum
+                || session.fragment_length != header.fragment_length
+            {
+                return Err(QuantusUrError::UrError(
+                    "Part header does not match the session".to_string(),
+                ));
+            }
+        }
+
+        if !self.seen.contains(&header.sequence) {
+            let required = header
+                .message_length
+                .saturating_add((self.seen.len() + 1).saturating_mul(header.fragment_length));
+            self.check_memory(required)?;
+        }
+
+        let text = if parsed.minimal_body {
+            part.to_lowercase()
+        } else {
+            parsed.canonical_text()
+        };
+        self.inner
+            .receive(&text)
+            .map_err(|e| QuantusUrError::UrError(e.to_string()))?;
+        self.ur_type = Some(parsed.ur_type);
+        self.header = Some(header);
+        let is_new = self.seen.insert(header.sequence);
+        self.record_scan(header.sequence, is_new);
+        trace_event!(
+            sequence = header.sequence,
+            sequence_count = header.sequence_count,
+            duplicate = !is_new,
+            unique_parts = self.seen.len(),
+            "part received"
+        );
+        if self.inner.complete() {
+            trace_event!(
+                unique_parts = self.seen.len(),
+                "multi-part message complete"
+            );
+            Ok(DecodeEvent::Complete)
+        } else if !is_new {
+            Ok(DecodeEvent::Duplicate {
+                sequence: header.sequence,
+            })
+        } else {
+            Ok(DecodeEvent::Progress {
+                received: self.seen.len(),
+                expected: header.sequence_count as usize,
+            })
+        }
+    }
+
+    fn check_casing(&self, part: &str) -> Result<(), QuantusUrError> {
+        if self.options.flavor == UrFlavor::BcUr
+            && part.bytes().any(|b| b.is_ascii_lowercase())
+            && part.bytes().any(|b| b.is_ascii_uppercase())
+        {
+            return Err(QuantusUrError::UrError(
+                "Mixed-case part rejected in strict BC-UR mode".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn check_memory(&self, required: usize) -> Result<(), QuantusUrError> {
+        match self.options.memory_limit {
+            Some(limit) if required > limit => {
+                Err(QuantusUrError::MemoryLimitExceeded { limit, required })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.single.is_some()
+            || self.inner.complete()
+            || self.chunks.is_complete()
+            || self.raptor_message().is_some()
+    }
+
+    /// Raw CBOR message, once complete.
+    pub(crate) fn cbor_message(&self) -> Result<Vec<u8>, QuantusUrError> {
+        if let Some(single) = &self.single {
+            return Ok(single.clone());
+        }
+        if let Some(message) = self.raptor_message() {
+            return Ok(message.to_vec());
+        }
+        if let Some(payload) = self.chunks.payload() {
+            let mut e = minicbor::Encoder::new(Vec::new());
+            e.bytes(&payload).map_err(cbor_error)?;
+            return Ok(e.into_writer());
+        }
+        if !self.inner.complete() {
+            return Err(QuantusUrError::Incomplete);
+        }
+        self.inner
+            .message()
+            .map_err(|e| QuantusUrError::UrError(e.to_string()))?
+            .ok_or_else(|| QuantusUrError::UrError("No message".to_string()))
+    }
+
+    /// Decoded payload bytes, once complete.
+    pub fn message(&self) -> Result<Vec<u8>, QuantusUrError> {
+        payload_from_cbor(&self.cbor_message()?, &self.options)
+    }
+
+    /// Decoded payload together with the semantic tag in front of it, if any.
+    pub fn tagged_message(&self) -> Result<TaggedPayload, QuantusUrError> {
+        tagged_payload_from_cbor(&self.cbor_message()?, &self.options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{encode_bytes, UrEncoder};
+
+    fn large_payload() -> Vec<u8> {
+        (0..2000u32).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_streaming_roundtrip() {
+        let payload = large_payload();
+        let parts = encode_bytes(&payload).expect("Encoding failed");
+        let mut decoder = UrDecoder::new();
+        for part in &parts {
+            assert!(!decoder.is_complete());
+            decoder.receive(part).expect("Receive failed");
+        }
+        assert!(decoder.is_complete());
+        assert_eq!(decoder.received_parts(), parts.len());
+        assert_eq!(decoder.expected_parts(), Some(parts.len()));
+        assert_eq!(decoder.message().expect("Decoding failed"), payload);
+    }
+
+    #[test]
+    fn test_reset_starts_fresh_session() {
+        let parts = encode_bytes(&large_payload()).expect("Encoding failed");
+        let mut decoder = UrDecoder::with_memory_limit(64 * 1024);
+        decoder.receive(&parts[0]).expect("Receive failed");
+        decoder.reset();
+        assert_eq!(decoder.status(), DecodeStatus::Waiting);
+        assert_eq!(decoder.ur_type(), None);
+        assert_eq!(decoder.memory_limit(), Some(64 * 1024));
+
+        let other = encode_bytes(b"Hello, Quantus!").expect("Encoding failed");
+        decoder.receive(&other[0]).expect("Receive failed");
+        assert_eq!(
+            decoder.message().expect("Decoding failed"),
+            b"Hello, Quantus!"
+        );
+    }
+
+    #[test]
+    fn test_scanner_noise_stripped() {
+        let payload = large_payload();
+        let parts = encode_bytes(&payload).expect("Encoding failed");
+        let mut decoder = UrDecoder::new();
+        for part in &parts {
+            decoder
+                .receive(&alloc::format!("\u{feff}{}\n", part))
+                .expect("Receive failed");
+        }
+        assert_eq!(decoder.message().expect("Decoding failed"), payload);
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        progress: usize,
+        duplicates: Vec<u32>,
+        completed: bool,
+    }
+
+    impl DecodeObserver for Recorder {
+        fn on_progress(&mut self, received: usize, _expected: usize) {
+            self.progress = received;
+        }
+        fn on_duplicate(&mut self, sequence: u32) {
+            self.duplicates.push(sequence);
+        }
+        fn on_complete(&mut self) {
+            self.completed = true;
+        }
+    }
+
+    #[test]
+    fn test_decode_events() {
+        let parts = encode_bytes(&large_payload()).expect("Encoding failed");
+        let mut decoder = UrDecoder::new();
+        assert_eq!(
+            decoder.receive_event(&parts[0]).expect("Receive failed"),
+            DecodeEvent::Progress {
+                received: 1,
+                expected: parts.len()
+            }
+        );
+        assert_eq!(
+            decoder.receive_event(&parts[0]).expect("Receive failed"),
+            DecodeEvent::Duplicate { sequence: 1 }
+        );
+
+        let mut recorder = Recorder::default();
+        for part in &parts[1..] {
+            decoder
+                .receive_observed(part, &mut recorder)
+                .expect("Receive failed");
+        }
+        assert_eq!(recorder.progress, parts.len() - 1);
+        assert!(recorder.duplicates.is_empty());
+        assert!(recorder.completed);
+    }
+
+    #[test]
+    fn test_skip_invalid_parts() {
+        let payload = large_payload();
+        let parts = encode_bytes(&payload).expect("Encoding failed");
+        let other = crate::response::SignResponse::for_request(b"x", alloc::vec![1; 8])
+            .to_parts()
+            .expect("Encoding failed");
+        let mut decoder = UrDecoder::with_options(DecodeOptions {
+            skip_invalid_parts: true,
+            ..DecodeOptions::default()
+        });
+        decoder.receive(&parts[0]).expect("Receive failed");
+        let truncated = &parts[1][..parts[1].len() / 2];
+        for bad in [truncated, "hello", other[0].as_str()] {
+            assert_eq!(
+                decoder.receive_event(bad).expect("Receive failed"),
+                DecodeEvent::Skipped
+            );
+        }
+        for part in &parts[1..] {
+            decoder.receive(part).expect("Receive failed");
+        }
+        assert_eq!(decoder.skipped_parts(), 3);
+        assert_eq!(decoder.message().expect("Decoding failed"), payload);
+        assert!(UrDecoder::new().receive("hello").is_err());
+    }
+
+    #[test]
+    fn test_stall_detection() {
+        let parts = encode_bytes(&large_payload()).expect("Encoding failed");
+        let mut decoder = UrDecoder::with_options(DecodeOptions {
+            stall_threshold: Some(3),
+            ..DecodeOptions::default()
+        });
+        assert_eq!(decoder.status(), DecodeStatus::Waiting);
+        decoder.receive(&parts[0]).expect("Receive failed");
+        decoder.receive(&parts[1]).expect("Receive failed");
+        for _ in 0..3 {
+            decoder.receive(&parts[1]).expect("Receive failed");
+        }
+        assert_eq!(
+            decoder.status(),
+            DecodeStatus::Stalled(StallReason::StaticFrame)
+        );
+
+        decoder.receive(&parts[2]).expect("Receive failed");
+        assert_eq!(decoder.status(), DecodeStatus::Receiving);
+        for part in parts[..3].iter().cycle().take(4) {
+            decoder.receive(part).expect("Receive failed");
+        }
+        assert_eq!(
+            decoder.status(),
+            DecodeStatus::Stalled(StallReason::RepeatingParts)
+        );
+    }
+
+    #[test]
+    fn test_pace_hints() {
+        let mut encoder = crate::UrEncoder::new(&large_payload()).expect("Encoder failed");
+        let parts: Vec<String> = (0..24).map(|_| encoder.next_part().unwrap()).collect();
+
+        let mut decoder = UrDecoder::new();
+        for part in parts.iter().take(3) {
+            for _ in 0..3 {
+                decoder.receive(part).expect("Receive failed");
+            }
+        }
+        assert_eq!(decoder.pace_hint(), PaceHint::SpeedUp);
+
+        let mut decoder = UrDecoder::new();
+        for part in parts.iter().step_by(3).take(7) {
+            decoder.receive(part).expect("Receive failed");
+            assert_eq!(decoder.pace_hint(), PaceHint::Hold);
+        }
+        decoder.receive(&parts[21]).expect("Receive failed");
+        assert_eq!(decoder.pace_hint(), PaceHint::SlowDown);
+    }
+
+    #[test]
+    fn test_inconsistent_sequence_rejected() {
+        let parts = encode_bytes(&large_payload()).expect("Encoding failed");
+        let other = crate::encode_bytes_with_options(
+            &large_payload(),
+            &crate::EncodeOptions {
+                max_fragment_length: 100,
+                ..crate::EncodeOptions::default()
+            },
+        )
+        .expect("Encoding failed");
+
+        let mut decoder = UrDecoder::new();
+        decoder.receive(&parts[0]).expect("Receive failed");
+        match decoder.receive(&other[1]) {
+            Err(QuantusUrError::InconsistentSequence { expected, actual }) => {
+                assert_eq!(expected as usize, parts.len());
+                assert_eq!(actual as usize, other.len());
+            }
+            result => panic!("Expected inconsistent sequence, got {:?}", result),
+        }
+        assert_eq!(decoder.received_parts(), 1);
+    }
+
+    #[test]
+    fn test_session_report() {
+        let payload = large_payload();
+        let mut encoder = UrEncoder::new(&payload).expect("Encoding failed");
+        let count = encoder.fragment_count();
+        let parts: Vec<String> = (0..count + 1)
+            .map(|_| encoder.next_part().expect("Encoding failed"))
+            .collect();
+        let mut decoder = UrDecoder::new();
+        for part in [&parts[1], &parts[1], &parts[count], &parts[0]] {
+            decoder.receive(part).expect("Receive failed");
+        }
+        let report = decoder.session_report();
+        assert_eq!(report.arrival_order, [2, 2, count as u32 + 1, 1]);
+        assert_eq!(report.used_sequences, [1, 2, count as u32 + 1]);
+        assert_eq!(report.duplicate_frames, 1);
+        assert_eq!(report.wasted_frames(), 1);
+        assert_eq!(report.expected_parts, Some(count));
+        assert!(!report.complete);
+
+        decoder.reset();
+        assert!(decoder.session_report().arrival_order.is_empty());
+    }
+
+    #[test]
+    fn test_scan_timing() {
+        let parts = encode_bytes(&large_payload()).expect("Encoding failed");
+        let mut decoder = UrDecoder::new();
+        decoder.receive_at(&parts[0], 0).expect("Receive failed");
+        assert_eq!(decoder.scan_timing(), None);
+        for (index, at) in [(2, 100), (3, 200), (3, 300)] {
+            decoder
+                .receive_at(&parts[index], at)
+                .expect("Receive failed");
+        }
+        let timing = decoder.scan_timing().expect("No timing");
+        assert_eq!(timing.elapsed, Duration::from_millis(300));
+        assert_eq!(timing.scans, 4);
+        assert!((timing.display_rate - 4.0 / 0.3).abs() < 1e-3);
+        assert!((timing.loss_rate - 0.25).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_deadline() {
+        let parts = encode_bytes(&large_payload()).expect("Encoding failed");
+        let mut decoder = UrDecoder::with_options(DecodeOptions {
+            max_scan_duration: Some(30),
+            ..DecodeOptions::default()
+        });
+        decoder.receive_at(&parts[0], 100).expect("Receive failed");
+        assert_eq!(decoder.deadline(), Some(130));
+        decoder.receive_at(&parts[1], 129).expect("Receive failed");
+
+        let expected = DecodeStats {
+            received_parts: 2,
+            expected_parts: Some(parts.len()),
+            skipped_parts: 0,
+        };
+        match decoder.receive_at(&parts[2], 130) {
+            Err(QuantusUrError::TimedOut(stats)) => assert_eq!(stats, expected),
+            result => panic!("Expected timeout, got {:?}", result),
+        }
+        assert_eq!(decoder.status(), DecodeStatus::TimedOut);
+        assert!(decoder.receive(&parts[2]).is_err());
+
+        decoder.reset();
+        assert!(decoder.receive(&parts[2]).is_ok());
+
+        let clock = crate::clock::ManualClock::new(5_000);
+        decoder.reset();
+        decoder
+            .receive_with_clock(&parts[0], &clock)
+            .expect("Receive failed");
+        clock.advance(30);
+        assert!(decoder.receive_with_clock(&parts[1], &clock).is_err());
+    }
+
+    #[test]
+    fn test_memory_limit_exceeded() {
+        let parts = encode_bytes(&large_payload()).expect("Encoding failed");
+        let mut decoder = UrDecoder::with_memory_limit(1024);
+        match decoder.receive(&parts[0]) {
+            Err(QuantusUrError::MemoryLimitExceeded { limit, required }) => {
+                assert_eq!(limit, 1024);
+                assert!(required > limit);
+            }
+            other => panic!("Expected memory limit error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_memory_limit_sufficient() {
+        let payload = large_payload();
+        let parts = encode_bytes(&payload).expect("Encoding failed");
+        let mut decoder = UrDecoder::with_memory_limit(8 * 1024);
+        for part in &parts {
+            decoder.receive(part).expect("Receive failed");
+        }
+        assert!(decoder.memory_usage() <= 8 * 1024);
+        assert_eq!(decoder.message().expect("Decoding failed"), payload);
+    }
+}
@@ -0,0 +1,397 @@
+//! The same payload encoded at several fragment lengths, so an app can show
+//! dense frames to capable scanners and sparse frames to weak ones.
+//!
+//! Every tier carries the same CBOR message, so its parts share the UR type
+//! and message checksum and differ only in their fragment count.
+//! [`MultiDensityDecoder`] keeps one session per tier and completes with
+//! whichever tier the scanner finishes first.
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::checksum::crc32;
+use crate::part::{parse_part, sanitize_part};
+use crate::{DecodeOptions, EncodeOptions, QuantusUrError, UrDecoder, UrEncoder};
+
+/// One density of a multi-density encoding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DensityTier {
+    pub fragment_length: usize,
+    /// Number of fragments, which also tags the tier's parts.
+    pub fragment_count: usize,
+    /// Minimal set of parts.
+    pub parts: Vec<String>,
+}
+
+/// Encodes `payload` once per fragment length, in the order given. Lengths
+/// that yield the same fragment count as an earlier one are skipped, since
+/// they would produce identical parts.
+pub fn encode_multi_density(
+    payload: &[u8],
+    fragment_lengths: &[usize],
+) -> Result<Vec<DensityTier>, QuantusUrError> {
+    let mut tiers: Vec<DensityTier> = Vec::with_capacity(fragment_lengths.len());
+    for &fragment_length in fragment_lengths {
+        let options = EncodeOptions {
+            max_fragment_length: fragment_length,
+            ..EncodeOptions::default()
+        };
+        let encoder = UrEncoder::with_options(payload, &options)?;
+        let fragment_count = encoder.fragment_count();
+        if tiers.iter().any(|t| t.fragment_count == fragment_count) {
+            continue;
+        }
+        tiers.push(DensityTier {
+            fragment_length,
+            fragment_count,
+            parts: encoder.into_minimal_parts().collect::<Result<_, _>>()?,
+        });
+    }
+    if tiers.is_empty() {
+        return Err(QuantusUrError::InvalidConfiguration(
+            "No fragment lengths given".to_string(),
+        ));
+    }
+    Ok(tiers)
+}
+
+/// When a [`DensityController`] moves to the next denser tier.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EscalationPolicy {
+    /// Frames to show in a tier before judging the scanner.
+    pub min_frames: usize,
+    /// Share of the frames shown in a tier that the counterpart must report
+    /// as received for the controller to escalate.
+    pub min_success_rate: f32,
+    /// Escalate after this many frames in a tier even without a report,
+    /// for setups where progress is not communicated back.
+    pub escalate_after_frames: Option<usize>,
+}
+
+impl Default for EscalationPolicy {
+    fn default() -> Self {
+        EscalationPolicy {
+            min_frames: 4,
+            min_success_rate: 0.75,
+            escalate_after_frames: None,
+        }
+    }
+}
+
+/// Animation that starts with the sparsest tier and moves to denser ones
+/// while the counterpart keeps up.
+///
+/// Switching tiers changes the fragment count, so the receiver should use a
+/// [`MultiDensityDecoder`]; parts received before a switch stay in their
+/// tier's session.
+pub struct DensityController {
+    tiers: Vec<(usize, UrEncoder)>,
+    tier: usize,
+    frames_in_tier: usize,
+    policy: EscalationPolicy,
+}
+
+impl DensityController {
+    /// Builds one tier per fragment length, ordered sparse to dense.
+    pub fn new(payload: &[u8], fragment_lengths: &[usize]) -> Result<Self, QuantusUrError> {
+        let mut lengths = fragment_lengths.to_vec();
+        lengths.sort_unstable();
+        let mut tiers: Vec<(usize, UrEncoder)> = Vec::with_capacity(lengths.len());
+        for fragment_length in lengths {
+            let options = EncodeOptions {
+                max_fragment_length: fragment_length,
+                ..EncodeOptions::default()
+            };
+            let encoder = UrEncoder::with_options(payload, &options)?;
+            if tiers
+                .iter()
+                .any(|(_, e)| e.fragment_count() == encoder.fragment_count())
+            {
+                continue;
+            }
+            tiers.push((fragment_length, encoder));
+        }
+        if tiers.is_empty() {
+            return Err(QuantusUrError::InvalidConfiguration(
+                "No fragment lengths given".to_string(),
+            ));
+        }
+        Ok(DensityController {
+            tiers,
+            tier: 0,
+            frames_in_tier: 0,
+            policy: EscalationPolicy::default(),
+        })
+    }
+
+    pub fn with_policy(mut self, policy: EscalationPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Index of the current tier, 0 being the sparsest.
+    pub fn tier(&self) -> usize {
+        self.tier
+    }
+
+    pub fn fragment_length(&self) -> usize {
+        self.tiers[self.tier].0
+    }
+
+    /// Next frame of the current tier.
+    pub fn next_part(&mut self) -> Result<String, QuantusUrError> {
+        if self
+            .policy
+            .escalate_after_frames
+            .map_or(false, |frames| self.frames_in_tier >= frames)
+        {
+            self.escalate();
+        }
+        self.frames_in_tier += 1;
+        self.tiers[self.tier].1.next_part()
+    }
+
+    /// Records how many parts of the current tier the counterpart reported
+    /// receiving, escalating if it keeps up with the animation. Returns
+    /// whether the tier changed.
+    pub fn report_progress(&mut self, received_parts: usize) -> bool {
+        if self.frames_in_tier < self.policy.min_frames {
+            return false;
+        }
+        let rate = received_parts as f32 / self.frames_in_tier as f32;
+        rate >= self.policy.min_success_rate && self.escalate()
+    }
+
+    fn escalate(&mut self) -> bool {
+        if self.tier + 1 >= self.tiers.len() {
+            return false;
+        }
+        self.tier += 1;
+        self.frames_in_tier = 0;
+        trace_event!(tier = self.tier, "density escalated");
+        true
+    }
+}
+
+/// Most tiers [`MultiDensityDecoder`] decodes at once.
+pub const MAX_DENSITY_TIERS: usize = 4;
+
+/// Decodes parts from any mix of density tiers of one message.
+///
+/// The UR type and message checksum of the first accepted part pin the
+/// message, so parts of a stale or foreign message are rejected rather than
+/// starting a tier of their own.
+#[derive(Default)]
+pub struct MultiDensityDecoder {
+    tiers: BTreeMap<u32, UrDecoder>,
+    /// UR type and message checksum every part must match.
+    message: Option<(String, u32)>,
+    completed: Option<u32>,
+    options: DecodeOptions,
+}
+
+impl MultiDensityDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_options(options: DecodeOptions) -> Self {
+        MultiDensityDecoder {
+            options,
+            ..Self::default()
+        }
+    }
+
+    /// Routes a scanned part to the session of its tier. Parts of another
+    /// message and parts that would open more than [`MAX_DENSITY_TIERS`]
+    /// tiers fail with [`QuantusUrError::UrError`].
+    pub fn receive(&mut self, part: &str) -> Result<(), QuantusUrError> {
+        if self.completed.is_some() {
+            return Ok(());
+        }
+        let part = sanitize_part(part);
+        let parsed = parse_part(part)?;
+        let fragment_count = parsed.header.map_or(1, |h| h.sequence_count);
+        let checksum = match parsed.header {
+            Some(header) => header.checksum,
+            None => crc32(&parsed.body),
+        };
+        match &self.message {
+            Some((ur_type, _)) if *ur_type != parsed.ur_type => {
+                return Err(QuantusUrError::UrError(
+                    "Part belongs to a different UR type".to_string(),
+                ));
+            }
+            Some((_, expected)) if *expected != checksum => {
+                return Err(QuantusUrError::UrError(
+                    "Part belongs to a different message".to_string(),
+                ));
+            }
+            _ => {}
+        }
+        if !self.tiers.contains_key(&fragment_count) && self.tiers.len() >= MAX_DENSITY_TIERS {
+            return Err(QuantusUrError::UrError(format!(
+                "More than {} density tiers",
+                MAX_DENSITY_TIERS
+            )));
+        }
+        let ur_type = parsed.ur_type.clone();
+        let options = &self.options;
+        let decoder = self
+            .tiers
+            .entry(fragment_count)
+            .or_insert_with(|| UrDecoder::with_options(options.clone()));
+        if let Err(e) = decoder.receive_text(parsed, part) {
+            // Do not let rejected parts hold a tier open
+            if decoder.received_parts() == 0 {
+                self.tiers.remove(&fragment_count);
+            }
+            return Err(e);
+        }
+        if decoder.is_complete() {
+            self.completed = Some(fragment_count);
+        }
+        if self.message.is_none() {
+            self.message = Some((ur_type, checksum));
+        }
+        Ok(())
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.completed.is_some()
+    }
+
+    /// Fragment count of the tier that completed first.
+    pub fn completed_tier(&self) -> Option<usize> {
+        self.completed.map(|count| count as usize)
+    }
+
+    /// Sessions in progress, by fragment count.
+    pub fn tiers(&self) -> impl Iterator<Item = (usize, &UrDecoder)> {
+        self.tiers
+            .iter()
+            .map(|(&count, decoder)| (count as usize, decoder))
+    }
+
+    pub fn message(&self) -> Result<Vec<u8>, QuantusUrError> {
+        match self.completed.and_then(|count| self.tiers.get(&count)) {
+            Some(decoder) => decoder.message(),
+            None => Err(QuantusUrError::Incomplete),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload() -> Vec<u8> {
+        (0..3000u32).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_tiers_skip_duplicates() {
+        let tiers = encode_multi_density(&payload(), &[1100, 1200, 200]).expect("Encoding failed");
+        assert_eq!(tiers.len(), 2);
+        assert_eq!(tiers[0].parts.len(), tiers[0].fragment_count);
+        assert!(tiers[0].fragment_count < tiers[1].fragment_count);
+    }
+
+    #[test]
+    fn test_mixed_tiers_decode() {
+        let payload = payload();
+        let tiers = encode_multi_density(&payload, &[1000, 200]).expect("Encoding failed");
+        let mut decoder = MultiDensityDecoder::new();
+        // Sparse frames first, then the scanner catches the dense tier
+        for part in tiers[1].parts.iter().take(3) {
+            decoder.receive(part).expect("Receive failed");
+        }
+        for part in &tiers[0].parts {
+            decoder.receive(part).expect("Receive failed");
+        }
+        assert_eq!(decoder.completed_tier(), Some(tiers[0].fragment_count));
+        assert_eq!(decoder.tiers().count(), 2);
+        assert_eq!(decoder.message().expect("Decoding failed"), payload);
+    }
+
+    #[test]
+    fn test_foreign_message_rejected() {
+        let payload = payload();
+        let tiers = encode_multi_density(&payload, &[1000, 200]).expect("Encoding failed");
+        let foreign = encode_multi_density(&payload[1..], &[500]).expect("Encoding failed");
+        let mut decoder = MultiDensityDecoder::new();
+        decoder.receive(&tiers[1].parts[0]).expect("Receive failed");
+        for part in &foreign[0].parts {
+            assert!(matches!(
+                decoder.receive(part),
+                Err(QuantusUrError::UrError(_))
+            ));
+        }
+        assert!(!decoder.is_complete());
+        assert_eq!(decoder.tiers().count(), 1);
+        for part in &tiers[0].parts {
+            decoder.receive(part).expect("Receive failed");
+        }
+        assert_eq!(decoder.message().expect("Decoding failed"), payload);
+    }
+
+    #[test]
+    fn test_tier_count_capped() {
+        let tiers = encode_multi_density(&payload(), &[100, 150, 200, 300, 400, 600])
+            .expect("Encoding failed");
+        assert!(tiers.len() > MAX_DENSITY_TIERS);
+        let mut decoder = MultiDensityDecoder::new();
+        for tier in &tiers[..MAX_DENSITY_TIERS] {
+            decoder.receive(&tier.parts[0]).expect("Receive failed");
+        }
+        assert!(matches!(
+            decoder.receive(&tiers[MAX_DENSITY_TIERS].parts[0]),
+            Err(QuantusUrError::UrError(_))
+        ));
+        assert_eq!(decoder.tiers().count(), MAX_DENSITY_TIERS);
+        assert!(decoder.receive(&tiers[0].parts[1]).is_ok());
+    }
+
+    #[test]
+    fn test_controller_escalates() {
+        let payload = payload();
+        let mut controller = DensityController::new(&payload, &[1000, 100]).expect("Failed");
+        assert_eq!(controller.fragment_length(), 100);
+
+        let mut decoder = MultiDensityDecoder::new();
+        for _ in 0..4 {
+            decoder
+                .receive(&controller.next_part().unwrap())
+                .expect("Receive failed");
+        }
+        assert!(!controller.report_progress(2));
+        assert!(controller.report_progress(4));
+        assert_eq!(controller.fragment_length(), 1000);
+
+        while !decoder.is_complete() {
+            decoder
+                .receive(&controller.next_part().unwrap())
+                .expect("Receive failed");
+        }
+        assert_eq!(decoder.message().expect("Decoding failed"), payload);
+    }
+
+    #[test]
+    fn test_controller_time_based() {
+        let policy = EscalationPolicy {
+            escalate_after_frames: Some(2),
+            ..EscalationPolicy::default()
+        };
+        let mut controller = DensityController::new(&payload(), &[100, 500, 1000])
+            .expect("Failed")
+            .with_policy(policy);
+        for _ in 0..6 {
+            controller.next_part().unwrap();
+        }
+        assert_eq!(controller.tier(), 2);
+    }
+}
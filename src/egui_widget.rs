@@ -0,0 +1,137 @@
+//! egui widget that animates a UR part sequence as QR frames.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::time::Duration;
+use egui::{Color32, Rect, Response, Sense, Ui, Vec2};
+
+use crate::qr::{part_matrix, ErrorCorrection, QrMatrix};
+use crate::{QuantusUrError, UrEncoder};
+
+const DEFAULT_FPS: f32 = 8.0;
+const DEFAULT_QUIET_ZONE: usize = 4;
+
+enum FrameSource {
+    Encoder(UrEncoder),
+    Parts { parts: Vec<String>, index: usize },
+}
+
+impl FrameSource {
+    fn next_part(&mut self) -> Result<String, QuantusUrError> {
+        match self {
+            FrameSource::Encoder(encoder) => encoder.next_part(),
+            FrameSource::Parts { parts, index } => {
+                let part = parts
+                    .get(*index)
+                    .cloned()
+                    .ok_or_else(|| QuantusUrError::UrError("No UR parts provided".to_string()))?;
+                *index = (*index + 1) % parts.len();
+                Ok(part)
+            }
+        }
+    }
+}
+
+/// Animated QR display of a UR message.
+///
+/// Handles frame cycling at a fixed rate, square sizing to the available
+/// space and the quiet zone around the code. Call [`UrAnimation::show`] every
+/// frame; the widget requests repaints itself.
+pub struct UrAnimation {
+    source: FrameSource,
+    frame_interval: f64,
+    quiet_zone: usize,
+    ec: ErrorCorrection,
+    current: Option<Result<QrMatrix, QuantusUrError>>,
+    last_switch: f64,
+}
+
+impl UrAnimation {
+    /// Animates an encoder, cycling through fountain parts indefinitely.
+    pub fn from_encoder(encoder: UrEncoder) -> Self {
+        Self::with_source(FrameSource::Encoder(encoder))
+    }
+
+    /// Animates a fixed set of parts, looping over them in order.
+    pub fn from_parts(parts: Vec<String>) -> Self {
+        Self::with_source(FrameSource::Parts { parts, index: 0 })
+    }
+
+    fn with_source(source: FrameSource) -> Self {
+        UrAnimation {
+            source,
+            frame_interval: 1.0 / DEFAULT_FPS as f64,
+            quiet_zone: DEFAULT_QUIET_ZONE,
+            ec: ErrorCorrection::default(),
+            current: None,
+            last_switch: 0.0,
+        }
+    }
+
+    pub fn fps(mut self, fps: f32) -> Self {
+        if fps > 0.0 {
+            self.frame_interval = 1.0 / fps as f64;
+        }
+        self
+    }
+
+    /// Width of the light border around the code, in modules.
+    pub fn quiet_zone(mut self, modules: usize) -> Self {
+        self.quiet_zone = modules;
+        self
+    }
+
+    pub fn error_correction(mut self, ec: ErrorCorrection) -> Self {
+        self.ec = ec;
+        self
+    }
+
+    fn advance(&mut self, now: f64) {
+        if self.current.is_some() && now - self.last_switch < self.frame_interval {
+            return;
+        }
+        let ec = self.ec;
+        self.current = Some(
+            self.source
+                .next_part()
+                .and_then(|part| part_matrix(&part, ec)),
+        );
+        self.last_switch = now;
+    }
+
+    pub fn show(&mut self, ui: &mut Ui) -> Response {
+        let now = ui.ctx().input(|i| i.time);
+        self.advance(now);
+        ui.ctx()
+            .request_repaint_after(Duration::from_secs_f64(self.frame_interval));
+
+        let matrix = match &self.current {
+            Some(Ok(matrix)) => matrix,
+            Some(Err(e)) => return ui.label(e.to_string()),
+            None => return ui.label(""),
+        };
+
+        let available = ui.available_size();
+        let side = available.x.min(available.y).max(0.0);
+        let (rect, response) = ui.allocate_exact_size(Vec2::splat(side), Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, Color32::WHITE);
+
+        let total = (matrix.width() + 2 * self.quiet_zone) as f32;
+        let module = side / total;
+        let origin = rect.min + Vec2::splat(self.quiet_zone as f32 * module);
+        for (y, row) in matrix.rows().enumerate() {
+            for (x, dark) in row.iter().enumerate() {
+                if *dark {
+                    let min = origin + Vec2::new(x as f32 * module, y as f32 * module);
+                    painter.rect_filled(
+                        Rect::from_min_size(min, Vec2::splat(module)),
+                        0.0,
+                        Color32::BLACK,
+                    );
+                }
+            }
+        }
+        response
+    }
+}
@@ -0,0 +1,586 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::time::Duration;
+use minicbor::data::Tag;
+use ur::bytewords::{self, Style};
+
+use crate::base45;
+use crate::cbor::cbor_error;
+use crate::chunked::encode_chunks;
+#[cfg(feature = "metrics")]
+use crate::metrics::SharedMetrics;
+use crate::part::parse_part;
+#[cfg(feature = "raptorq")]
+use crate::raptor::RaptorSource;
+use crate::{EncodeOptions, EncodingMode, QuantusUrError, UrFlavor, UR_TYPE};
+
+/// Most parts [`UrEncoder::part_at`] generates to catch up with the clock in
+/// one call. A larger jump moves the animation forward by this much instead.
+pub const MAX_FRAME_SKIP: usize = 1024;
+
+/// Stateful encoder producing the UR parts of a single payload one at a time.
+///
+/// Unlike [`crate::encode_bytes`], which returns the minimal set of parts, the
+/// encoder keeps generating fountain parts past the minimal set, so it can
+/// drive an endlessly looping QR animation.
+pub struct UrEncoder {
+    cbor: Vec<u8>,
+    ur_type: String,
+    options: EncodeOptions,
+    single: Option<String>,
+    inner: Option<ur::Encoder>,
+    fragment_count: usize,
+    sequence: usize,
+    resume_at: usize,
+    /// Mixed parts emitted since the minimal set was last started.
+    mixed_emitted: usize,
+    /// Parts emitted since the last reset, counting replays of the minimal set.
+    emitted: usize,
+    /// Last part returned by [`UrEncoder::part_at`] and its sequence number.
+    frame: Option<(usize, String)>,
+    /// Hex chunks cycled instead of UR parts in [`EncodingMode::HexChunks`].
+    chunks: Vec<String>,
+    /// Packet source in [`EncodingMode::RaptorQ`], built on the first reset.
+    #[cfg(feature = "raptorq")]
+    raptor: Option<RaptorSource>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<SharedMetrics>,
+}
+
+impl UrEncoder {
+    pub fn new(payload: &[u8]) -> Result<Self, QuantusUrError> {
+        Self::with_options(payload, &EncodeOptions::default())
+    }
+
+    /// Creates an encoder splitting the message into fragments of at most
+    /// `max_fragment_length` bytes instead of the default.
+    pub fn with_max_fragment_length(
+        payload: &[u8],
+        max_fragment_length: usize,
+    ) -> Result<Self, QuantusUrError> {
+        let options = EncodeOptions {
+            max_fragment_length,
+            ..EncodeOptions::default()
+        };
+        Self::with_options(payload, &options)
+    }
+
+    /// Fails with [`QuantusUrError::EmptyPayload`] for an empty payload.
+    pub fn with_options(payload: &[u8], options: &EncodeOptions) -> Result<Self, QuantusUrError> {
+        if payload.is_empty() {
+            return Err(QuantusUrError::EmptyPayload);
+        }
+        let mut e = minicbor::Encoder::new(Vec::new());
+        if options.tag_embedded_cbor {
+            e.tag(Tag::Cbor).map_err(cbor_error)?;
+        }
+        e.bytes(payload).map_err(cbor_error)?;
+        let cbor = e.into_writer();
+        let chunks = match options.mode {
+            EncodingMode::HexChunks => {
+                // Chunks split the raw payload, so its length sizes them
+                options.validate_for(payload.len())?;
+                encode_chunks(payload, options.fragment_length_for(payload.len()))?
+            }
+            _ => {
+                options.validate_for(cbor.len())?;
+                Vec::new()
+            }
+        };
+        Self::build(cbor, UR_TYPE, options, chunks)
+    }
+
+    /// Creates an encoder for an already CBOR-encoded message of the given UR type.
+    pub(crate) fn from_cbor(cbor: Vec<u8>, ur_type: &str) -> Result<Self, QuantusUrError> {
+        Self::from_cbor_with_options(cbor, ur_type, &EncodeOptions::default())
+    }
+
+    pub(crate) fn from_cbor_with_options(
+        cbor: Vec<u8>,
+        ur_type: &str,
+        options: &EncodeOptions,
+    ) -> Result<Self, QuantusUrError> {
+        if options.mode == EncodingMode::HexChunks {
+            return Err(QuantusUrError::InvalidConfiguration(
+                "hex chunks only carry sign-request payloads".to_string(),
+            ));
+        }
+        options.validate_for(cbor.len())?;
+        Self::build(cbor, ur_type, options, Vec::new())
+    }
+
+    fn build(
+        cbor: Vec<u8>,
+        ur_type: &str,
+        options: &EncodeOptions,
+        chunks: Vec<String>,
+    ) -> Result<Self, QuantusUrError> {
+        trace_span!("encoder_new", ur_type, message_len = cbor.len());
+        let mut encoder = UrEncoder {
+            cbor,
+            ur_type: ur_type.to_string(),
+            options: *options,
+            single: None,
+            inner: None,
+            fragment_count: 0,
+            sequence: 0,
+            resume_at: 0,
+            mixed_emitted: 0,
+            emitted: 0,
+            frame: None,
+            chunks,
+            #[cfg(feature = "raptorq")]
+            raptor: None,
+            #[cfg(feature = "metrics")]
+            metrics: None,
+        };
+        encoder.reset()?;
+        trace_event!(fragment_count = encoder.fragment_count, "encoder ready");
+        Ok(encoder)
+    }
+
+    /// Whether the payload fits into a single UR part.
+    pub fn is_single_part(&self) -> bool {
+        self.fragment_count == 1
+    }
+
+    /// Number of parts in the minimal set needed to reconstruct the payload.
+    pub fn fragment_count(&self) -> usize {
+        self.fragment_count
+    }
+
+    /// Sequence number of the most recently emitted part (0 before the first).
+    pub fn current_sequence(&self) -> usize {
+        self.sequence
+    }
+
+    /// Whether every part of the minimal set has been emitted since the last reset.
+    pub fn minimal_set_emitted(&self) -> bool {
+        self.sequence >= self.fragment_count
+    }
+
+    pub fn next_part(&mut self) -> Result<String, QuantusUrError> {
+        let part = match self.generate()? {
+            Some(part) => part,
+            None => self.single_part()?.to_string(),
+        };
+        self.report_part();
+        Ok(part)
+    }
+
+    /// Reports every part handed out from now on to `metrics`.
+    #[cfg(feature = "metrics")]
+    pub fn set_metrics(&mut self, metrics: SharedMetrics) {
+        self.metrics = Some(metrics);
+    }
+
+    fn report_part(&self) {
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            metrics.part_encoded();
+        }
+    }
+
+    /// Part to display `elapsed` into an animation running at `fps`, for
+    /// stateless render loops that redraw more often than the frame rate.
+    ///
+    /// Repeated calls within one frame return the same part. Moving forward
+    /// generates the skipped parts, at most [`MAX_FRAME_SKIP`] per call;
+    /// moving back in time restarts the sequence. A non-finite or negative
+    /// `fps` fails with [`QuantusUrError::InvalidConfiguration`].
+    pub fn part_at(&mut self, elapsed: Duration, fps: f32) -> Result<String, QuantusUrError> {
+        if !fps.is_finite() || fps < 0.0 {
+            return Err(QuantusUrError::InvalidConfiguration(format!(
+                "fps {} is not a finite, non-negative rate",
+                fps
+            )));
+        }
+        // Float to integer casts saturate, so only the increment can overflow
+        let sequence = ((elapsed.as_secs_f32() * fps) as usize).saturating_add(1);
+        if let Some((cached, part)) = &self.frame {
+            if *cached == sequence {
+                return Ok(part.clone());
+            }
+        }
+        if sequence <= self.emitted {
+            self.reset()?;
+        }
+        let target = sequence.min(self.emitted.saturating_add(MAX_FRAME_SKIP + 1));
+        while self.emitted + 1 < target {
+            self.generate()?;
+        }
+        let part = self.next_part()?;
+        self.frame = Some((sequence, part.clone()));
+        Ok(part)
+    }
+
+    /// Writes the next part into `writer`, without the `String` that
+    /// [`UrEncoder::next_part`] returns. Single-part payloads are written from
+    /// the cached part and multi-part ones are cased in place.
+    pub fn write_next_part(&mut self, writer: &mut dyn PartWriter) -> Result<(), QuantusUrError> {
+        match self.generate()? {
+            Some(part) => writer.write_part(&part),
+            None => writer.write_part(self.single_part()?),
+        }?;
+        self.report_part();
+        Ok(())
+    }
+
+    fn single_part(&self) -> Result<&str, QuantusUrError> {
+        self.single
+            .as_deref()
+            .ok_or_else(|| QuantusUrError::UrError("No single part available".to_string()))
+    }
+
+    /// Advances the sequence and returns the new fountain part, or `None`
+    /// when the cached single part is next.
+    fn generate(&mut self) -> Result<Option<String>, QuantusUrError> {
+        if self.options.fountain.mixed_window == Some(self.mixed_emitted) {
+            let emitted = self.emitted;
+            self.restart_from_minimal_set()?;
+            self.emitted = emitted;
+        }
+        self.emitted += 1;
+        if !self.chunks.is_empty() {
+            let part = self.chunks[self.sequence % self.chunks.len()].clone();
+            self.sequence += 1;
+            return Ok(Some(part));
+        }
+        #[cfg(feature = "raptorq")]
+        if let Some(source) = &self.raptor {
+            let mut part = source.part(self.sequence)?;
+            self.sequence += 1;
+            self.apply_casing(&mut part);
+            return Ok(Some(part));
+        }
+        let inner = match self.inner.as_mut() {
+            Some(inner) => inner,
+            None => {
+                self.sequence += 1;
+                trace_event!(sequence = self.sequence, "single part generated");
+                return Ok(None);
+            }
+        };
+
+        // Skip the parts already shown before `restart_from_minimal_set`, and
+        // those before the fountain seed, so the animation continues with
+        // fountain parts the receiver has not seen yet.
+        if self.sequence == self.fragment_count && self.resume_at > self.sequence {
+            while self.sequence < self.resume_at {
+                inner
+                    .next_part()
+                    .map_err(|e| QuantusUrError::UrError(e.to_string()))?;
+                self.sequence += 1;
+            }
+            self.resume_at = 0;
+        }
+
+        let mut part = inner
+            .next_part()
+            .map_err(|e| QuantusUrError::UrError(e.to_string()))?;
+        self.sequence += 1;
+        if self.sequence > self.fragment_count {
+            self.mixed_emitted += 1;
+        }
+        trace_event!(
+            sequence = self.sequence,
+            fragment_count = self.fragment_count,
+            "part generated"
+        );
+        if self.options.mode == EncodingMode::Base45 {
+            let parsed = parse_part(&part)?;
+            let sequence = parsed.header.map(|h| (h.sequence, h.sequence_count));
+            return Ok(Some(base45::encode_part(
+                &parsed.ur_type,
+                sequence,
+                &parsed.body,
+            )));
+        }
+        self.apply_casing(&mut part);
+        Ok(Some(part))
+    }
+
+    /// Minimal part count when the parts are hex chunks or RaptorQ packets
+    /// rather than BC-UR fountain parts.
+    fn alternative_part_count(&self) -> Option<usize> {
+        #[cfg(feature = "raptorq")]
+        if let Some(source) = &self.raptor {
+            return Some(source.source_count());
+        }
+        (!self.chunks.is_empty()).then_some(self.chunks.len())
+    }
+
+    /// Parts are ASCII, so the casing is changed in place.
+    fn apply_casing(&self, part: &mut str) {
+        match self.options.flavor {
+            UrFlavor::Keystone => part.make_ascii_uppercase(),
+            UrFlavor::BcUr => part.make_ascii_lowercase(),
+        }
+    }
+
+    /// Restarts the sequence from the first part, as if the encoder had just
+    /// been created.
+    pub fn reset(&mut self) -> Result<(), QuantusUrError> {
+        trace_event!(sequence = self.sequence, "encoder reset");
+        let max_fragment_length = self.options.fragment_length_for(self.cbor.len());
+        #[cfg(feature = "raptorq")]
+        if self.options.mode == EncodingMode::RaptorQ && self.raptor.is_none() {
+            self.raptor = Some(RaptorSource::new(
+                &self.cbor,
+                &self.ur_type,
+                max_fragment_length,
+            )?);
+        }
+        if let Some(count) = self.alternative_part_count() {
+            self.fragment_count = count;
+            self.inner = None;
+            self.single = None;
+        } else if self.cbor.len() > max_fragment_length {
+            let encoder = ur::Encoder::new(&self.cbor, max_fragment_length, self.ur_type.clone())
+                .map_err(|e| QuantusUrError::UrError(e.to_string()))?;
+            self.fragment_count = encoder.fragment_count();
+            self.inner = Some(encoder);
+            self.single = None;
+        } else {
+            self.fragment_count = 1;
+            self.inner = None;
+            self.single = Some(if self.options.mode == EncodingMode::Base45 {
+                base45::encode_part(&self.ur_type, None, &self.cbor)
+            } else {
+                let body = bytewords::encode(&self.cbor, Style::Minimal);
+                let mut single = format!("ur:{}/{}", self.ur_type, body);
+                self.apply_casing(&mut single);
+                single
+            });
+        }
+        self.sequence = 0;
+        self.resume_at = match self.inner {
+            Some(_) => self.fragment_count + self.options.fountain.seed as usize,
+            None => 0,
+        };
+        self.mixed_emitted = 0;
+        self.emitted = 0;
+        Ok(())
+    }
+
+    /// Replays the minimal set of parts from the beginning, then resumes the
+    /// fountain sequence after the last part emitted before the restart.
+    ///
+    /// Use this when a user returns to an animation: a fresh scanner completes
+    /// fastest from the minimal set, while a scanner that kept its progress
+    /// still receives parts it has not seen.
+    pub fn restart_from_minimal_set(&mut self) -> Result<(), QuantusUrError> {
+        let resume_at = self.sequence;
+        self.reset()?;
+        self.resume_at = self.resume_at.max(resume_at);
+        Ok(())
+    }
+
+    /// Consumes the encoder into an iterator over the rest of the minimal set,
+    /// generating each part only when it is requested.
+    pub fn into_minimal_parts(self) -> MinimalParts {
+        MinimalParts {
+            remaining: self.fragment_count.saturating_sub(self.sequence),
+            encoder: self,
+        }
+    }
+}
+
+/// Destination for parts written by [`UrEncoder::write_next_part`], e.g. a
+/// reusable buffer or a QR encoder fed directly from the part text.
+pub trait PartWriter {
+    fn write_part(&mut self, part: &str) -> Result<(), QuantusUrError>;
+}
+
+/// Replaces the buffer contents with the part, reusing its allocation.
+impl PartWriter for String {
+    fn write_part(&mut self, part: &str) -> Result<(), QuantusUrError> {
+        self.clear();
+        self.push_str(part);
+        Ok(())
+    }
+}
+
+/// Lazily generated minimal set of parts, see [`UrEncoder::into_minimal_parts`].
+pub struct MinimalParts {
+    encoder: UrEncoder,
+    remaining: usize,
+}
+
+impl Iterator for MinimalParts {
+    type Item = Result<String, QuantusUrError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        Some(self.encoder.next_part())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for MinimalParts {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode_bytes;
+    use alloc::vec;
+
+    fn large_payload() -> Vec<u8> {
+        (0..250).map(|i| i as u8).collect()
+    }
+
+    #[test]
+    fn test_reset_restarts_sequence() {
+        let mut encoder = UrEncoder::new(&large_payload()).expect("Encoder creation failed");
+        let first = encoder.next_part().expect("Part generation failed");
+        encoder.next_part().expect("Part generation failed");
+
+        encoder.reset().expect("Reset failed");
+        assert_eq!(encoder.current_sequence(), 0);
+        assert_eq!(encoder.next_part().expect("Part generation failed"), first);
+    }
+
+    #[test]
+    fn test_fountain_params() {
+        let payload = large_payload();
+        let mut plain = UrEncoder::new(&payload).expect("Encoder creation failed");
+        let count = plain.fragment_count();
+        let stream: Vec<String> = (0..count + 8).map(|_| plain.next_part().unwrap()).collect();
+
+        let options = EncodeOptions {
+            fountain: crate::FountainParams {
+                seed: 3,
+                mixed_window: Some(2),
+            },
+            ..EncodeOptions::default()
+        };
+        let mut tuned = UrEncoder::with_options(&payload, &options).expect("Encoder failed");
+        let parts: Vec<String> = (0..2 * count + 4)
+            .map(|_| tuned.next_part().unwrap())
+            .collect();
+        assert_eq!(parts[..count], stream[..count]);
+        assert_eq!(parts[count..count + 2], stream[count + 3..count + 5]);
+        assert_eq!(parts[count + 2..2 * count + 2], stream[..count]);
+        assert_eq!(parts[2 * count + 2..], stream[count + 5..count + 7]);
+        assert_eq!(
+            decode_bytes(&parts[count..]).expect("Decoding failed"),
+            payload
+        );
+
+        let options = EncodeOptions {
+            fountain: crate::FountainParams {
+                seed: crate::MAX_FOUNTAIN_SEED + 1,
+                mixed_window: None,
+            },
+            ..EncodeOptions::default()
+        };
+        assert!(UrEncoder::with_options(&payload, &options).is_err());
+    }
+
+    #[test]
+    fn test_minimal_parts_lazy() {
+        let payload = large_payload();
+        let encoder = UrEncoder::new(&payload).expect("Encoder creation failed");
+        let count = encoder.fragment_count();
+        let mut parts = encoder.into_minimal_parts();
+        assert_eq!(parts.len(), count);
+        let first = parts
+            .next()
+            .expect("Missing part")
+            .expect("Part generation failed");
+        assert_eq!(parts.len(), count - 1);
+
+        let mut all = vec![first];
+        for part in parts {
+            all.push(part.expect("Part generation failed"));
+        }
+        assert_eq!(decode_bytes(&all).expect("Decoding failed"), payload);
+    }
+
+    #[test]
+    fn test_write_next_part_matches_next_part() {
+        for payload in [large_payload(), b"Hello, Quantus!".to_vec()] {
+            let mut encoder = UrEncoder::new(&payload).expect("Encoder creation failed");
+            let mut writer = UrEncoder::new(&payload).expect("Encoder creation failed");
+            let mut buffer = String::new();
+            for _ in 0..encoder.fragment_count() + 2 {
+                writer
+                    .write_next_part(&mut buffer)
+                    .expect("Part generation failed");
+                assert_eq!(buffer, encoder.next_part().expect("Part generation failed"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_part_at_follows_clock() {
+        let payload = large_payload();
+        let mut sequential = UrEncoder::new(&payload).expect("Encoder creation failed");
+        let parts: Vec<String> = (0..6)
+            .map(|_| sequential.next_part().expect("Part generation failed"))
+            .collect();
+
+        let mut encoder = UrEncoder::new(&payload).expect("Encoder creation failed");
+        let at = |encoder: &mut UrEncoder, ms| {
+            encoder
+                .part_at(Duration::from_millis(ms), 4.0)
+                .expect("Part generation failed")
+        };
+        assert_eq!(at(&mut encoder, 0), parts[0]);
+        assert_eq!(at(&mut encoder, 200), parts[0]);
+        assert_eq!(at(&mut encoder, 260), parts[1]);
+        assert_eq!(at(&mut encoder, 1300), parts[5]);
+        assert_eq!(at(&mut encoder, 500), parts[2]);
+
+        for fps in [f32::NAN, f32::INFINITY, -1.0] {
+            assert!(matches!(
+                encoder.part_at(Duration::from_secs(1), fps),
+                Err(QuantusUrError::InvalidConfiguration(_))
+            ));
+        }
+        assert!(encoder.part_at(Duration::MAX, f32::MAX).is_ok());
+        assert!(encoder.emitted <= MAX_FRAME_SKIP + 4);
+    }
+
+    #[test]
+    fn test_single_part_repeats() {
+        let mut encoder = UrEncoder::new(b"Hello, Quantus!").expect("Encoder creation failed");
+        assert!(encoder.is_single_part());
+        let first = encoder.next_part().expect("Part generation failed");
+        assert_eq!(encoder.next_part().expect("Part generation failed"), first);
+    }
+
+    #[test]
+    fn test_restart_from_minimal_set_resumes_after_last_part() {
+        let payload = large_payload();
+        let mut encoder = UrEncoder::new(&payload).expect("Encoder creation failed");
+        let count = encoder.fragment_count();
+
+        let mut shown = vec![];
+        for _ in 0..count + 2 {
+            shown.push(encoder.next_part().expect("Part generation failed"));
+        }
+
+        encoder.restart_from_minimal_set().expect("Restart failed");
+        let replayed: Vec<String> = (0..count)
+            .map(|_| encoder.next_part().expect("Part generation failed"))
+            .collect();
+        assert_eq!(replayed[..], shown[..count]);
+
+        let resumed = encoder.next_part().expect("Part generation failed");
+        assert_eq!(encoder.current_sequence(), count + 3);
+        assert!(
+            !shown.contains(&resumed),
+            "Should continue with unseen parts"
+        );
+
+        assert_eq!(decode_bytes(&replayed).expect("Decoding failed"), payload);
+    }
+}
@@ -0,0 +1,339 @@
+//! Signed envelopes authenticating the sender of a request.
+//!
+//! The wallet installation holds a long-term ML-DSA-87 key and signs each
+//! envelope; signer firmware pins the public keys of the installations it
+//! trusts and refuses envelopes that do not verify against them. The
+//! signature covers a domain-separated header of the payload hash, the
+//! metadata and the key id, so neither the payload nor the metadata can be
+//! swapped without invalidating it.
+//!
+//! Envelopes may also name the sending wallet with a [`SenderId`], which
+//! deployments restrict with a [`TrustPolicy`] passed to
+//! [`SignedEnvelope::open_with_policy`].
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use minicbor::data::Type;
+use minicbor::{Decoder, Encoder};
+use ml_dsa::{KeyPair, MlDsa87};
+
+use crate::cbor::{cbor_error, expect_ur_type};
+use crate::transfer::{blake2_256, Hash};
+use crate::{
+    constant_time_eq, decode_message, verify_ml_dsa, EncodeOptions, QuantusUrError, UrDecoder,
+    UrEncoder,
+};
+
+pub const ENVELOPE_UR_TYPE: &str = "quantus-signed-envelope";
+
+pub(crate) const ENVELOPE_VERSION: u8 = 1;
+const ENVELOPE_CONTEXT: &str = "quantus-ur envelope";
+
+fn failed(msg: &str) -> QuantusUrError {
+    QuantusUrError::SignatureVerificationFailed(msg.to_string())
+}
+
+/// Identifier of a sending wallet, e.g. an installation or fleet id.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SenderId(pub String);
+
+/// Verdict of a [`TrustPolicy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Decision {
+    Allow,
+    Deny,
+}
+
+/// Decides whether envelopes from a sender are accepted.
+pub type TrustPolicy = fn(&SenderId) -> Decision;
+
+/// Long-term key signing envelopes.
+pub trait EnvelopeSigner {
+    /// Encoded public key the receiver pins.
+    fn public_key(&self) -> Vec<u8>;
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, QuantusUrError>;
+}
+
+impl EnvelopeSigner for KeyPair<MlDsa87> {
+    fn public_key(&self) -> Vec<u8> {
+        self.verifying_key().encode().to_vec()
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, QuantusUrError> {
+        self.signing_key()
+            .sign_deterministic(message, &[])
+            .map(|signature| signature.encode().to_vec())
+            .map_err(|_| QuantusUrError::InvalidConfiguration("Signing failed".to_string()))
+    }
+}
+
+/// Id of a public key in an envelope: its BLAKE2b-256 hash.
+pub fn key_id(public_key: &[u8]) -> Hash {
+    blake2_256(public_key)
+}
+
+/// Signed metadata describing the payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EnvelopeMetadata {
+    /// How to interpret the payload, e.g. [`crate::bundle::CONTENT_SIGN_REQUEST`].
+    pub content_type: String,
+    /// Creation time in milliseconds, see [`crate::clock::Clock`].
+    pub created_at: u64,
+    /// Sending wallet, checked by a [`TrustPolicy`] when present.
+    pub sender_id: Option<SenderId>,
+}
+
+impl EnvelopeMetadata {
+    fn encode(&self, e: &mut Encoder<Vec<u8>>) -> Result<(), QuantusUrError> {
+        e.str(&self.content_type)
+            .and_then(|e| e.u64(self.created_at))
+            .map_err(cbor_error)?;
+        match &self.sender_id {
+            Some(sender) => e.str(&sender.0),
+            None => e.null(),
+        }
+        .map_err(cbor_error)?;
+        Ok(())
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Self, QuantusUrError> {
+        let content_type = d.str().map_err(cbor_error)?.to_string();
+        let created_at = d.u64().map_err(cbor_error)?;
+        let sender_id = if d.datatype().map_err(cbor_error)? == Type::Null {
+            d.skip().map_err(cbor_error)?;
+            None
+        } else {
+            Some(SenderId(d.str().map_err(cbor_error)?.to_string()))
+        };
+        Ok(EnvelopeMetadata {
+            content_type,
+            created_at,
+            sender_id,
+        })
+    }
+}
+
+/// A payload with its metadata, signed by the sender's long-term key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SignedEnvelope {
+    pub payload: Vec<u8>,
+    pub metadata: EnvelopeMetadata,
+    pub key_id: Hash,
+    pub signature: Vec<u8>,
+}
+
+impl SignedEnvelope {
+    pub fn sign(
+        payload: &[u8],
+        metadata: EnvelopeMetadata,
+        signer: &impl EnvelopeSigner,
+    ) -> Result<Self, QuantusUrError> {
+        if payload.is_empty() {
+            return Err(QuantusUrError::EmptyPayload);
+        }
+        let mut envelope = SignedEnvelope {
+            payload: payload.to_vec(),
+            metadata,
+            key_id: key_id(&signer.public_key()),
+            signature: Vec::new(),
+        };
+        envelope.signature = signer.sign(&envelope.signed_message()?)?;
+        Ok(envelope)
+    }
+
+    /// The bytes covered by the signature.
+    fn signed_message(&self) -> Result<Vec<u8>, QuantusUrError> {
+        let mut e = Encoder::new(Vec::new());
+        e.array(7)
+            .and_then(|e| e.str(ENVELOPE_CONTEXT))
+            .and_then(|e| e.u8(ENVELOPE_VERSION))
+            .and_then(|e| e.bytes(&blake2_256(&self.payload)))
+            .map_err(cbor_error)?;
+        self.metadata.encode(&mut e)?;
+        e.bytes(&self.key_id).map_err(cbor_error)?;
+        Ok(e.into_writer())
+    }
+
+    /// Checks that the envelope was signed by `pinned_key`.
+    pub fn verify(&self, pinned_key: &[u8]) -> Result<(), QuantusUrError> {
+        if !constant_time_eq(&key_id(pinned_key), &self.key_id) {
+            return Err(failed("Envelope signed by an unknown key"));
+        }
+        verify_ml_dsa(pinned_key, &self.signed_message()?, &self.signature)
+    }
+
+    /// Decodes an envelope and verifies it against `pinned_key`.
+    pub fn open(ur_parts: &[String], pinned_key: &[u8]) -> Result<Self, QuantusUrError> {
+        let envelope = Self::from_parts(ur_parts)?;
+        envelope.verify(pinned_key)?;
+        Ok(envelope)
+    }
+
+    /// Like [`SignedEnvelope::open`], then asks `policy` about the sender.
+    /// Envelopes without a sender id fail with
+    /// [`QuantusUrError::SenderNotTrusted`], as do denied senders.
+    pub fn open_with_policy(
+        ur_parts: &[String],
+        pinned_key: &[u8],
+        policy: TrustPolicy,
+    ) -> Result<Self, QuantusUrError> {
+        let envelope = Self::open(ur_parts, pinned_key)?;
+        match envelope.metadata.sender_id.as_ref().map(policy) {
+            Some(Decision::Allow) => Ok(envelope),
+            _ => Err(QuantusUrError::SenderNotTrusted),
+        }
+    }
+
+    fn to_cbor(&self) -> Result<Vec<u8>, QuantusUrError> {
+        let mut e = Encoder::new(Vec::new());
+        e.array(7)
+            .and_then(|e| e.u8(ENVELOPE_VERSION))
+            .and_then(|e| e.bytes(&self.payload))
+            .map_err(cbor_error)?;
+        self.metadata.encode(&mut e)?;
+        e.bytes(&self.key_id)
+            .and_then(|e| e.bytes(&self.signature))
+            .map_err(cbor_error)?;
+        Ok(e.into_writer())
+    }
+
+    fn from_cbor(cbor: &[u8]) -> Result<Self, QuantusUrError> {
+        let mut d = Decoder::new(cbor);
+        if d.array().map_err(cbor_error)? != Some(7) {
+            return Err(QuantusUrError::CborError(
+                "Malformed signed envelope".to_string(),
+            ));
+        }
+        let version = d.u8().map_err(cbor_error)?;
+        if version != ENVELOPE_VERSION {
+            return Err(QuantusUrError::CborError(format!(
+                "Unsupported envelope version {}",
+                version
+            )));
+        }
+        let payload = d.bytes().map_err(cbor_error)?.to_vec();
+        let metadata = EnvelopeMetadata::decode(&mut d)?;
+        let key_id = d
+            .bytes()
+            .map_err(cbor_error)?
+            .try_into()
+            .map_err(|_| QuantusUrError::CborError("Expected a 32-byte key id".to_string()))?;
+        let signature = d.bytes().map_err(cbor_error)?.to_vec();
+        if payload.is_empty() {
+            return Err(QuantusUrError::EmptyPayload);
+        }
+        Ok(SignedEnvelope {
+            payload,
+            metadata,
+            key_id,
+            signature,
+        })
+    }
+
+    /// Encoder for animating the envelope.
+    pub fn encoder(&self, options: &EncodeOptions) -> Result<UrEncoder, QuantusUrError> {
+        UrEncoder::from_cbor_with_options(self.to_cbor()?, ENVELOPE_UR_TYPE, options)
+    }
+
+    /// Minimal set of parts.
+    pub fn to_parts(&self) -> Result<Vec<String>, QuantusUrError> {
+        self.encoder(&EncodeOptions::default())?
+            .into_minimal_parts()
+            .collect()
+    }
+
+    /// Decodes an envelope without verifying it.
+    pub fn from_parts(ur_parts: &[String]) -> Result<Self, QuantusUrError> {
+        let (ur_type, cbor) = decode_message(ur_parts)?;
+        expect_ur_type(&ur_type, ENVELOPE_UR_TYPE)?;
+        Self::from_cbor(&cbor)
+    }
+
+    /// Reads the envelope, unverified, from a completed streaming session.
+    pub fn from_decoder(decoder: &UrDecoder) -> Result<Self, QuantusUrError> {
+        let cbor = decoder.cbor_message()?;
+        expect_ur_type(decoder.ur_type().unwrap_or_default(), ENVELOPE_UR_TYPE)?;
+        Self::from_cbor(&cbor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ml_dsa::{KeyGen, B32};
+
+    fn keypair(seed: u8) -> KeyPair<MlDsa87> {
+        MlDsa87::key_gen_internal(&B32::from([seed; 32]))
+    }
+
+    fn metadata() -> EnvelopeMetadata {
+        EnvelopeMetadata {
+            content_type: crate::bundle::CONTENT_SIGN_REQUEST.to_string(),
+            created_at: 1_700_000_000_000,
+            sender_id: Some(SenderId("wallet-a".to_string())),
+        }
+    }
+
+    #[test]
+    fn test_envelope_roundtrip() {
+        let wallet = keypair(7);
+        let envelope =
+            SignedEnvelope::sign(b"transfer 10 QUAN", metadata(), &wallet).expect("Signing failed");
+        let parts = envelope.to_parts().expect("Encoding failed");
+        let opened = SignedEnvelope::open(&parts, &wallet.public_key()).expect("Open failed");
+        assert_eq!(opened, envelope);
+        assert_eq!(opened.payload, b"transfer 10 QUAN");
+    }
+
+    #[test]
+    fn test_untrusted_envelopes_rejected() {
+        let wallet = keypair(7);
+        let envelope =
+            SignedEnvelope::sign(b"transfer 10 QUAN", metadata(), &wallet).expect("Signing failed");
+        assert!(matches!(
+            envelope.verify(&keypair(8).public_key()),
+            Err(QuantusUrError::SignatureVerificationFailed(_))
+        ));
+
+        let mut tampered = envelope.clone();
+        tampered.payload = b"transfer 99 QUAN".to_vec();
+        assert!(tampered.verify(&wallet.public_key()).is_err());
+        let mut tampered = envelope;
+        tampered.metadata.created_at += 1;
+        assert!(tampered.verify(&wallet.public_key()).is_err());
+    }
+
+    #[test]
+    fn test_trust_policy() {
+        fn fleet_only(sender: &SenderId) -> Decision {
+            if sender.0.starts_with("wallet-") {
+                Decision::Allow
+            } else {
+                Decision::Deny
+            }
+        }
+
+        let wallet = keypair(7);
+        let open = |sender: Option<&str>| {
+            let metadata = EnvelopeMetadata {
+                sender_id: sender.map(|id| SenderId(id.to_string())),
+                ..metadata()
+            };
+            let envelope =
+                SignedEnvelope::sign(b"transfer", metadata, &wallet).expect("Signing failed");
+            let parts = envelope.to_parts().expect("Encoding failed");
+            SignedEnvelope::open_with_policy(&parts, &wallet.public_key(), fleet_only)
+        };
+        assert!(open(Some("wallet-a")).is_ok());
+        assert!(matches!(
+            open(Some("intruder")),
+            Err(QuantusUrError::SenderNotTrusted)
+        ));
+        assert!(matches!(open(None), Err(QuantusUrError::SenderNotTrusted)));
+    }
+}
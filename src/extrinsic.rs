@@ -0,0 +1,407 @@
+//! Builders for common Quantus operations (transfer, bond/unbond, nominate,
+//! vote), producing a [`StructuredSignRequest`]: the SCALE-encoded call plus
+//! display hints the signer shows instead of decoding the call itself.
+//!
+//! Pallet and call indices depend on the runtime, so the caller supplies
+//! them in [`CallIndices`], e.g. from the runtime metadata at build time.
+//! The request is a `quantus-structured-request` UR whose CBOR body is
+//! `[version, payload, title, [[label, value], ...]]`.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use minicbor::{Decoder, Encoder};
+
+use crate::account::ss58_encode;
+use crate::cbor::{cbor_error, expect_ur_type};
+use crate::transfer::Hash;
+use crate::{decode_message, EncodeOptions, QuantusUrError, UrDecoder, UrEncoder};
+
+pub const STRUCTURED_UR_TYPE: &str = "quantus-structured-request";
+
+pub(crate) const STRUCTURED_VERSION: u8 = 1;
+
+/// Highest conviction of a conviction vote (6x, locked longest).
+pub const MAX_CONVICTION: u8 = 6;
+
+/// Pallet and call index of a call in the runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CallIndex {
+    pub pallet: u8,
+    pub call: u8,
+}
+
+/// Indices of the calls the builders emit; builders for calls left unset
+/// fail with [`QuantusUrError::InvalidConfiguration`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CallIndices {
+    /// `Balances::transfer_keep_alive`.
+    pub transfer: Option<CallIndex>,
+    /// `Staking::bond`.
+    pub bond: Option<CallIndex>,
+    /// `Staking::unbond`.
+    pub unbond: Option<CallIndex>,
+    /// `Staking::nominate`.
+    pub nominate: Option<CallIndex>,
+    /// `ConvictionVoting::vote`.
+    pub vote: Option<CallIndex>,
+}
+
+/// Where staking rewards go, `RewardDestination` without the deprecated
+/// controller variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Payee {
+    /// Added to the bonded amount.
+    Staked,
+    /// Paid to the stash account, not bonded.
+    Stash,
+    Account(Hash),
+    None,
+}
+
+/// Chain parameters the builders need to encode calls and format hints.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChainInfo {
+    pub ss58_prefix: u16,
+    pub token_symbol: String,
+    pub decimals: u8,
+    pub calls: CallIndices,
+}
+
+/// A signing payload with the hints to display it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StructuredSignRequest {
+    pub payload: Vec<u8>,
+    /// Short name of the operation, e.g. `"Transfer"`.
+    pub title: String,
+    /// Labelled values in display order.
+    pub fields: Vec<(String, String)>,
+}
+
+fn compact(out: &mut Vec<u8>, value: u128) {
+    if value < 1 << 6 {
+        out.push((value as u8) << 2);
+    } else if value < 1 << 14 {
+        out.extend_from_slice(&(((value as u16) << 2) | 0b01).to_le_bytes());
+    } else if value < 1 << 30 {
+        out.extend_from_slice(&(((value as u32) << 2) | 0b10).to_le_bytes());
+    } else {
+        let bytes = value.to_le_bytes();
+        let len = 16 - value.leading_zeros() as usize / 8;
+        out.push((((len - 4) as u8) << 2) | 0b11);
+        out.extend_from_slice(&bytes[..len]);
+    }
+}
+
+impl ChainInfo {
+    fn call(&self, index: Option<CallIndex>, name: &str) -> Result<Vec<u8>, QuantusUrError> {
+        let index = index.ok_or_else(|| {
+            QuantusUrError::InvalidConfiguration(format!("No call index configured for {}", name))
+        })?;
+        Ok([index.pallet, index.call].to_vec())
+    }
+
+    fn address(&self, account: &Hash) -> Result<String, QuantusUrError> {
+        ss58_encode(account, self.ss58_prefix)
+    }
+
+    /// Formats a balance in planck as whole tokens, e.g. `1.5 QUAN`.
+    pub fn format_amount(&self, amount: u128) -> String {
+        let unit = 10u128.checked_pow(self.decimals as u32);
+        let (whole, fraction) = match unit {
+            Some(unit) => (amount / unit, amount % unit),
+            None => (0, amount),
+        };
+        let mut text = whole.to_string();
+        if fraction > 0 {
+            let digits = format!("{:0width$}", fraction, width = self.decimals as usize);
+            text.push('.');
+            text.push_str(digits.trim_end_matches('0'));
+        }
+        format!("{} {}", text, self.token_symbol)
+    }
+
+    /// `Balances::transfer_keep_alive` of `amount` planck to `dest`.
+    pub fn transfer(
+        &self,
+        dest: &Hash,
+        amount: u128,
+    ) -> Result<StructuredSignRequest, QuantusUrError> {
+        let mut payload = self.call(self.calls.transfer, "transfer")?;
+        payload.push(0);
+        payload.extend_from_slice(dest);
+        compact(&mut payload, amount);
+        Ok(StructuredSignRequest::new(payload, "Transfer")
+            .with_field("To", &self.address(dest)?)
+            .with_field("Amount", &self.format_amount(amount)))
+    }
+
+    /// `Staking::bond` of `amount` planck.
+    pub fn bond(
+        &self,
+        amount: u128,
+        payee: Payee,
+    ) -> Result<StructuredSignRequest, QuantusUrError> {
+        let mut payload = self.call(self.calls.bond, "bond")?;
+        compact(&mut payload, amount);
+        let rewards = match payee {
+            Payee::Staked => {
+                payload.push(0);
+                "Staked".to_string()
+            }
+            Payee::Stash => {
+                payload.push(1);
+                "Stash".to_string()
+            }
+            Payee::Account(account) => {
+                payload.push(3);
+                payload.extend_from_slice(&account);
+                self.address(&account)?
+            }
+            Payee::None => {
+                payload.push(4);
+                "None".to_string()
+            }
+        };
+        Ok(StructuredSignRequest::new(payload, "Bond")
+            .with_field("Amount", &self.format_amount(amount))
+            .with_field("Rewards", &rewards))
+    }
+
+    /// `Staking::unbond` of `amount` planck.
+    pub fn unbond(&self, amount: u128) -> Result<StructuredSignRequest, QuantusUrError> {
+        let mut payload = self.call(self.calls.unbond, "unbond")?;
+        compact(&mut payload, amount);
+        Ok(StructuredSignRequest::new(payload, "Unbond")
+            .with_field("Amount", &self.format_amount(amount)))
+    }
+
+    /// `Staking::nominate` of the given validators.
+    pub fn nominate(&self, targets: &[Hash]) -> Result<StructuredSignRequest, QuantusUrError> {
+        if targets.is_empty() {
+            return Err(QuantusUrError::InvalidConfiguration(
+                "Nominate needs at least one validator".to_string(),
+            ));
+        }
+        let mut payload = self.call(self.calls.nominate, "nominate")?;
+        compact(&mut payload, targets.len() as u128);
+        let mut request = StructuredSignRequest::new(Vec::new(), "Nominate");
+        for (i, target) in targets.iter().enumerate() {
+            payload.push(0);
+            payload.extend_from_slice(target);
+            request = request.with_field(&format!("Validator {}", i + 1), &self.address(target)?);
+        }
+        request.payload = payload;
+        Ok(request)
+    }
+
+    /// `ConvictionVoting::vote` on referendum `poll` with a standard vote.
+    /// `conviction` 0 locks nothing and counts 0.1x, 1 to
+    /// [`MAX_CONVICTION`] multiply the vote.
+    pub fn vote(
+        &self,
+        poll: u32,
+        aye: bool,
+        conviction: u8,
+        balance: u128,
+    ) -> Result<StructuredSignRequest, QuantusUrError> {
+        if conviction > MAX_CONVICTION {
+            return Err(QuantusUrError::InvalidConfiguration(format!(
+                "Conviction {} out of range",
+                conviction
+            )));
+        }
+        let mut payload = self.call(self.calls.vote, "vote")?;
+        compact(&mut payload, poll as u128);
+        // AccountVote::Standard { vote, balance }
+        payload.push(0);
+        payload.push((if aye { 0x80 } else { 0 }) | conviction);
+        payload.extend_from_slice(&balance.to_le_bytes());
+        let conviction = match conviction {
+            0 => "0.1x".to_string(),
+            n => format!("{}x", n),
+        };
+        Ok(StructuredSignRequest::new(payload, "Vote")
+            .with_field("Referendum", &format!("#{}", poll))
+            .with_field("Vote", if aye { "Aye" } else { "Nay" })
+            .with_field("Conviction", &conviction)
+            .with_field("Balance", &self.format_amount(balance)))
+    }
+}
+
+impl StructuredSignRequest {
+    pub fn new(payload: Vec<u8>, title: &str) -> Self {
+        StructuredSignRequest {
+            payload,
+            title: title.to_string(),
+            fields: Vec::new(),
+        }
+    }
+
+    /// Adds a display hint.
+    pub fn with_field(mut self, label: &str, value: &str) -> Self {
+        self.fields.push((label.to_string(), value.to_string()));
+        self
+    }
+
+    /// Appends signed extensions and additional data to the call, turning it
+    /// into the signing payload.
+    pub fn with_extra(mut self, extra: &[u8]) -> Self {
+        self.payload.extend_from_slice(extra);
+        self
+    }
+
+    fn to_cbor(&self) -> Result<Vec<u8>, QuantusUrError> {
+        if self.payload.is_empty() {
+            return Err(QuantusUrError::EmptyPayload);
+        }
+        let mut e = Encoder::new(Vec::new());
+        e.array(4)
+            .and_then(|e| e.u8(STRUCTURED_VERSION))
+            .and_then(|e| e.bytes(&self.payload))
+            .and_then(|e| e.str(&self.title))
+            .and_then(|e| e.array(self.fields.len() as u64))
+            .map_err(cbor_error)?;
+        for (label, value) in &self.fields {
+            e.array(2)
+                .and_then(|e| e.str(label))
+                .and_then(|e| e.str(value))
+                .map_err(cbor_error)?;
+        }
+        Ok(e.into_writer())
+    }
+
+    fn from_cbor(cbor: &[u8]) -> Result<Self, QuantusUrError> {
+        let mut d = Decoder::new(cbor);
+        if d.array().map_err(cbor_error)? != Some(4) {
+            return Err(QuantusUrError::CborError(
+                "Malformed structured request".to_string(),
+            ));
+        }
+        let version = d.u8().map_err(cbor_error)?;
+        if version != STRUCTURED_VERSION {
+            return Err(QuantusUrError::CborError(format!(
+                "Unsupported structured request version {}",
+                version
+            )));
+        }
+        let mut request = StructuredSignRequest::new(
+            d.bytes().map_err(cbor_error)?.to_vec(),
+            d.str().map_err(cbor_error)?,
+        );
+        let count = d
+            .array()
+            .map_err(cbor_error)?
+            .ok_or_else(|| QuantusUrError::CborError("Indefinite field array".to_string()))?;
+        for _ in 0..count {
+            if d.array().map_err(cbor_error)? != Some(2) {
+                return Err(QuantusUrError::CborError("Malformed field".to_string()));
+            }
+            let label = d.str().map_err(cbor_error)?;
+            let value = d.str().map_err(cbor_error)?;
+            request = request.with_field(label, value);
+        }
+        if request.payload.is_empty() {
+            return Err(QuantusUrError::EmptyPayload);
+        }
+        Ok(request)
+    }
+
+    /// Encoder for animating the request.
+    pub fn encoder(&self, options: &EncodeOptions) -> Result<UrEncoder, QuantusUrError> {
+        UrEncoder::from_cbor_with_options(self.to_cbor()?, STRUCTURED_UR_TYPE, options)
+    }
+
+    /// Minimal set of parts.
+    pub fn to_parts(&self) -> Result<Vec<String>, QuantusUrError> {
+        self.encoder(&EncodeOptions::default())?
+            .into_minimal_parts()
+            .collect()
+    }
+
+    pub fn from_parts(ur_parts: &[String]) -> Result<Self, QuantusUrError> {
+        let (ur_type, cbor) = decode_message(ur_parts)?;
+        expect_ur_type(&ur_type, STRUCTURED_UR_TYPE)?;
+        Self::from_cbor(&cbor)
+    }
+
+    /// Reads the request from a completed streaming session.
+    pub fn from_decoder(decoder: &UrDecoder) -> Result<Self, QuantusUrError> {
+        let cbor = decoder.cbor_message()?;
+        expect_ur_type(decoder.ur_type().unwrap_or_default(), STRUCTURED_UR_TYPE)?;
+        Self::from_cbor(&cbor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preview::{preview_call, ArgType, CallRegistry};
+
+    fn chain() -> ChainInfo {
+        ChainInfo {
+            ss58_prefix: 189,
+            token_symbol: "QUAN".to_string(),
+            decimals: 12,
+            calls: CallIndices {
+                transfer: Some(CallIndex { pallet: 5, call: 3 }),
+                vote: Some(CallIndex {
+                    pallet: 20,
+                    call: 0,
+                }),
+                ..CallIndices::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_transfer_matches_preview() {
+        let request = chain()
+            .transfer(&[0x11; 32], 1_500_000_000_000)
+            .expect("Build failed");
+        assert_eq!(request.fields[1].1, "1.5 QUAN");
+
+        let mut registry = CallRegistry::new();
+        registry.register(
+            5,
+            "Balances",
+            3,
+            "transfer_keep_alive",
+            &[("dest", ArgType::MultiAddress), ("value", ArgType::Compact)],
+        );
+        let preview = preview_call(&request.payload, &registry).expect("No preview");
+        assert!(preview.complete);
+        assert_eq!(preview.args[1].1, "1500000000000");
+
+        let parts = request.to_parts().expect("Encoding failed");
+        let decoded = StructuredSignRequest::from_parts(&parts).expect("Decoding failed");
+        assert_eq!(decoded, request);
+    }
+
+    #[test]
+    fn test_vote_and_missing_indices() {
+        let request = chain()
+            .vote(12, true, 3, 10_000_000_000_000)
+            .expect("Build failed");
+        assert_eq!(&request.payload[..5], &[20, 0, 12 << 2, 0, 0x83]);
+        assert_eq!(request.payload.len(), 5 + 16);
+        assert_eq!(
+            request.fields[2],
+            ("Conviction".to_string(), "3x".to_string())
+        );
+        assert!(chain().vote(12, true, 7, 1).is_err());
+        assert!(matches!(
+            chain().unbond(1),
+            Err(QuantusUrError::InvalidConfiguration(_))
+        ));
+
+        let mut payload = Vec::new();
+        compact(&mut payload, 1 << 30);
+        assert_eq!(payload, [0b11, 0, 0, 0, 0x40]);
+    }
+}
@@ -0,0 +1,120 @@
+//! Heap-free encoding of single-part URs for signer firmware.
+
+use alloc::string::ToString;
+
+use crate::checksum::crc32_update;
+use crate::{QuantusUrError, MAX_FRAGMENT_LENGTH, UR_TYPE};
+
+/// Minimal bytewords: first and last letter of each of the 256 words.
+pub(crate) const MINIMAL_BYTEWORDS: &[u8; 512] = b"\
+aeadaoaxaaahamatayasbkbdbnbtbabsbebybgbwbbbzcmchcscfcycwcecackctcxclcpcndkda\
+dsdidedtdrdndwdpdmdldyeheyeoeeecenemetesftfrfnfsfmfhfzfpfwfxfyfefgflfdgagegr\
+gsgtglgwgdgygmgughgohfhghdhkhthphhhlhyhehnhsidiaieihiyioisinimjejzjnjtjljojs\
+jpjkjykpkoktkskkknkgkekikblblalylflslrlplnltloldlelulklgmnmymhmemomumwmdmtms\
+mknlnyndnsntnnnenboyoeotoxonolospdptpkpypspmplpepfpaprqdqzrerprlrorhrdrkrfry\
+rnrsrtsesasrssskswstspsosgsbsfsntotktitttdtetytltbtstptatnuyuoutueurvtvyvovl\
+vevwvavdvswlwdwmwpwewywswtwnwzwfwkykynylyaytzszoztzczezm";
+
+struct FixedWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl FixedWriter<'_> {
+    fn push(&mut self, bytes: &[u8]) {
+        self.buf[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+    }
+
+    fn push_bytewords(&mut self, data: &[u8]) {
+        for &byte in data {
+            let word = &MINIMAL_BYTEWORDS[byte as usize * 2..byte as usize * 2 + 2];
+            self.push(&[word[0].to_ascii_uppercase(), word[1].to_ascii_uppercase()]);
+        }
+    }
+}
+
+/// Encodes `payload` as a single-part sign request into `buf` without
+/// allocating, returning the written part. The output is identical to
+/// [`crate::encode_bytes`] for payloads that fit one fragment.
+///
+/// Fails with [`QuantusUrError::EmptyPayload`] for an empty payload, with
+/// [`QuantusUrError::InvalidConfiguration`] if the payload needs more than
+/// one part, and with [`QuantusUrError::MemoryLimitExceeded`] if
+/// `buf` is too small.
+pub fn encode_single_part_into<'a>(
+    payload: &[u8],
+    buf: &'a mut [u8],
+) -> Result<&'a str, QuantusUrError> {
+    if payload.is_empty() {
+        return Err(QuantusUrError::EmptyPayload);
+    }
+    // CBOR byte string head; payloads fitting one fragment need at most 2 bytes
+    let head: &[u8] = if payload.len() < 24 {
+        &[0x40 | payload.len() as u8]
+    } else {
+        &[0x58, payload.len() as u8]
+    };
+    if head.len() + payload.len() > MAX_FRAGMENT_LENGTH {
+        return Err(QuantusUrError::InvalidConfiguration(
+            "Payload does not fit a single part".to_string(),
+        ));
+    }
+
+    let required = 4 + UR_TYPE.len() + (head.len() + payload.len() + 4) * 2;
+    if buf.len() < required {
+        return Err(QuantusUrError::MemoryLimitExceeded {
+            limit: buf.len(),
+            required,
+        });
+    }
+
+    let checksum = !crc32_update(crc32_update(!0, head), payload);
+    let mut writer = FixedWriter { buf, len: 0 };
+    writer.push(b"UR:");
+    for &b in UR_TYPE.as_bytes() {
+        writer.push(&[b.to_ascii_uppercase()]);
+    }
+    writer.push(b"/");
+    writer.push_bytewords(head);
+    writer.push_bytewords(payload);
+    writer.push_bytewords(&checksum.to_be_bytes());
+
+    let FixedWriter { buf, len } = writer;
+    core::str::from_utf8(&buf[..len]).map_err(|e| QuantusUrError::UrError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decode_bytes, encode_bytes};
+    use alloc::vec;
+
+    #[test]
+    fn test_matches_encode_bytes() {
+        let mut buf = [0u8; 512];
+        for len in [1usize, 23, 24, 100, 198] {
+            let payload: alloc::vec::Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let part = encode_single_part_into(&payload, &mut buf).expect("Encoding failed");
+            assert_eq!(part, encode_bytes(&payload).expect("Encoding failed")[0]);
+            assert_eq!(
+                decode_bytes(&vec![part.to_string()]).expect("Decoding failed"),
+                payload
+            );
+        }
+    }
+
+    #[test]
+    fn test_limits() {
+        let mut small = [0u8; 16];
+        assert!(matches!(
+            encode_single_part_into(b"signature", &mut small),
+            Err(QuantusUrError::MemoryLimitExceeded { limit: 16, .. })
+        ));
+        let mut buf = [0u8; 1024];
+        assert!(matches!(
+            encode_single_part_into(&[0u8; 199], &mut buf),
+            Err(QuantusUrError::InvalidConfiguration(_))
+        ));
+    }
+}
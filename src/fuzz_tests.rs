@@ -0,0 +1,155 @@
+//! Seeded fuzzing of every decode path with corrupted and forged parts.
+//!
+//! Signer firmware decodes untrusted scans, where a panic ends the session,
+//! so these tests only assert that nothing panics: any `Ok` or `Err` is fine.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use minicbor::Encoder;
+use ur::bytewords::{self, Style};
+
+use crate::account::AccountKey;
+use crate::bytewords::verify_part_checksum;
+use crate::response::SignResponse;
+use crate::rng::SeededRandom;
+use crate::transfer::TransferReceiver;
+use crate::{
+    decode_bytes, decode_partial, encode_bytes, DecodeOptions, UrDecoder, UrFlavor, UrPart,
+};
+
+const ITERATIONS: usize = 2000;
+
+/// Seeded stream, so failures reproduce from the iteration number.
+struct Rng(SeededRandom);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(SeededRandom::new(seed))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n.max(1) as u64) as usize
+    }
+
+    /// Mostly edge values, which is where header arithmetic breaks.
+    fn field(&mut self) -> u32 {
+        match self.below(6) {
+            0 => 0,
+            1 => 1,
+            2 => u32::MAX,
+            3 => self.next() as u32,
+            _ => self.below(40) as u32,
+        }
+    }
+}
+
+fn payload(rng: &mut Rng) -> Vec<u8> {
+    let len = 1 + rng.below(1500);
+    (0..len).map(|_| rng.next() as u8).collect()
+}
+
+/// Flips, truncates, duplicates or inserts characters of a part string.
+fn mutate(part: &str, rng: &mut Rng) -> String {
+    let mut bytes = part.as_bytes().to_vec();
+    for _ in 0..1 + rng.below(3) {
+        let at = rng.below(bytes.len());
+        match rng.below(4) {
+            0 if !bytes.is_empty() => bytes[at] = b"AEDLPSYZ0-/:"[rng.below(12)],
+            1 => bytes.truncate(at),
+            2 => bytes.insert(at, b'/'),
+            _ => bytes.extend_from_slice(b"-1"),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// A multi-part UR with a valid bytewords checksum but arbitrary header.
+fn forged_part(rng: &mut Rng) -> String {
+    let (sequence, count) = (rng.field(), rng.field());
+    let data: Vec<u8> = (0..rng.below(64)).map(|_| rng.next() as u8).collect();
+    let mut e = Encoder::new(Vec::new());
+    e.array(5)
+        .and_then(|e| e.u32(sequence))
+        .and_then(|e| e.u32(count))
+        .and_then(|e| e.u32(rng.field()))
+        .and_then(|e| e.u32(rng.field()))
+        .and_then(|e| e.bytes(&data))
+        .expect("CBOR encoding into a Vec cannot fail");
+    let body = bytewords::encode(&e.into_writer(), Style::Minimal);
+    format!("ur:quantus-sign-request/{}-{}/{}", sequence, count, body)
+}
+
+fn decode_everything(parts: &[String]) {
+    let _ = decode_bytes(parts);
+    let _ = decode_partial(parts);
+    let _ = SignResponse::from_parts(parts);
+    let _ = AccountKey::from_parts(parts);
+    let _ = TransferReceiver::new().receive(parts);
+    for options in [
+        DecodeOptions::default(),
+        DecodeOptions {
+            strict_cbor: true,
+            skip_invalid_parts: true,
+            flavor: UrFlavor::BcUr,
+            memory_limit: Some(4096),
+            ..DecodeOptions::default()
+        },
+    ] {
+        let mut decoder = UrDecoder::with_options(options);
+        for part in parts {
+            let _ = decoder.receive_event(part);
+            let _ = decoder.missing_parts();
+            let _ = decoder.pace_hint();
+        }
+        let _ = decoder.message();
+        let _ = decoder.tagged_message();
+    }
+    for part in parts {
+        let _ = part.parse::<UrPart>();
+        let _ = verify_part_checksum(part);
+    }
+}
+
+#[test]
+fn test_mutated_parts_never_panic() {
+    let mut rng = Rng::new(0x6675_7a7a);
+    for _ in 0..ITERATIONS {
+        let mut parts = encode_bytes(&payload(&mut rng)).expect("Encoding failed");
+        let index = rng.below(parts.len());
+        parts[index] = mutate(&parts[index], &mut rng);
+        decode_everything(&parts);
+    }
+}
+
+#[test]
+fn test_forged_headers_never_panic() {
+    let mut rng = Rng::new(0x6865_6164);
+    for _ in 0..ITERATIONS {
+        let mut parts = encode_bytes(&payload(&mut rng)).expect("Encoding failed");
+        // Forged parts both open sessions and land inside genuine ones
+        let forged = forged_part(&mut rng);
+        if rng.below(2) == 0 {
+            parts.insert(0, forged);
+        } else {
+            parts.push(forged);
+        }
+        decode_everything(&parts);
+    }
+}
+
+#[test]
+fn test_random_text_never_panics() {
+    let mut rng = Rng::new(0x7465_7874);
+    for _ in 0..ITERATIONS {
+        let len = rng.below(120);
+        let text: String = (0..len)
+            .map(|_| char::from(b"ur:/-0123456789aeadlpsyzUR"[rng.below(26)]))
+            .collect();
+        decode_everything(&[text]);
+    }
+}
@@ -0,0 +1,77 @@
+//! Hex dumps of payloads for diagnostics and mismatch reports.
+
+use alloc::string::{String, ToString};
+use core::fmt;
+
+const BYTES_PER_LINE: usize = 16;
+
+/// Formats bytes as a classic hex dump: offset, 16 bytes of hex split in two
+/// groups of eight, and the printable ASCII characters.
+///
+/// ```text
+/// 00000000  48 65 6c 6c 6f 2c 20 51  75 61 6e 74 75 73 21     |Hello, Quantus!|
+/// ```
+#[derive(Clone, Copy)]
+pub struct HexDump<'a>(pub &'a [u8]);
+
+impl fmt::Display for HexDump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (line, chunk) in self.0.chunks(BYTES_PER_LINE).enumerate() {
+            if line > 0 {
+                f.write_str("\n")?;
+            }
+            write!(f, "{:08x} ", line * BYTES_PER_LINE)?;
+            for i in 0..BYTES_PER_LINE {
+                if i % 8 == 0 {
+                    f.write_str(" ")?;
+                }
+                match chunk.get(i) {
+                    Some(byte) => write!(f, "{:02x} ", byte)?,
+                    None => f.write_str("   ")?,
+                }
+            }
+            f.write_str(" |")?;
+            for &byte in chunk {
+                let c = if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                };
+                write!(f, "{}", c)?;
+            }
+            f.write_str("|")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for HexDump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// Hex dump of `data` as a string, see [`HexDump`].
+pub fn hex_dump(data: &[u8]) -> String {
+    HexDump(data).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_dump_layout() {
+        let dump = hex_dump(b"Hello, Quantus!\nsecond line\x00");
+        let lines: alloc::vec::Vec<&str> = dump.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0],
+            "00000000  48 65 6c 6c 6f 2c 20 51  75 61 6e 74 75 73 21 0a  |Hello, Quantus!.|"
+        );
+        assert!(lines[1].starts_with("00000010  73 65 63"));
+        assert!(lines[1].ends_with("|second line.|"));
+        assert_eq!(lines[0].find('|'), lines[1].find('|'));
+        assert_eq!(hex_dump(&[]), "");
+    }
+}
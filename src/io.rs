@@ -0,0 +1,159 @@
+//! `std::io` entry points for CLIs and server-side tools.
+//!
+//! Fountain coding needs the whole CBOR message, so encoding still holds the
+//! payload in memory once; the parts themselves are generated and written one
+//! at a time, and decoding consumes parts line by line as they are read.
+
+use std::io::{BufRead, Read, Write};
+use std::string::ToString;
+use std::vec::Vec;
+#[cfg(feature = "tokio")]
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt,
+};
+
+use crate::{EncodeOptions, QuantusUrError, UrDecoder, UrEncoder};
+
+fn io_error(e: std::io::Error) -> QuantusUrError {
+    QuantusUrError::IoError(e.to_string())
+}
+
+/// Reads the payload from `reader` and returns an encoder for it.
+pub fn encode_reader<R: Read>(
+    mut reader: R,
+    options: &EncodeOptions,
+) -> Result<UrEncoder, QuantusUrError> {
+    let mut payload = Vec::new();
+    reader.read_to_end(&mut payload).map_err(io_error)?;
+    UrEncoder::with_options(&payload, options)
+}
+
+/// Encodes the payload read from `reader` and writes the minimal set of parts
+/// to `writer`, one per line. Returns the number of parts written.
+pub fn encode_to_writer<R: Read, W: Write>(
+    reader: R,
+    mut writer: W,
+    options: &EncodeOptions,
+) -> Result<usize, QuantusUrError> {
+    let mut count = 0;
+    for part in encode_reader(reader, options)?.into_minimal_parts() {
+        writeln!(writer, "{}", part?).map_err(io_error)?;
+        count += 1;
+    }
+    writer.flush().map_err(io_error)?;
+    Ok(count)
+}
+
+/// Feeds parts read line by line from `reader` to a decoder, stopping as soon
+/// as the message is complete, and writes the payload to `writer`. Blank
+/// lines are skipped. Returns the payload length.
+pub fn decode_to_writer<R: BufRead, W: Write>(
+    reader: R,
+    mut writer: W,
+) -> Result<usize, QuantusUrError> {
+    let mut decoder = UrDecoder::new();
+    for line in reader.lines() {
+        let line = line.map_err(io_error)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        decoder.receive(&line)?;
+        if decoder.is_complete() {
+            break;
+        }
+    }
+    let payload = decoder.message()?;
+    writer.write_all(&payload).map_err(io_error)?;
+    writer.flush().map_err(io_error)?;
+    Ok(payload.len())
+}
+
+/// Async version of [`encode_reader`].
+#[cfg(feature = "tokio")]
+pub async fn encode_async_reader<R: AsyncRead + Unpin>(
+    mut reader: R,
+    options: &EncodeOptions,
+) -> Result<UrEncoder, QuantusUrError> {
+    let mut payload = Vec::new();
+    reader.read_to_end(&mut payload).await.map_err(io_error)?;
+    UrEncoder::with_options(&payload, options)
+}
+
+/// Async version of [`encode_to_writer`].
+#[cfg(feature = "tokio")]
+pub async fn encode_to_async_writer<R: AsyncRead + Unpin, W: AsyncWrite + Unpin>(
+    reader: R,
+    mut writer: W,
+    options: &EncodeOptions,
+) -> Result<usize, QuantusUrError> {
+    let mut count = 0;
+    for part in encode_async_reader(reader, options)
+        .await?
+        .into_minimal_parts()
+    {
+        let mut line = part?;
+        line.push('\n');
+        writer.write_all(line.as_bytes()).await.map_err(io_error)?;
+        count += 1;
+    }
+    writer.flush().await.map_err(io_error)?;
+    Ok(count)
+}
+
+/// Async version of [`decode_to_writer`].
+#[cfg(feature = "tokio")]
+pub async fn decode_to_async_writer<R: AsyncBufRead + Unpin, W: AsyncWrite + Unpin>(
+    reader: R,
+    mut writer: W,
+) -> Result<usize, QuantusUrError> {
+    let mut decoder = UrDecoder::new();
+    let mut lines = reader.lines();
+    while let Some(line) = lines.next_line().await.map_err(io_error)? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        decoder.receive(&line)?;
+        if decoder.is_complete() {
+            break;
+        }
+    }
+    let payload = decoder.message()?;
+    writer.write_all(&payload).await.map_err(io_error)?;
+    writer.flush().await.map_err(io_error)?;
+    Ok(payload.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reader_writer_roundtrip() {
+        let payload: Vec<u8> = (0..3000u32).map(|i| (i % 251) as u8).collect();
+        let mut text = Vec::new();
+        let count = encode_to_writer(&payload[..], &mut text, &EncodeOptions::default())
+            .expect("Encoding failed");
+        assert_eq!(text.iter().filter(|&&b| b == b'\n').count(), count);
+
+        let mut decoded = Vec::new();
+        let len = decode_to_writer(&text[..], &mut decoded).expect("Decoding failed");
+        assert_eq!(len, payload.len());
+        assert_eq!(decoded, payload);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_async_roundtrip() {
+        let payload: Vec<u8> = (0..3000u32).map(|i| (i % 251) as u8).collect();
+        let mut text = Vec::new();
+        encode_to_async_writer(&payload[..], &mut text, &EncodeOptions::default())
+            .await
+            .expect("Encoding failed");
+
+        let mut decoded = Vec::new();
+        decode_to_async_writer(&text[..], &mut decoded)
+            .await
+            .expect("Decoding failed");
+        assert_eq!(decoded, payload);
+    }
+}
@@ -0,0 +1,262 @@
+//! Keystone-style sign-request envelopes, so Keystone-family hardware can
+//! sign Quantus payloads without a firmware fork.
+//!
+//! The envelope is a CBOR map using the key layout of Keystone's
+//! `eth-sign-request`: `1` the request id (a UUID under tag 37), `2` the data
+//! to sign, `3` the data type and `7` the origin (the requesting app). The
+//! signature comes back as a map of `1` the request id, `2` the signature and
+//! `3` the origin. Unknown keys are skipped when decoding.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use minicbor::data::{Tag, Type};
+use minicbor::{Decoder, Encoder};
+
+use crate::cbor::{cbor_error, expect_ur_type};
+use crate::rng::{random_array, RandomSource};
+use crate::{decode_message, encode_message, QuantusUrError, UR_TYPE};
+
+pub const KEYSTONE_REQUEST_UR_TYPE: &str = "keystone-sign-request";
+pub const KEYSTONE_SIGNATURE_UR_TYPE: &str = "keystone-signature";
+
+/// Data type for a Quantus signing payload (a SCALE-encoded extrinsic payload).
+pub const DATA_TYPE_TRANSACTION: u32 = 1;
+
+const UUID_TAG: u64 = 37;
+
+fn encode_request_id(e: &mut Encoder<Vec<u8>>, id: &[u8; 16]) -> Result<(), QuantusUrError> {
+    e.tag(Tag::Unassigned(UUID_TAG))
+        .and_then(|e| e.bytes(id))
+        .map_err(cbor_error)?;
+    Ok(())
+}
+
+fn decode_request_id(d: &mut Decoder) -> Result<[u8; 16], QuantusUrError> {
+    if d.datatype().map_err(cbor_error)? == Type::Tag {
+        let tag = u64::from(d.tag().map_err(cbor_error)?);
+        if tag != UUID_TAG {
+            return Err(QuantusUrError::CborError(format!(
+                "Unexpected tag {} on request id",
+                tag
+            )));
+        }
+    }
+    d.bytes()
+        .map_err(cbor_error)?
+        .try_into()
+        .map_err(|_| QuantusUrError::CborError("Request id is not a UUID".to_string()))
+}
+
+fn map_len(d: &mut Decoder) -> Result<u64, QuantusUrError> {
+    d.map()
+        .map_err(cbor_error)?
+        .ok_or_else(|| QuantusUrError::CborError("Indefinite-length map".to_string()))
+}
+
+/// A payload wrapped in a Keystone sign-request envelope.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeystoneSignRequest {
+    pub request_id: [u8; 16],
+    pub sign_data: Vec<u8>,
+    pub data_type: u32,
+    pub origin: Option<String>,
+}
+
+impl KeystoneSignRequest {
+    /// Wraps a signing payload; `request_id` correlates the signature.
+    pub fn new(request_id: [u8; 16], sign_data: &[u8]) -> Self {
+        KeystoneSignRequest {
+            request_id,
+            sign_data: sign_data.to_vec(),
+            data_type: DATA_TYPE_TRANSACTION,
+            origin: None,
+        }
+    }
+
+    /// Like [`KeystoneSignRequest::new`], with a random version 4 UUID as the
+    /// request id.
+    pub fn with_random_id(
+        sign_data: &[u8],
+        rng: &mut impl RandomSource,
+    ) -> Result<Self, QuantusUrError> {
+        let mut request_id: [u8; 16] = random_array(rng)?;
+        request_id[6] = request_id[6] & 0x0f | 0x40;
+        request_id[8] = request_id[8] & 0x3f | 0x80;
+        Ok(Self::new(request_id, sign_data))
+    }
+
+    /// Names the requesting app, shown by the device.
+    pub fn with_origin(mut self, origin: &str) -> Self {
+        self.origin = Some(origin.to_string());
+        self
+    }
+
+    fn to_cbor(&self) -> Result<Vec<u8>, QuantusUrError> {
+        let mut e = Encoder::new(Vec::new());
+        let len = if self.origin.is_some() { 4 } else { 3 };
+        e.map(len).and_then(|e| e.u8(1)).map_err(cbor_error)?;
+        encode_request_id(&mut e, &self.request_id)?;
+        e.u8(2)
+            .and_then(|e| e.bytes(&self.sign_data))
+            .and_then(|e| e.u8(3))
+            .and_then(|e| e.u32(self.data_type))
+            .map_err(cbor_error)?;
+        if let Some(origin) = &self.origin {
+            e.u8(7).and_then(|e| e.str(origin)).map_err(cbor_error)?;
+        }
+        Ok(e.into_writer())
+    }
+
+    fn from_cbor(cbor: &[u8]) -> Result<Self, QuantusUrError> {
+        let mut d = Decoder::new(cbor);
+        let (mut request_id, mut sign_data, mut data_type, mut origin) = (None, None, None, None);
+        for _ in 0..map_len(&mut d)? {
+            match d.u32().map_err(cbor_error)? {
+                1 => request_id = Some(decode_request_id(&mut d)?),
+                2 => sign_data = Some(d.bytes().map_err(cbor_error)?.to_vec()),
+                3 => data_type = Some(d.u32().map_err(cbor_error)?),
+                7 => origin = Some(d.str().map_err(cbor_error)?.to_string()),
+                _ => d.skip().map_err(cbor_error)?,
+            }
+        }
+        let missing = |field: &str| QuantusUrError::CborError(format!("Missing {}", field));
+        Ok(KeystoneSignRequest {
+            request_id: request_id.ok_or_else(|| missing("request id"))?,
+            sign_data: sign_data.ok_or_else(|| missing("sign data"))?,
+            data_type: data_type.unwrap_or(DATA_TYPE_TRANSACTION),
+            origin,
+        })
+    }
+
+    pub fn to_parts(&self) -> Result<Vec<String>, QuantusUrError> {
+        encode_message(self.to_cbor()?, KEYSTONE_REQUEST_UR_TYPE)
+    }
+
+    pub fn from_parts(ur_parts: &[String]) -> Result<Self, QuantusUrError> {
+        let (ur_type, cbor) = decode_message(ur_parts)?;
+        expect_ur_type(&ur_type, KEYSTONE_REQUEST_UR_TYPE)?;
+        Self::from_cbor(&cbor)
+    }
+}
+
+/// Signature returned by a Keystone device for a [`KeystoneSignRequest`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeystoneSignature {
+    pub request_id: [u8; 16],
+    pub signature: Vec<u8>,
+    pub origin: Option<String>,
+}
+
+impl KeystoneSignature {
+    /// Whether this signature answers `request`.
+    pub fn answers(&self, request: &KeystoneSignRequest) -> bool {
+        self.request_id == request.request_id
+    }
+
+    fn to_cbor(&self) -> Result<Vec<u8>, QuantusUrError> {
+        let mut e = Encoder::new(Vec::new());
+        let len = if self.origin.is_some() { 3 } else { 2 };
+        e.map(len).and_then(|e| e.u8(1)).map_err(cbor_error)?;
+        encode_request_id(&mut e, &self.request_id)?;
+        e.u8(2)
+            .and_then(|e| e.bytes(&self.signature))
+            .map_err(cbor_error)?;
+        if let Some(origin) = &self.origin {
+            e.u8(3).and_then(|e| e.str(origin)).map_err(cbor_error)?;
+        }
+        Ok(e.into_writer())
+    }
+
+    fn from_cbor(cbor: &[u8]) -> Result<Self, QuantusUrError> {
+        let mut d = Decoder::new(cbor);
+        let (mut request_id, mut signature, mut origin) = (None, None, None);
+        for _ in 0..map_len(&mut d)? {
+            match d.u32().map_err(cbor_error)? {
+                1 => request_id = Some(decode_request_id(&mut d)?),
+                2 => signature = Some(d.bytes().map_err(cbor_error)?.to_vec()),
+                3 => origin = Some(d.str().map_err(cbor_error)?.to_string()),
+                _ => d.skip().map_err(cbor_error)?,
+            }
+        }
+        let missing = |field: &str| QuantusUrError::CborError(format!("Missing {}", field));
+        Ok(KeystoneSignature {
+            request_id: request_id.ok_or_else(|| missing("request id"))?,
+            signature: signature.ok_or_else(|| missing("signature"))?,
+            origin,
+        })
+    }
+
+    pub fn to_parts(&self) -> Result<Vec<String>, QuantusUrError> {
+        encode_message(self.to_cbor()?, KEYSTONE_SIGNATURE_UR_TYPE)
+    }
+
+    pub fn from_parts(ur_parts: &[String]) -> Result<Self, QuantusUrError> {
+        let (ur_type, cbor) = decode_message(ur_parts)?;
+        expect_ur_type(&ur_type, KEYSTONE_SIGNATURE_UR_TYPE)?;
+        Self::from_cbor(&cbor)
+    }
+}
+
+/// Signing payload of either a native `quantus-sign-request` or a Keystone
+/// envelope, for signers accepting both.
+pub fn decode_any_sign_request(ur_parts: &[String]) -> Result<Vec<u8>, QuantusUrError> {
+    let (ur_type, cbor) = decode_message(ur_parts)?;
+    match ur_type.as_str() {
+        UR_TYPE => crate::payload_from_cbor(&cbor, &crate::DecodeOptions::default()),
+        KEYSTONE_REQUEST_UR_TYPE => Ok(KeystoneSignRequest::from_cbor(&cbor)?.sign_data),
+        other => Err(QuantusUrError::UrError(format!(
+            "Expected a sign request, got {}",
+            other
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encode_bytes;
+
+    #[test]
+    fn test_request_roundtrip() {
+        let payload: Vec<u8> = (0..600u32).map(|i| (i % 251) as u8).collect();
+        let request = KeystoneSignRequest::new([0x2a; 16], &payload).with_origin("Quantus Wallet");
+        let parts = request.to_parts().expect("Encoding failed");
+        assert!(parts[0].starts_with("UR:KEYSTONE-SIGN-REQUEST/"));
+        assert_eq!(
+            KeystoneSignRequest::from_parts(&parts).expect("Decoding failed"),
+            request
+        );
+
+        assert_eq!(
+            decode_any_sign_request(&parts).expect("Decoding failed"),
+            payload
+        );
+        let native = encode_bytes(&payload).expect("Encoding failed");
+        assert_eq!(
+            decode_any_sign_request(&native).expect("Decoding failed"),
+            payload
+        );
+    }
+
+    #[test]
+    fn test_signature_correlates_request() {
+        let request = KeystoneSignRequest::new([7; 16], b"payload");
+        let signature = KeystoneSignature {
+            request_id: [7; 16],
+            signature: alloc::vec![0x55; 64],
+            origin: None,
+        };
+        let parts = signature.to_parts().expect("Encoding failed");
+        let decoded = KeystoneSignature::from_parts(&parts).expect("Decoding failed");
+        assert!(decoded.answers(&request));
+        assert!(KeystoneSignRequest::from_parts(&parts).is_err());
+
+        let mut rng = crate::rng::SeededRandom::new(1);
+        let other = KeystoneSignRequest::with_random_id(b"payload", &mut rng).expect("RNG failed");
+        assert_eq!(other.request_id[6] >> 4, 4);
+        assert!(!decoded.answers(&other));
+    }
+}
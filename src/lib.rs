@@ -1,65 +1,294 @@
 #![no_std]
 
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
+use alloc::collections::BTreeSet;
+use alloc::format;
 use alloc::string::{String, ToString};
-use alloc::vec;
 use alloc::vec::Vec;
-use hex;
-use minicbor::{bytes::ByteVec, Decoder};
-use ur_parse_lib::keystone_ur_encoder::probe_encode;
+use minicbor::data::Type;
+use minicbor::Decoder;
+
+use crate::cbor::cbor_error;
+
+#[macro_use]
+mod trace;
+
+pub mod account;
+#[cfg(feature = "airgap")]
+pub mod airgap;
+pub mod auth;
+pub mod base45;
+pub mod bundle;
+pub mod bytewords;
+pub mod capacity;
+mod cbor;
+mod checksum;
+pub mod chunked;
+mod classify;
+pub mod clock;
+pub mod config;
+pub mod conversation;
+mod ct;
+mod decoder;
+pub mod density;
+#[cfg(test)]
+mod differential_tests;
+#[cfg(feature = "egui")]
+mod egui_widget;
+mod encoder;
+#[cfg(feature = "ml-dsa")]
+pub mod envelope;
+#[cfg(feature = "substrate")]
+pub mod extrinsic;
+mod fixed;
+#[cfg(test)]
+mod fuzz_tests;
+pub mod hexdump;
+#[cfg(feature = "std")]
+pub mod io;
+pub mod keystone;
+mod merkle;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod mux;
+pub mod optimizer;
+mod options;
+mod part;
+#[cfg(feature = "passphrase")]
+pub mod passphrase;
+pub mod pending;
+#[cfg(feature = "substrate")]
+pub mod preview;
+#[cfg(feature = "qr")]
+pub mod qr;
+#[cfg(feature = "raptorq")]
+pub mod raptor;
+pub mod response;
+pub mod rng;
+#[cfg(all(feature = "serde", feature = "hex"))]
+pub mod schema;
+pub mod session;
+#[cfg(feature = "std")]
+mod shared;
+pub mod split;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod transfer;
+pub mod transport;
+#[cfg(feature = "substrate")]
+pub mod uos;
+#[cfg(feature = "ml-dsa")]
+mod verify;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "std")]
+pub mod worker;
+
+pub use classify::{classify_payload, PayloadKind};
+pub use ct::constant_time_eq;
+pub use decoder::{
+    DecodeEvent, DecodeObserver, DecodeStats, DecodeStatus, PaceHint, ScanTiming, SessionReport,
+    StallReason, UrDecoder, DEFAULT_STALL_THRESHOLD, MAX_REPORTED_ARRIVALS,
+};
+#[cfg(feature = "egui")]
+pub use egui_widget::UrAnimation;
+pub use encoder::{MinimalParts, PartWriter, UrEncoder, MAX_FRAME_SKIP};
+pub use fixed::encode_single_part_into;
+pub use options::{
+    DecodeOptions, EncodeOptions, EncodingMode, FountainParams, UrFlavor, MAX_FOUNTAIN_SEED,
+    MAX_QR_FRAGMENT_LENGTH, MIN_FRAGMENT_LENGTH,
+};
+pub use part::{UrPart, MAX_SEQUENCE_COUNT};
+#[cfg(feature = "std")]
+pub use shared::SharedDecoder;
+#[cfg(feature = "ml-dsa")]
+pub use verify::verify_ml_dsa;
 
 const UR_TYPE: &str = "quantus-sign-request";
 const MAX_FRAGMENT_LENGTH: usize = 200;
 
 #[derive(Debug)]
 pub enum QuantusUrError {
+    #[cfg(feature = "hex")]
     HexError(hex::FromHexError),
     UrError(String),
     CborError(String),
+    BytewordsError(String),
+    TransferError(String),
+    ChunkVerificationFailed(u32),
+    MemoryLimitExceeded {
+        limit: usize,
+        required: usize,
+    },
+    QrError(String),
+    InvalidConfiguration(String),
+    IoError(String),
+    ResponseMismatch,
+    SessionExpired,
+    SchemaError(String),
+    SignatureVerificationFailed(String),
+    /// A part body failed its bytewords CRC32, i.e. it was corrupted in transport.
+    PartChecksumMismatch {
+        expected: u32,
+        actual: u32,
+    },
+    /// The decode session passed its deadline; carries its final stats.
+    TimedOut(DecodeStats),
+    /// A part declares a different fragment count than the session started with.
+    InconsistentSequence {
+        expected: u32,
+        actual: u32,
+    },
+    /// A part's MAC is missing or does not verify under the session key.
+    PartAuthenticationFailed,
+    /// Sign requests must carry a payload; empty ones are refused on both
+    /// sides rather than signed.
+    EmptyPayload,
+    /// Wrong passphrase, or the encrypted message was tampered with.
+    DecryptionFailed,
+    /// The trust policy refused the envelope's sender.
+    SenderNotTrusted,
     Incomplete,
 }
 
+/// With `compact-errors`, errors display as their [`QuantusUrError::kind`]
+/// only, which keeps the message formatting code out of firmware images.
+#[cfg(feature = "compact-errors")]
+impl core::fmt::Display for QuantusUrError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.kind())
+    }
+}
+
+#[cfg(not(feature = "compact-errors"))]
 impl core::fmt::Display for QuantusUrError {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
+            #[cfg(feature = "hex")]
             QuantusUrError::HexError(e) => write!(f, "Hex decoding error: {}", e),
             QuantusUrError::UrError(msg) => write!(f, "UR error: {}", msg),
             QuantusUrError::CborError(msg) => write!(f, "CBOR error: {}", msg),
+            QuantusUrError::BytewordsError(msg) => write!(f, "Bytewords error: {}", msg),
+            QuantusUrError::TransferError(msg) => write!(f, "Transfer error: {}", msg),
+            QuantusUrError::ChunkVerificationFailed(index) => {
+                write!(f, "Chunk {} failed Merkle verification", index)
+            }
+            QuantusUrError::MemoryLimitExceeded { limit, required } => write!(
+                f,
+                "Decode session needs {} bytes, exceeding the {} byte memory limit",
+                required, limit
+            ),
+            QuantusUrError::QrError(msg) => write!(f, "QR error: {}", msg),
+            QuantusUrError::InvalidConfiguration(msg) => {
+                write!(f, "Invalid configuration: {}", msg)
+            }
+            QuantusUrError::IoError(msg) => write!(f, "I/O error: {}", msg),
+            QuantusUrError::ResponseMismatch => {
+                write!(f, "Response does not belong to this request")
+            }
+            QuantusUrError::SessionExpired => write!(f, "Session expired"),
+            QuantusUrError::SchemaError(msg) => write!(f, "Schema violation: {}", msg),
+            QuantusUrError::SignatureVerificationFailed(msg) => {
+                write!(f, "Signature verification failed: {}", msg)
+            }
+            QuantusUrError::PartChecksumMismatch { expected, actual } => write!(
+                f,
+                "Part checksum mismatch: expected {:08x}, computed {:08x}",
+                expected, actual
+            ),
+            QuantusUrError::TimedOut(stats) => {
+                write!(
+                    f,
+                    "Decode session timed out after {} parts",
+                    stats.received_parts
+                )?;
+                match stats.expected_parts {
+                    Some(expected) => write!(f, " of {}", expected),
+                    None => Ok(()),
+                }
+            }
+            QuantusUrError::InconsistentSequence { expected, actual } => write!(
+                f,
+                "Part declares {} fragments but the session has {}",
+                actual, expected
+            ),
+            QuantusUrError::PartAuthenticationFailed => {
+                write!(f, "Part failed authentication")
+            }
+            QuantusUrError::EmptyPayload => write!(f, "Payload is empty"),
+            QuantusUrError::DecryptionFailed => {
+                write!(
+                    f,
+                    "Decryption failed: wrong passphrase or corrupted message"
+                )
+            }
+            QuantusUrError::SenderNotTrusted => write!(f, "Sender is not trusted"),
             QuantusUrError::Incomplete => write!(f, "Decoding incomplete"),
         }
     }
 }
 
-fn encode_internal(payload: &[u8]) -> Result<Vec<String>, QuantusUrError> {
-    let cbor = minicbor::to_vec(ByteVec::from(payload.to_vec()))
-        .map_err(|e| QuantusUrError::CborError(e.to_string()))?;
+impl QuantusUrError {
+    /// Stable identifier of the error variant, for logs and diagnostics.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "hex")]
+            QuantusUrError::HexError(_) => "hex",
+            QuantusUrError::UrError(_) => "ur",
+            QuantusUrError::CborError(_) => "cbor",
+            QuantusUrError::BytewordsError(_) => "bytewords",
+            QuantusUrError::TransferError(_) => "transfer",
+            QuantusUrError::ChunkVerificationFailed(_) => "chunk_verification_failed",
+            QuantusUrError::MemoryLimitExceeded { .. } => "memory_limit_exceeded",
+            QuantusUrError::QrError(_) => "qr",
+            QuantusUrError::InvalidConfiguration(_) => "invalid_configuration",
+            QuantusUrError::IoError(_) => "io",
+            QuantusUrError::ResponseMismatch => "response_mismatch",
+            QuantusUrError::SessionExpired => "session_expired",
+            QuantusUrError::SchemaError(_) => "schema",
+            QuantusUrError::SignatureVerificationFailed(_) => "signature_verification_failed",
+            QuantusUrError::PartChecksumMismatch { .. } => "part_checksum_mismatch",
+            QuantusUrError::TimedOut(_) => "timed_out",
+            QuantusUrError::InconsistentSequence { .. } => "inconsistent_sequence",
+            QuantusUrError::PartAuthenticationFailed => "part_authentication_failed",
+            QuantusUrError::EmptyPayload => "empty_payload",
+            QuantusUrError::DecryptionFailed => "decryption_failed",
+            QuantusUrError::SenderNotTrusted => "sender_not_trusted",
+            QuantusUrError::Incomplete => "incomplete",
+        }
+    }
+}
 
-    let result = probe_encode(&cbor, MAX_FRAGMENT_LENGTH, UR_TYPE.to_string())
-        .map_err(|e| QuantusUrError::UrError(e.to_string()))?;
+/// Errors serialize as data: `{ "kind": ..., "message": ... }`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for QuantusUrError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
 
-    if !result.is_multi_part {
-        return Ok(vec![result.data.to_uppercase()]);
+        let mut state = serializer.serialize_struct("QuantusUrError", 2)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
     }
+}
 
-    let mut encoder = result
-        .encoder
-        .ok_or_else(|| QuantusUrError::UrError("Multi-part but no encoder returned".to_string()))?;
-
-    let count = encoder.fragment_count();
-    let mut parts = Vec::with_capacity(count);
-    parts.push(result.data.to_uppercase());
+fn encode_message(cbor: Vec<u8>, ur_type: &str) -> Result<Vec<String>, QuantusUrError> {
+    collect_minimal_set(UrEncoder::from_cbor(cbor, ur_type)?)
+}
 
-    while parts.len() < count {
-        let part = encoder
-            .next_part()
-            .map_err(|e| QuantusUrError::UrError(e.to_string()))?;
-        parts.push(part.to_uppercase());
-    }
+fn encode_internal(payload: &[u8]) -> Result<Vec<String>, QuantusUrError> {
+    trace_span!("encode", payload_len = payload.len());
+    collect_minimal_set(UrEncoder::new(payload)?)
+}
 
-    Ok(parts)
+fn collect_minimal_set(encoder: UrEncoder) -> Result<Vec<String>, QuantusUrError> {
+    encoder.into_minimal_parts().collect()
 }
 
+#[cfg(feature = "hex")]
 pub fn encode_hex(hex_payload: &str) -> Result<Vec<String>, QuantusUrError> {
     let payload = hex::decode(hex_payload).map_err(QuantusUrError::HexError)?;
     encode_internal(&payload)
@@ -69,83 +298,296 @@ pub fn encode_bytes(payload: &[u8]) -> Result<Vec<String>, QuantusUrError> {
     encode_internal(payload)
 }
 
-fn decode_internal(ur_parts: &[String]) -> Result<Vec<u8>, QuantusUrError> {
+/// Largest ratio accepted by [`encode_with_redundancy`].
+pub const MAX_REDUNDANCY: f32 = 10.0;
+
+/// Encodes the payload into `redundancy` times the minimal number of parts
+/// (rounded up), for static exports such as GIFs or printed sheets that must
+/// survive unreadable frames. Ratios outside `1.0..=`[`MAX_REDUNDANCY`] fail
+/// with [`QuantusUrError::InvalidConfiguration`].
+pub fn encode_with_redundancy(
+    payload: &[u8],
+    redundancy: f32,
+) -> Result<Vec<String>, QuantusUrError> {
+    if !(1.0..=MAX_REDUNDANCY).contains(&redundancy) {
+        return Err(QuantusUrError::InvalidConfiguration(format!(
+            "redundancy {} outside 1..={}",
+            redundancy, MAX_REDUNDANCY
+        )));
+    }
+    let mut encoder = UrEncoder::new(payload)?;
+    if encoder.is_single_part() {
+        return encoder.into_minimal_parts().collect();
+    }
+    let count = encoder.fragment_count();
+    let scaled = count as f32 * redundancy;
+    let mut total = scaled as usize;
+    if (total as f32) < scaled {
+        total += 1;
+    }
+    (0..total.max(count)).map(|_| encoder.next_part()).collect()
+}
+
+/// Like [`encode_bytes`], but generates the parts on demand so callers that
+/// only display a few of them never build the rest.
+pub fn encode_bytes_lazy(payload: &[u8]) -> Result<MinimalParts, QuantusUrError> {
+    trace_span!("encode", payload_len = payload.len());
+    Ok(UrEncoder::new(payload)?.into_minimal_parts())
+}
+
+pub fn encode_bytes_with_options(
+    payload: &[u8],
+    options: &EncodeOptions,
+) -> Result<Vec<String>, QuantusUrError> {
+    collect_minimal_set(UrEncoder::with_options(payload, options)?)
+}
+
+/// Cleans up raw scan events before decoding: strips scanner noise around
+/// each part, drops empty entries, lowercases and removes duplicates while
+/// keeping the first occurrence of each part in place.
+pub fn normalize_parts(ur_parts: &mut Vec<String>) {
+    let mut seen = BTreeSet::new();
+    ur_parts.retain_mut(|part| {
+        let normalized = part::sanitize_part(part).to_lowercase();
+        if normalized.is_empty() || !seen.insert(normalized.clone()) {
+            return false;
+        }
+        *part = normalized;
+        true
+    });
+}
+
+fn normalized(ur_parts: &[String]) -> Vec<String> {
+    let mut parts = ur_parts.to_vec();
+    normalize_parts(&mut parts);
+    parts
+}
+
+/// Reassembles a UR message and returns its type together with the raw CBOR body.
+fn decode_message(ur_parts: &[String]) -> Result<(String, Vec<u8>), QuantusUrError> {
+    let ur_parts = normalized(ur_parts);
     if ur_parts.is_empty() {
         return Err(QuantusUrError::UrError("No UR parts provided".to_string()));
     }
 
-    let first = ur_parts[0].to_lowercase();
-    let (kind, decoded) =
-        ur::ur::decode(&first).map_err(|e| QuantusUrError::UrError(e.to_string()))?;
+    trace_span!("decode", parts = ur_parts.len());
+    let mut decoder = UrDecoder::new();
+    for part in &ur_parts {
+        decoder.receive(part)?;
+        if decoder.is_complete() {
+            break;
+        }
+    }
+    let message = decoder.cbor_message()?;
+    let ur_type = decoder
+        .ur_type()
+        .ok_or_else(|| QuantusUrError::UrError("Missing UR type".to_string()))?;
+    Ok((ur_type.to_string(), message))
+}
 
-    match kind {
-        ur::ur::Kind::SinglePart => {
-            let mut d = Decoder::new(&decoded);
-            let bytes = d
-                .bytes()
-                .map_err(|e| QuantusUrError::CborError(e.to_string()))?;
-            Ok(bytes.to_vec())
+/// Unwraps the CBOR byte string carrying a sign-request payload.
+fn payload_from_cbor(message: &[u8], options: &DecodeOptions) -> Result<Vec<u8>, QuantusUrError> {
+    Ok(tagged_payload_from_cbor(message, options)?.payload)
+}
+
+/// Payload together with the semantic tag that preceded its byte string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TaggedPayload {
+    pub tag: Option<u64>,
+    pub payload: Vec<u8>,
+}
+
+fn tagged_payload_from_cbor(
+    message: &[u8],
+    options: &DecodeOptions,
+) -> Result<TaggedPayload, QuantusUrError> {
+    if options.strict_cbor {
+        let (tag, payload) = cbor::strict_payload(message, &options.allowed_tags)?;
+        if payload.is_empty() {
+            return Err(QuantusUrError::EmptyPayload);
         }
-        ur::ur::Kind::MultiPart => {
-            let mut d = ur::ur::Decoder::default();
-            for part in ur_parts {
-                d.receive(&part.to_lowercase())
-                    .map_err(|e| QuantusUrError::UrError(e.to_string()))?;
-            }
-            if !d.complete() {
-                return Err(QuantusUrError::Incomplete);
-            }
-            let message = d
-                .message()
-                .map_err(|e| QuantusUrError::UrError(e.to_string()))?
-                .ok_or_else(|| QuantusUrError::UrError("No message".to_string()))?;
-            let mut dec = Decoder::new(&message);
-            let bytes = dec
-                .bytes()
-                .map_err(|e| QuantusUrError::CborError(e.to_string()))?;
-            Ok(bytes.to_vec())
+        return Ok(TaggedPayload {
+            tag,
+            payload: payload.to_vec(),
+        });
+    }
+    let mut d = Decoder::new(message);
+    let mut tag = None;
+    if d.datatype().map_err(cbor_error)? == Type::Tag {
+        let value = u64::from(d.tag().map_err(cbor_error)?);
+        if value != cbor::EMBEDDED_CBOR_TAG && !options.allowed_tags.contains(&value) {
+            return Err(QuantusUrError::CborError(format!(
+                "Tag {} is not in the allow-list",
+                value
+            )));
         }
+        tag = Some(value);
+    }
+    // Chunks of indefinite-length byte strings are concatenated
+    let mut payload = Vec::new();
+    for chunk in d.bytes_iter().map_err(cbor_error)? {
+        payload.extend_from_slice(chunk.map_err(cbor_error)?);
     }
+    if payload.is_empty() {
+        return Err(QuantusUrError::EmptyPayload);
+    }
+    Ok(TaggedPayload { tag, payload })
 }
 
+fn decode_internal(ur_parts: &[String]) -> Result<Vec<u8>, QuantusUrError> {
+    let (_, message) = decode_message(ur_parts)?;
+    payload_from_cbor(&message, &DecodeOptions::default())
+}
+
+#[cfg(feature = "hex")]
 pub fn decode_hex(ur_parts: &[String]) -> Result<String, QuantusUrError> {
     let bytes = decode_internal(ur_parts)?;
     Ok(hex::encode(bytes))
 }
 
+/// Like [`decode_hex`], but `0x`-prefixed as expected by Substrate tooling.
+#[cfg(feature = "hex")]
+pub fn decode_hex_prefixed(ur_parts: &[String]) -> Result<String, QuantusUrError> {
+    let mut prefixed = String::from("0x");
+    prefixed.push_str(&decode_hex(ur_parts)?);
+    Ok(prefixed)
+}
+
 pub fn decode_bytes(ur_parts: &[String]) -> Result<Vec<u8>, QuantusUrError> {
     decode_internal(ur_parts)
 }
 
+/// Decodes timestamped scan events as delivered by a camera callback,
+/// returning the payload together with the frame rate and loss measured from
+/// the scan times. Invalid scans are skipped and counted, as camera streams
+/// contain noise.
+#[cfg(feature = "std")]
+pub fn decode_scan_events(
+    events: &[(std::time::Instant, String)],
+) -> Result<(Vec<u8>, Option<ScanTiming>), QuantusUrError> {
+    let mut decoder = UrDecoder::with_options(DecodeOptions {
+        skip_invalid_parts: true,
+        ..DecodeOptions::default()
+    });
+    for (at, part) in events {
+        decoder.receive_scan(*at, part)?;
+        if decoder.is_complete() {
+            break;
+        }
+    }
+    Ok((decoder.message()?, decoder.scan_timing()))
+}
+
+/// Like [`decode_bytes`], with the given options, e.g. strict CBOR checking.
+pub fn decode_bytes_with_options(
+    ur_parts: &[String],
+    options: &DecodeOptions,
+) -> Result<Vec<u8>, QuantusUrError> {
+    let mut decoder = UrDecoder::with_options(options.clone());
+    for part in &normalized(ur_parts) {
+        decoder.receive(part)?;
+        if decoder.is_complete() {
+            break;
+        }
+    }
+    decoder.message()
+}
+
+/// Decodes the payload and reports the semantic tag in front of it, if any.
+/// Tags other than 24 are only accepted when listed in
+/// [`DecodeOptions::allowed_tags`].
+pub fn decode_tagged(
+    ur_parts: &[String],
+    options: &DecodeOptions,
+) -> Result<TaggedPayload, QuantusUrError> {
+    let (_, message) = decode_message(ur_parts)?;
+    tagged_payload_from_cbor(&message, options)
+}
+
+/// Outcome of [`decode_partial`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PartialDecode {
+    Decoded(Vec<u8>),
+    /// `expected` is zero until a part has been received; `missing` lists the
+    /// fragment sequence numbers not seen yet.
+    InProgress {
+        received: usize,
+        expected: usize,
+        missing: Vec<u32>,
+    },
+}
+
+/// Decodes the parts collected so far, or reports how far along the scan is.
+/// Reassembly runs once, unlike calling [`is_complete`] then [`decode_bytes`].
+pub fn decode_partial(ur_parts: &[String]) -> Result<PartialDecode, QuantusUrError> {
+    let mut decoder = UrDecoder::new();
+    for part in &normalized(ur_parts) {
+        decoder.receive(part)?;
+        if decoder.is_complete() {
+            return Ok(PartialDecode::Decoded(decoder.message()?));
+        }
+    }
+    Ok(PartialDecode::InProgress {
+        received: decoder.received_parts(),
+        expected: decoder.expected_parts().unwrap_or(0),
+        missing: decoder.missing_parts(),
+    })
+}
+
+/// Returns the payload if the parts are complete, reassembling only once.
+/// Replaces calling [`is_complete`] followed by [`decode_bytes`].
+pub fn try_decode(ur_parts: &[String]) -> Option<Vec<u8>> {
+    match decode_partial(ur_parts) {
+        Ok(PartialDecode::Decoded(payload)) => Some(payload),
+        _ => None,
+    }
+}
+
+/// Whether the parts are enough to reassemble the message.
+///
+/// Every call replays all parts into a fresh decoder, so checking after each
+/// scan costs O(n²) over a session. Scan loops should feed a [`UrDecoder`]
+/// instead, whose [`UrDecoder::is_complete`] is O(1) per received part.
 pub fn is_complete(ur_parts: &[String]) -> bool {
-    if ur_parts.is_empty() {
-        return false;
-    }
-
-    let first = ur_parts[0].to_lowercase();
-    let (kind, _) = match ur::ur::decode(&first) {
-        Ok(result) => result,
-        Err(_) => return false,
-    };
-
-    match kind {
-        ur::ur::Kind::SinglePart => true,
-        ur::ur::Kind::MultiPart => {
-            let mut d = ur::ur::Decoder::default();
-            for part in ur_parts {
-                if d.receive(&part.to_lowercase()).is_err() {
-                    return false;
-                }
-            }
-            d.complete()
+    let mut decoder = UrDecoder::new();
+    for part in &normalized(ur_parts) {
+        if decoder.receive(part).is_err() {
+            return false;
+        }
+        if decoder.is_complete() {
+            return true;
         }
     }
+    false
+}
+
+/// Serializes a part set as newline-delimited text, e.g. for pasting into a ticket.
+pub fn parts_to_text(ur_parts: &[String]) -> String {
+    let mut text = ur_parts.join("\n");
+    text.push('\n');
+    text
+}
+
+/// Parses a newline-delimited part set, trimming each line and dropping empty
+/// lines and case-insensitive duplicates while keeping the original order.
+pub fn parts_from_text(text: &str) -> Vec<String> {
+    let mut seen = BTreeSet::new();
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .filter(|line| seen.insert(line.to_lowercase()))
+        .map(ToString::to_string)
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::{format, vec};
 
+    #[cfg(feature = "hex")]
     #[test]
     fn test_single_part_roundtrip() {
         // Small payload that fits in 200 bytes
@@ -158,6 +600,7 @@ mod tests {
         assert_eq!(decoded_hex.to_lowercase(), hex_payload.to_lowercase());
     }
 
+    #[cfg(feature = "hex")]
     #[test]
     fn test_multi_part_roundtrip() {
         // Create a large payload (> 200 bytes)
@@ -179,19 +622,261 @@ mod tests {
         assert_eq!(decoded_hex.to_lowercase(), large_payload.to_lowercase());
     }
 
+    #[test]
+    fn test_decode_partial() {
+        let payload: Vec<u8> = (0..500).map(|i| i as u8).collect();
+        let parts = encode_bytes(&payload).expect("Encoding failed");
+        let count = parts.len();
+
+        match decode_partial(&parts[1..3]).expect("Decoding failed") {
+            PartialDecode::InProgress {
+                received,
+                expected,
+                missing,
+            } => {
+                assert_eq!(received, 2);
+                assert_eq!(expected, count);
+                assert_eq!(missing[0], 1);
+                assert_eq!(missing.len(), count - 2);
+            }
+            other => panic!("Expected progress, got {:?}", other),
+        }
+        assert_eq!(
+            decode_partial(&parts).expect("Decoding failed"),
+            PartialDecode::Decoded(payload)
+        );
+    }
+
+    #[test]
+    fn test_encode_with_redundancy() {
+        let payload: Vec<u8> = (0..1000u32).map(|i| (i % 251) as u8).collect();
+        let minimal = encode_bytes(&payload).expect("Encoding failed");
+        let parts = encode_with_redundancy(&payload, 1.5).expect("Encoding failed");
+        assert_eq!(parts.len(), (minimal.len() * 3 + 1) / 2);
+        assert_eq!(parts[..minimal.len()], minimal[..]);
+        assert_eq!(decode_bytes(&parts).expect("Decoding failed"), payload);
+
+        assert_eq!(
+            encode_with_redundancy(&payload, 1.0).expect("Encoding failed"),
+            minimal
+        );
+        for redundancy in [f32::NAN, f32::INFINITY, 0.5, MAX_REDUNDANCY + 1.0] {
+            assert!(matches!(
+                encode_with_redundancy(&payload, redundancy),
+                Err(QuantusUrError::InvalidConfiguration(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_decode_tolerates_duplicates() {
+        let payload: Vec<u8> = (0..1000u32).map(|i| (i % 251) as u8).collect();
+        let parts = encode_bytes(&payload).expect("Encoding failed");
+        let doubled: Vec<String> = parts
+            .iter()
+            .flat_map(|part| [part.clone(), part.clone()])
+            .collect();
+        assert_eq!(decode_bytes(&doubled).expect("Decoding failed"), payload);
+
+        let mut repeated_first = vec![parts[0].clone(); 5];
+        repeated_first.extend(parts.iter().cloned());
+        assert_eq!(
+            decode_bytes(&repeated_first).expect("Decoding failed"),
+            payload
+        );
+    }
+
+    #[test]
+    fn test_decode_accepts_any_sufficient_superset() {
+        let payload: Vec<u8> = (0..1000u32).map(|i| (i % 251) as u8).collect();
+        let mut encoder = UrEncoder::new(&payload).expect("Encoder creation failed");
+        let count = encoder.fragment_count();
+        let stream: Vec<String> = (0..count * 3)
+            .map(|_| encoder.next_part().expect("Part generation failed"))
+            .collect();
+
+        // Extras past the minimal set, before and after it, and out of order
+        for superset in [
+            stream.clone(),
+            stream.iter().rev().cloned().collect::<Vec<_>>(),
+            [&stream[count..], &stream[..count]].concat(),
+        ] {
+            assert_eq!(decode_bytes(&superset).expect("Decoding failed"), payload);
+            assert!(is_complete(&superset));
+        }
+    }
+
+    #[test]
+    fn test_strict_cbor_decode() {
+        let strict = DecodeOptions {
+            strict_cbor: true,
+            ..DecodeOptions::default()
+        };
+        let payload: Vec<u8> = (0..500u32).map(|i| i as u8).collect();
+        let parts = encode_bytes(&payload).expect("Encoding failed");
+        assert_eq!(
+            decode_bytes_with_options(&parts, &strict).expect("Decoding failed"),
+            payload
+        );
+
+        // Trailing byte after the byte string
+        let sloppy = encode_message(vec![0x42, 1, 2, 3], UR_TYPE).expect("Encoding failed");
+        assert_eq!(decode_bytes(&sloppy).expect("Decoding failed"), [1, 2]);
+        assert!(matches!(
+            decode_bytes_with_options(&sloppy, &strict),
+            Err(QuantusUrError::CborError(_))
+        ));
+    }
+
+    #[test]
+    fn test_indefinite_length_byte_string() {
+        let message = vec![0x5f, 0x42, 1, 2, 0x41, 3, 0x40, 0xff];
+        let parts = encode_message(message, UR_TYPE).expect("Encoding failed");
+        assert_eq!(decode_bytes(&parts).expect("Decoding failed"), [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_embedded_cbor_tag() {
+        let options = EncodeOptions {
+            tag_embedded_cbor: true,
+            ..EncodeOptions::default()
+        };
+        let payload = b"Hello, Quantus!".to_vec();
+        let parts = encode_bytes_with_options(&payload, &options).expect("Encoding failed");
+        assert_ne!(parts, encode_bytes(&payload).expect("Encoding failed"));
+        assert_eq!(decode_bytes(&parts).expect("Decoding failed"), payload);
+
+        let strict = DecodeOptions {
+            strict_cbor: true,
+            ..DecodeOptions::default()
+        };
+        assert_eq!(
+            decode_bytes_with_options(&parts, &strict).expect("Decoding failed"),
+            payload
+        );
+    }
+
+    #[test]
+    fn test_tag_allow_list() {
+        // Tag 304 (a registry type) around a 2-byte payload
+        let message = vec![0xd9, 0x01, 0x30, 0x42, 1, 2];
+        let parts = encode_message(message, UR_TYPE).expect("Encoding failed");
+        assert!(decode_bytes(&parts).is_err());
+
+        for strict_cbor in [false, true] {
+            let options = DecodeOptions {
+                allowed_tags: vec![304],
+                strict_cbor,
+                ..DecodeOptions::default()
+            };
+            let tagged = decode_tagged(&parts, &options).expect("Decoding failed");
+            assert_eq!(tagged.tag, Some(304));
+            assert_eq!(tagged.payload, [1, 2]);
+        }
+    }
+
+    #[test]
+    fn test_try_decode() {
+        let payload: Vec<u8> = (0..500u32).map(|i| i as u8).collect();
+        let parts = encode_bytes(&payload).expect("Encoding failed");
+        assert_eq!(try_decode(&parts), Some(payload));
+        assert_eq!(try_decode(&parts[1..]), None);
+        assert_eq!(try_decode(&["not-a-valid-ur".to_string()]), None);
+    }
+
+    #[test]
+    fn test_normalize_parts() {
+        let payload: Vec<u8> = (0..500u32).map(|i| i as u8).collect();
+        let parts = encode_bytes(&payload).expect("Encoding failed");
+        let mut raw = vec![
+            String::new(),
+            format!("{}\r\n", parts[0]),
+            parts[0].to_lowercase(),
+            "  ".to_string(),
+        ];
+        raw.extend(parts.iter().cloned());
+        normalize_parts(&mut raw);
+        let expected: Vec<String> = parts.iter().map(|p| p.to_lowercase()).collect();
+        assert_eq!(raw, expected);
+
+        let mut noisy = vec![String::new()];
+        noisy.extend(parts.iter().map(|p| format!("{}\n", p)));
+        assert_eq!(decode_bytes(&noisy).expect("Decoding failed"), payload);
+    }
+
+    #[cfg(feature = "hex")]
+    #[test]
+    fn test_decode_hex_prefixed() {
+        let parts = encode_hex("deadbeef").expect("Encoding failed");
+        assert_eq!(
+            decode_hex_prefixed(&parts).expect("Decoding failed"),
+            "0xdeadbeef"
+        );
+    }
+
     #[test]
     fn test_is_complete_empty() {
         assert!(!is_complete(&[]), "Empty parts should be incomplete");
     }
 
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_decode_scan_events() {
+        use std::time::{Duration, Instant};
+
+        let payload: Vec<u8> = (0..1000u32).map(|i| (i % 251) as u8).collect();
+        let mut scans = vec!["camera noise".to_string()];
+        scans.extend(encode_bytes(&payload).expect("Encoding failed"));
+        let start = Instant::now();
+        let events: Vec<(Instant, String)> = scans
+            .iter()
+            .enumerate()
+            .map(|(i, scan)| (start + Duration::from_millis(100 * i as u64), scan.clone()))
+            .collect();
+        let (decoded, timing) = decode_scan_events(&events).expect("Decoding failed");
+        assert_eq!(decoded, payload);
+        let timing = timing.expect("No timing");
+        assert_eq!(timing.scans, scans.len());
+        assert_eq!(
+            timing.elapsed,
+            Duration::from_millis(100 * (scans.len() as u64 - 1))
+        );
+    }
+
+    #[test]
+    fn test_empty_payload_rejected() {
+        assert!(matches!(
+            encode_bytes(&[]),
+            Err(QuantusUrError::EmptyPayload)
+        ));
+        assert!(matches!(
+            encode_single_part_into(&[], &mut [0u8; 64]),
+            Err(QuantusUrError::EmptyPayload)
+        ));
+        // An empty byte string from another encoder is refused too
+        let foreign = UrEncoder::from_cbor(vec![0x40], UR_TYPE)
+            .expect("Encoding failed")
+            .next_part()
+            .expect("Encoding failed");
+        assert!(matches!(
+            decode_bytes(&[foreign]),
+            Err(QuantusUrError::EmptyPayload)
+        ));
+    }
+
+    #[cfg(feature = "hex")]
     #[test]
     fn test_is_complete_single_part() {
         let hex_payload = "0200007416854906f03a9dff66e3270a736c44e15970ac03a638471523a03069f276ca0700e876481755010000007400000002000000";
         let encoded_parts = encode_hex(hex_payload).expect("Encoding failed");
         assert_eq!(encoded_parts.len(), 1, "Should be single part");
-        assert!(is_complete(&encoded_parts), "Single part should be complete");
+        assert!(
+            is_complete(&encoded_parts),
+            "Single part should be complete"
+        );
     }
 
+    #[cfg(feature = "hex")]
     #[test]
     fn test_is_complete_multi_part_complete() {
         let mut large_payload = String::with_capacity(500);
@@ -200,9 +885,13 @@ mod tests {
         }
         let encoded_parts = encode_hex(&large_payload).expect("Encoding failed");
         assert!(encoded_parts.len() > 1, "Should be multi-part");
-        assert!(is_complete(&encoded_parts), "Complete multi-part should return true");
+        assert!(
+            is_complete(&encoded_parts),
+            "Complete multi-part should return true"
+        );
     }
 
+    #[cfg(feature = "hex")]
     #[test]
     fn test_is_complete_multi_part_incomplete() {
         let mut large_payload = String::with_capacity(500);
@@ -211,17 +900,24 @@ mod tests {
         }
         let encoded_parts = encode_hex(&large_payload).expect("Encoding failed");
         assert!(encoded_parts.len() > 1, "Should be multi-part");
-        
+
         let incomplete_parts = &encoded_parts[..encoded_parts.len() - 1];
-        assert!(!is_complete(incomplete_parts), "Incomplete multi-part should return false");
+        assert!(
+            !is_complete(incomplete_parts),
+            "Incomplete multi-part should return false"
+        );
     }
 
     #[test]
     fn test_is_complete_invalid_ur() {
         let invalid_parts = vec!["not-a-valid-ur".to_string()];
-        assert!(!is_complete(&invalid_parts), "Invalid UR should return false");
+        assert!(
+            !is_complete(&invalid_parts),
+            "Invalid UR should return false"
+        );
     }
 
+    #[cfg(feature = "hex")]
     #[test]
     fn test_is_complete_multi_part_partial() {
         let mut large_payload = String::with_capacity(500);
@@ -230,9 +926,12 @@ mod tests {
         }
         let encoded_parts = encode_hex(&large_payload).expect("Encoding failed");
         assert!(encoded_parts.len() > 1, "Should be multi-part");
-        
+
         let partial_parts = &encoded_parts[..1];
-        assert!(!is_complete(partial_parts), "Single part of multi-part should return false");
+        assert!(
+            !is_complete(partial_parts),
+            "Single part of multi-part should return false"
+        );
     }
 
     #[test]
@@ -255,15 +954,76 @@ mod tests {
         assert_eq!(decoded_bytes, large_payload);
     }
 
+    #[test]
+    fn test_bc_ur_flavor_roundtrip() {
+        let options = EncodeOptions {
+            flavor: UrFlavor::BcUr,
+            ..EncodeOptions::default()
+        };
+        let payload: Vec<u8> = (0..250).map(|i| i as u8).collect();
+        let encoded_parts = encode_bytes_with_options(&payload, &options).expect("Encoding failed");
+        assert!(encoded_parts
+            .iter()
+            .all(|p| p.starts_with("ur:quantus-sign-request/")));
+
+        let mut decoder = UrDecoder::with_options(DecodeOptions {
+            flavor: UrFlavor::BcUr,
+            ..DecodeOptions::default()
+        });
+        let mut mixed = encoded_parts[0].clone();
+        mixed.replace_range(..2, "UR");
+        assert!(
+            decoder.receive(&mixed).is_err(),
+            "Mixed case should be rejected"
+        );
+
+        for part in &encoded_parts {
+            decoder.receive(part).expect("Receive failed");
+        }
+        assert_eq!(decoder.message().expect("Decoding failed"), payload);
+    }
+
+    #[test]
+    fn test_parts_text_roundtrip() {
+        let payload: Vec<u8> = (0..250).map(|i| i as u8).collect();
+        let encoded_parts = encode_bytes(&payload).expect("Encoding failed");
+        let text = parts_to_text(&encoded_parts);
+        assert_eq!(parts_from_text(&text), encoded_parts);
+
+        // Pasted text often has indentation, blank lines, CRLF and repeats
+        let messy = format!(
+            "\r\n  {}\r\n\n{}\n{}  \n",
+            encoded_parts[0],
+            encoded_parts[0].to_lowercase(),
+            encoded_parts[1..].join("\n")
+        );
+        assert_eq!(parts_from_text(&messy), encoded_parts);
+        assert_eq!(
+            decode_bytes(&parts_from_text(&messy)).expect("Decoding failed"),
+            payload
+        );
+    }
+
+    #[cfg(feature = "hex")]
     #[test]
     fn test_decode_bytes_hex_equivalence() {
         let hex_payload = "0200007416854906f03a9dff66e3270a736c44e15970ac03a638471523a03069f276ca0700e876481755010000007400000002000000";
         let encoded_parts = encode_hex(hex_payload).expect("Encoding failed");
-        
+
         let decoded_hex = decode_hex(&encoded_parts).expect("Decoding failed");
         let decoded_bytes = decode_bytes(&encoded_parts).expect("Decoding failed");
-        
+
         assert_eq!(decoded_hex.to_lowercase(), hex_payload.to_lowercase());
         assert_eq!(hex::encode(&decoded_bytes), decoded_hex);
     }
+
+    #[cfg(feature = "compact-errors")]
+    #[test]
+    fn test_compact_error_display() {
+        let error = QuantusUrError::PartChecksumMismatch {
+            expected: 1,
+            actual: 2,
+        };
+        assert_eq!(error.to_string(), error.kind());
+    }
 }
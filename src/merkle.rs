@@ -0,0 +1,140 @@
+//! Binary Merkle tree over chunk hashes, used to verify transfer chunks one by one.
+//!
+//! Leaves and inner nodes are domain-separated with a one-byte prefix. A node
+//! without a sibling is promoted to the next level unchanged, so proofs skip
+//! that level.
+
+use alloc::vec::Vec;
+
+use crate::constant_time_eq;
+use crate::transfer::{blake2_256, Hash};
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+pub(crate) fn leaf_hash(data: &[u8]) -> Hash {
+    let mut buf = Vec::with_capacity(data.len() + 1);
+    buf.push(LEAF_PREFIX);
+    buf.extend_from_slice(data);
+    blake2_256(&buf)
+}
+
+fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut buf = [0u8; 65];
+    buf[0] = NODE_PREFIX;
+    buf[1..33].copy_from_slice(left);
+    buf[33..].copy_from_slice(right);
+    blake2_256(&buf)
+}
+
+/// All levels of the tree, from the leaves up to the single root.
+pub(crate) struct MerkleTree {
+    levels: Vec<Vec<Hash>>,
+}
+
+impl MerkleTree {
+    pub(crate) fn new(leaves: Vec<Hash>) -> Self {
+        let mut levels = Vec::new();
+        let mut level = leaves;
+        while level.len() > 1 {
+            let next = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => node_hash(left, right),
+                    // An odd node is carried up unchanged
+                    _ => pair[0],
+                })
+                .collect();
+            levels.push(level);
+            level = next;
+        }
+        levels.push(level);
+        MerkleTree { levels }
+    }
+
+    pub(crate) fn root(&self) -> Hash {
+        self.levels
+            .last()
+            .and_then(|level| level.first())
+            .copied()
+            .unwrap_or_else(|| leaf_hash(&[]))
+    }
+
+    /// Sibling hashes from the leaf at `index` up to the root.
+    pub(crate) fn proof(&self, index: usize) -> Vec<Hash> {
+        let mut proof = Vec::new();
+        let mut index = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            if let Some(sibling) = level.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+        proof
+    }
+}
+
+/// Checks that `leaf` sits at `index` of a tree with `leaf_count` leaves and the given root.
+pub(crate) fn verify_proof(
+    leaf: Hash,
+    index: usize,
+    leaf_count: usize,
+    proof: &[Hash],
+    root: &Hash,
+) -> bool {
+    if index >= leaf_count {
+        return false;
+    }
+    let mut hash = leaf;
+    let mut index = index;
+    let mut width = leaf_count;
+    let mut siblings = proof.iter();
+    while width > 1 {
+        if (index ^ 1) < width {
+            let sibling = match siblings.next() {
+                Some(sibling) => sibling,
+                None => return false,
+            };
+            hash = if index % 2 == 0 {
+                node_hash(&hash, sibling)
+            } else {
+                node_hash(sibling, &hash)
+            };
+        }
+        index /= 2;
+        width = (width + 1) / 2;
+    }
+    siblings.next().is_none() && constant_time_eq(&hash, root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_leaf_verifies() {
+        for leaf_count in 1..=9 {
+            let leaves: Vec<Hash> = (0..leaf_count).map(|i| leaf_hash(&[i as u8])).collect();
+            let tree = MerkleTree::new(leaves.clone());
+            for (index, leaf) in leaves.iter().enumerate() {
+                let proof = tree.proof(index);
+                assert!(verify_proof(*leaf, index, leaf_count, &proof, &tree.root()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_wrong_leaf_rejected() {
+        let leaves: Vec<Hash> = (0..5).map(|i| leaf_hash(&[i as u8])).collect();
+        let tree = MerkleTree::new(leaves);
+        let proof = tree.proof(2);
+        assert!(!verify_proof(
+            leaf_hash(b"forged"),
+            2,
+            5,
+            &proof,
+            &tree.root()
+        ));
+        assert!(!verify_proof(leaf_hash(&[2]), 3, 5, &proof, &tree.root()));
+    }
+}
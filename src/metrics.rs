@@ -0,0 +1,137 @@
+//! Counters for application telemetry, as an alternative to scraping the
+//! `tracing` output.
+//!
+//! Attach a [`Metrics`] implementation to encoders with
+//! [`crate::UrEncoder::set_metrics`] and to decoders with
+//! [`crate::UrDecoder::set_metrics`]. Durations are only known for sessions
+//! fed through [`crate::UrDecoder::receive_at`] or
+//! [`crate::UrDecoder::receive_with_clock`], and are in the same unit.
+
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::DecodeEvent;
+
+/// Telemetry sink. All methods default to no-ops.
+pub trait Metrics: Send + Sync {
+    /// A part was handed out for display.
+    fn part_encoded(&self) {}
+    /// A scanned part was accepted or skipped.
+    fn part_received(&self, _event: DecodeEvent) {}
+    /// A scanned part was rejected, with the error's [`crate::QuantusUrError::kind`].
+    fn part_rejected(&self, _kind: &'static str) {}
+    /// A decode session completed, after `duration` when known.
+    fn session_completed(&self, _duration: Option<u64>) {}
+    /// A decode session ended without its message.
+    fn session_failed(&self, _kind: &'static str, _duration: Option<u64>) {}
+}
+
+/// Handle shared between the application and the sessions it instruments.
+pub type SharedMetrics = Arc<dyn Metrics>;
+
+/// Ready-made [`Metrics`] counting every event.
+#[derive(Debug, Default)]
+pub struct Counters {
+    parts_encoded: AtomicUsize,
+    parts_received: AtomicUsize,
+    parts_rejected: AtomicUsize,
+    sessions_completed: AtomicUsize,
+    sessions_failed: AtomicUsize,
+}
+
+impl Counters {
+    pub fn parts_encoded(&self) -> usize {
+        self.parts_encoded.load(Ordering::Relaxed)
+    }
+
+    pub fn parts_received(&self) -> usize {
+        self.parts_received.load(Ordering::Relaxed)
+    }
+
+    pub fn parts_rejected(&self) -> usize {
+        self.parts_rejected.load(Ordering::Relaxed)
+    }
+
+    pub fn sessions_completed(&self) -> usize {
+        self.sessions_completed.load(Ordering::Relaxed)
+    }
+
+    pub fn sessions_failed(&self) -> usize {
+        self.sessions_failed.load(Ordering::Relaxed)
+    }
+}
+
+impl Metrics for Counters {
+    fn part_encoded(&self) {
+        self.parts_encoded.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn part_received(&self, _event: DecodeEvent) {
+        self.parts_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn part_rejected(&self, _kind: &'static str) {
+        self.parts_rejected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn session_completed(&self, _duration: Option<u64>) {
+        self.sessions_completed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn session_failed(&self, _kind: &'static str, _duration: Option<u64>) {
+        self.sessions_failed.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{encode_bytes, DecodeOptions, UrDecoder, UrEncoder};
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_counters() {
+        let counters = Arc::new(Counters::default());
+        let payload: Vec<u8> = (0..1000u32).map(|i| (i % 251) as u8).collect();
+        let mut encoder = UrEncoder::new(&payload).expect("Encoding failed");
+        encoder.set_metrics(counters.clone());
+        let count = encoder.fragment_count();
+        let parts: Vec<_> = (0..count)
+            .map(|_| encoder.next_part().expect("Encoding failed"))
+            .collect();
+        assert_eq!(counters.parts_encoded(), count);
+
+        let mut decoder = UrDecoder::new();
+        decoder.set_metrics(counters.clone());
+        assert!(decoder.receive("ur:bogus").is_err());
+        for part in &parts {
+            decoder.receive(part).expect("Receive failed");
+        }
+        assert_eq!(counters.parts_received(), count);
+        assert_eq!(counters.parts_rejected(), 1);
+        assert_eq!(counters.sessions_completed(), 1);
+    }
+
+    #[test]
+    fn test_timeout_reported_with_duration() {
+        struct Failures(AtomicUsize);
+        impl Metrics for Failures {
+            fn session_failed(&self, kind: &'static str, duration: Option<u64>) {
+                assert_eq!((kind, duration), ("timed_out", Some(10)));
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let failures = Arc::new(Failures(AtomicUsize::new(0)));
+        let parts = encode_bytes(&[7; 1000]).expect("Encoding failed");
+        let mut decoder = UrDecoder::with_options(DecodeOptions {
+            max_scan_duration: Some(10),
+            ..DecodeOptions::default()
+        });
+        decoder.set_metrics(failures.clone());
+        decoder.receive_at(&parts[0], 0).expect("Receive failed");
+        assert!(decoder.receive_at(&parts[1], 10).is_err());
+        assert!(decoder.receive_at(&parts[2], 20).is_err());
+        assert_eq!(failures.0.load(Ordering::Relaxed), 1);
+    }
+}
@@ -0,0 +1,167 @@
+//! Interleaving several independent UR messages into one animation.
+//!
+//! [`UrMultiplexer`] takes parts from its encoders in turn, e.g. a sign
+//! request and an accompanying metadata proof. [`UrDemultiplexer`] routes
+//! every scanned part to its own session, keyed by UR type and the CRC32 of
+//! the CBOR message that multi-part headers already carry.
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::checksum::crc32;
+use crate::part::{parse_part, sanitize_part};
+use crate::{DecodeOptions, QuantusUrError, UrDecoder, UrEncoder};
+
+/// Identifies one message within a multiplexed stream.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StreamId {
+    pub ur_type: String,
+    /// CRC32 of the CBOR message.
+    pub checksum: u32,
+}
+
+/// Round-robin interleaving of parts from several encoders.
+#[derive(Default)]
+pub struct UrMultiplexer {
+    encoders: Vec<UrEncoder>,
+    next: usize,
+}
+
+impl UrMultiplexer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, encoder: UrEncoder) {
+        self.encoders.push(encoder);
+    }
+
+    pub fn stream_count(&self) -> usize {
+        self.encoders.len()
+    }
+
+    /// Next part of the combined animation.
+    pub fn next_part(&mut self) -> Result<String, QuantusUrError> {
+        if self.encoders.is_empty() {
+            return Err(QuantusUrError::UrError(
+                "No streams to multiplex".to_string(),
+            ));
+        }
+        let index = self.next % self.encoders.len();
+        self.next = index + 1;
+        self.encoders[index].next_part()
+    }
+
+    /// Parts until every stream has emitted its minimal set once.
+    pub fn minimal_round(&mut self) -> Result<Vec<String>, QuantusUrError> {
+        let max = self
+            .encoders
+            .iter()
+            .map(UrEncoder::fragment_count)
+            .max()
+            .unwrap_or(0);
+        let mut parts = Vec::with_capacity(max * self.encoders.len());
+        for _ in 0..max * self.encoders.len() {
+            parts.push(self.next_part()?);
+        }
+        Ok(parts)
+    }
+}
+
+/// Splits a multiplexed part stream back into per-message sessions.
+#[derive(Default)]
+pub struct UrDemultiplexer {
+    sessions: BTreeMap<StreamId, UrDecoder>,
+    options: DecodeOptions,
+}
+
+impl UrDemultiplexer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Options applied to every session the demultiplexer opens.
+    pub fn with_options(options: DecodeOptions) -> Self {
+        UrDemultiplexer {
+            options,
+            ..Self::default()
+        }
+    }
+
+    /// Routes a scanned part to its session, opening one if needed.
+    pub fn receive(&mut self, part: &str) -> Result<StreamId, QuantusUrError> {
+        let part = sanitize_part(part);
+        let parsed = parse_part(part)?;
+        let id = StreamId {
+            ur_type: parsed.ur_type.clone(),
+            checksum: match parsed.header {
+                Some(header) => header.checksum,
+                None => crc32(&parsed.body),
+            },
+        };
+        let options = &self.options;
+        self.sessions
+            .entry(id.clone())
+            .or_insert_with(|| UrDecoder::with_options(options.clone()))
+            .receive_text(parsed, part)?;
+        Ok(id)
+    }
+
+    pub fn session(&self, id: &StreamId) -> Option<&UrDecoder> {
+        self.sessions.get(id)
+    }
+
+    pub fn streams(&self) -> impl Iterator<Item = &StreamId> {
+        self.sessions.keys()
+    }
+
+    /// Ids of the sessions whose message is complete.
+    pub fn completed(&self) -> Vec<StreamId> {
+        self.sessions
+            .iter()
+            .filter(|(_, decoder)| decoder.is_complete())
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+
+    /// Removes a session, e.g. once its message has been consumed.
+    pub fn take(&mut self, id: &StreamId) -> Option<UrDecoder> {
+        self.sessions.remove(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interleaved_roundtrip() {
+        let request: Vec<u8> = (0..600u32).map(|i| (i % 251) as u8).collect();
+        let proof: Vec<u8> = (0..300u32).map(|i| (i % 13) as u8).collect();
+        let mut mux = UrMultiplexer::new();
+        mux.push(UrEncoder::new(&request).expect("Encoder creation failed"));
+        mux.push(UrEncoder::new(&proof).expect("Encoder creation failed"));
+
+        let mut demux = UrDemultiplexer::new();
+        for part in mux.minimal_round().expect("Multiplexing failed") {
+            demux.receive(&part).expect("Receive failed");
+        }
+        let completed = demux.completed();
+        assert_eq!(completed.len(), 2);
+
+        let mut messages: Vec<Vec<u8>> = completed
+            .iter()
+            .map(|id| {
+                demux
+                    .session(id)
+                    .unwrap()
+                    .message()
+                    .expect("Decoding failed")
+            })
+            .collect();
+        messages.sort_by_key(Vec::len);
+        assert_eq!(messages, [proof, request]);
+    }
+}
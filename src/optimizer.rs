@@ -0,0 +1,261 @@
+//! Fragment length recommendations that minimize expected scan time.
+//!
+//! Denser QR codes carry more per frame but are missed more often by weak
+//! cameras. The model assumes the given loss rate applies to codes of the
+//! target version and scales it down in proportion to module count for
+//! smaller versions, then compares the expected frame count of each version.
+
+use alloc::format;
+use core::fmt;
+
+use crate::capacity::{max_fragment_length, ErrorCorrection};
+use crate::{QuantusUrError, MIN_FRAGMENT_LENGTH, UR_TYPE};
+
+/// Camera and display conditions to optimize for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScanConditions {
+    /// Frames per second the animation is shown and the camera samples at.
+    pub fps: f32,
+    /// Fraction of frames of the target version the scanner misses.
+    pub loss_rate: f32,
+    /// Largest QR version the display can render legibly.
+    pub target_version: u8,
+    pub error_correction: ErrorCorrection,
+}
+
+impl ScanConditions {
+    /// Checks for a finite, positive `fps` and a finite `loss_rate` in
+    /// `0.0..1.0`.
+    pub fn validate(&self) -> Result<(), QuantusUrError> {
+        if !self.fps.is_finite() || self.fps <= 0.0 {
+            return Err(QuantusUrError::InvalidConfiguration(format!(
+                "fps {} is not a finite, positive rate",
+                self.fps
+            )));
+        }
+        if !(0.0..1.0).contains(&self.loss_rate) {
+            return Err(QuantusUrError::InvalidConfiguration(format!(
+                "loss_rate {} outside 0..1",
+                self.loss_rate
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Default for ScanConditions {
+    fn default() -> Self {
+        ScanConditions {
+            fps: 8.0,
+            loss_rate: 0.1,
+            target_version: 20,
+            error_correction: ErrorCorrection::Low,
+        }
+    }
+}
+
+/// Recommended fragment length together with the numbers behind it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScanPlan {
+    pub fragment_length: usize,
+    /// QR version every part fits.
+    pub version: u8,
+    /// Parts in the minimal set.
+    pub parts: usize,
+    /// Estimated fraction of frames missed at this version.
+    pub loss_rate: f32,
+    pub expected_frames: f32,
+    pub expected_seconds: f32,
+}
+
+impl fmt::Display for ScanPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "fragment length {} -> {} parts at QR version {}, {:.0}% loss, ~{:.1} frames ({:.1}s)",
+            self.fragment_length,
+            self.parts,
+            self.version,
+            self.loss_rate * 100.0,
+            self.expected_frames,
+            self.expected_seconds
+        )
+    }
+}
+
+fn modules(version: u8) -> f32 {
+    let side = 17.0 + 4.0 * version as f32;
+    side * side
+}
+
+/// Length of the CBOR byte string wrapping a payload.
+fn message_length(payload_len: usize) -> usize {
+    let head = match payload_len {
+        0..=23 => 1,
+        24..=0xff => 2,
+        0x100..=0xffff => 3,
+        _ => 5,
+    };
+    head + payload_len
+}
+
+/// Plan for one QR version, or `None` if no fragment of at least
+/// [`MIN_FRAGMENT_LENGTH`] fits it. Invalid conditions fail with
+/// [`QuantusUrError::InvalidConfiguration`].
+pub fn plan_for_version(
+    payload_len: usize,
+    version: u8,
+    conditions: &ScanConditions,
+) -> Result<Option<ScanPlan>, QuantusUrError> {
+    conditions.validate()?;
+    Ok(plan(payload_len, version, conditions))
+}
+
+fn plan(payload_len: usize, version: u8, conditions: &ScanConditions) -> Option<ScanPlan> {
+    let fragment_length = max_fragment_length(UR_TYPE, version, conditions.error_correction)
+        .filter(|&length| length >= MIN_FRAGMENT_LENGTH)?;
+    let message_len = message_length(payload_len);
+    let parts = ((message_len + fragment_length - 1) / fragment_length).max(1);
+    let scale = modules(version) / modules(conditions.target_version.max(1));
+    let loss_rate = (conditions.loss_rate * scale).clamp(0.0, 0.99);
+    // Fountain decoding typically needs a few percent more parts than the
+    // minimal set once any are missed.
+    let needed = if parts == 1 { 1.0 } else { parts as f32 * 1.05 };
+    let expected_frames = needed / (1.0 - loss_rate);
+    Some(ScanPlan {
+        fragment_length,
+        version,
+        parts,
+        loss_rate,
+        expected_frames,
+        expected_seconds: expected_frames / conditions.fps,
+    })
+}
+
+/// Recommends the fragment length minimizing expected scan time for a
+/// payload of `payload_len` bytes, considering every QR version up to the
+/// target. Returns `None` if no part fits the target version.
+pub fn optimize_fragment_length(
+    payload_len: usize,
+    conditions: &ScanConditions,
+) -> Result<Option<ScanPlan>, QuantusUrError> {
+    conditions.validate()?;
+    Ok((1..=conditions.target_version.min(40))
+        .filter_map(|version| plan(payload_len, version, conditions))
+        .min_by(|a, b| a.expected_seconds.total_cmp(&b.expected_seconds)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decode_bytes, encode_bytes_with_options, EncodeOptions};
+
+    #[test]
+    fn test_lossless_prefers_densest() {
+        let conditions = ScanConditions {
+            loss_rate: 0.0,
+            ..ScanConditions::default()
+        };
+        let plan = optimize_fragment_length(5000, &conditions)
+            .expect("Invalid conditions")
+            .expect("No plan");
+        assert_eq!(plan.version, conditions.target_version);
+
+        let payload: alloc::vec::Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+        let options = EncodeOptions {
+            max_fragment_length: plan.fragment_length,
+            ..EncodeOptions::default()
+        };
+        let parts = encode_bytes_with_options(&payload, &options).expect("Encoding failed");
+        assert_eq!(parts.len(), plan.parts);
+        assert_eq!(decode_bytes(&parts).expect("Decoding failed"), payload);
+    }
+
+    #[test]
+    fn test_high_loss_backs_off() {
+        let conditions = ScanConditions {
+            loss_rate: 0.9,
+            target_version: 25,
+            ..ScanConditions::default()
+        };
+        let plan = optimize_fragment_length(5000, &conditions)
+            .expect("Invalid conditions")
+            .expect("No plan");
+        assert!(plan.version < conditions.target_version);
+        assert!(optimize_fragment_length(
+            5000,
+            &ScanConditions {
+                target_version: 1,
+                error_correction: ErrorCorrection::High,
+                ..conditions
+            }
+        )
+        .is_ok_and(|plan| plan.is_none()));
+    }
+
+    #[test]
+    fn test_invalid_conditions() {
+        let invalid = [
+            (f32::NAN, 0.1),
+            (f32::INFINITY, 0.1),
+            (0.0, 0.1),
+            (8.0, f32::NAN),
+            (8.0, -0.1),
+            (8.0, 1.0),
+        ];
+        for (fps, loss_rate) in invalid {
+            let conditions = ScanConditions {
+                fps,
+                loss_rate,
+                ..ScanConditions::default()
+            };
+            assert!(matches!(
+                optimize_fragment_length(500, &conditions),
+                Err(QuantusUrError::InvalidConfiguration(_))
+            ));
+            assert!(plan_for_version(500, 10, &conditions).is_err());
+        }
+    }
+
+    #[test]
+    fn test_every_plan_encodes() {
+        let levels = [
+            ErrorCorrection::Low,
+            ErrorCorrection::Medium,
+            ErrorCorrection::Quartile,
+            ErrorCorrection::High,
+        ];
+        for payload_len in [1, 5, 6, 30, 500, 3000] {
+            let payload: alloc::vec::Vec<u8> =
+                (0..payload_len as u32).map(|i| (i % 251) as u8).collect();
+            for target_version in [1, 4, 6, 10, 25, 40] {
+                for error_correction in levels {
+                    for loss_rate in [0.0, 0.5] {
+                        let conditions = ScanConditions {
+                            loss_rate,
+                            target_version,
+                            error_correction,
+                            ..ScanConditions::default()
+                        };
+                        let Some(plan) = optimize_fragment_length(payload_len, &conditions)
+                            .expect("Invalid conditions")
+                        else {
+                            continue;
+                        };
+                        assert!(plan.fragment_length >= MIN_FRAGMENT_LENGTH);
+                        let options = EncodeOptions {
+                            max_fragment_length: plan.fragment_length,
+                            ..EncodeOptions::default()
+                        };
+                        assert!(options.validate_for(message_length(payload_len)).is_ok());
+                        let parts =
+                            encode_bytes_with_options(&payload, &options).expect("Encoding failed");
+                        assert_eq!(decode_bytes(&parts).expect("Decoding failed"), payload);
+                    }
+                }
+            }
+        }
+    }
+}
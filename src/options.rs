@@ -0,0 +1,275 @@
+use alloc::format;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use crate::rng::{random_array, RandomSource};
+use crate::{QuantusUrError, MAX_FRAGMENT_LENGTH};
+
+/// Smallest fragment length accepted, matching the BC-UR reference encoder.
+pub const MIN_FRAGMENT_LENGTH: usize = 10;
+
+/// Largest fragment whose part still fits a version 40 QR code in
+/// alphanumeric mode at low error correction.
+pub const MAX_QR_FRAGMENT_LENGTH: usize = 2000;
+
+/// Largest [`FountainParams::seed`]: skipped parts are still generated, so
+/// this bounds the work done on every reset.
+pub const MAX_FOUNTAIN_SEED: u32 = 4096;
+
+/// Tuning of the fountain parts emitted after the minimal set.
+///
+/// Which fragments a mixed part combines is fixed by the BC-UR spec, since
+/// decoders derive it from the sequence number. These knobs only choose which
+/// mixed parts are shown and how often the pure fragments come around again,
+/// so every setting stays decodable by any BC-UR decoder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FountainParams {
+    /// Mixed parts skipped after the minimal set, selecting a different run
+    /// of mixed parts. At most [`MAX_FOUNTAIN_SEED`].
+    pub seed: u32,
+    /// Mixed parts shown before the minimal set is replayed. `None` keeps
+    /// emitting mixed parts indefinitely.
+    pub mixed_window: Option<usize>,
+}
+
+impl FountainParams {
+    /// Picks a random [`FountainParams::seed`], so repeated displays of the
+    /// same message do not show the same run of mixed parts.
+    pub fn with_random_seed(mut self, rng: &mut impl RandomSource) -> Result<Self, QuantusUrError> {
+        let value = u32::from_le_bytes(random_array(rng)?);
+        // The modulo bias is below 2^-19
+        self.seed = value % (MAX_FOUNTAIN_SEED + 1);
+        Ok(self)
+    }
+
+    pub fn validate(&self) -> Result<(), QuantusUrError> {
+        if self.seed > MAX_FOUNTAIN_SEED {
+            return Err(QuantusUrError::InvalidConfiguration(format!(
+                "fountain seed {} above {}",
+                self.seed, MAX_FOUNTAIN_SEED
+            )));
+        }
+        if self.mixed_window == Some(0) {
+            return Err(QuantusUrError::InvalidConfiguration(
+                "mixed_window must be at least 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Selects which ecosystem's UR conventions to follow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UrFlavor {
+    /// Keystone-flavored behavior, as used by Keystone hardware and SDKs:
+    /// parts are emitted uppercase and mixed-case input is accepted.
+    #[default]
+    Keystone,
+    /// Strict BC-UR (BCR-2020-005) behavior: parts are emitted in canonical
+    /// lowercase and received parts must be entirely upper- or lowercase.
+    BcUr,
+}
+
+/// Text format of the emitted parts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EncodingMode {
+    /// Fountain-coded UR parts.
+    #[default]
+    Ur,
+    /// Numbered plain-text chunks (`QSR 3/12 <HEX>`) for legacy scanners that
+    /// cannot read UR, see [`crate::chunked`]. Only sign-request payloads can
+    /// be sent this way, and every chunk must be scanned.
+    HexChunks,
+    /// UR parts with base45 instead of bytewords bodies under a `UR45:`
+    /// scheme, about 25% shorter, see [`crate::base45`]. Only this crate's
+    /// decoder reads them. Parts are always uppercase, whatever the flavor.
+    Base45,
+    /// RaptorQ packets instead of BC-UR fountain parts under a `URQ:` scheme,
+    /// for very large payloads, see [`crate::raptor`]. Only this crate's
+    /// decoder reads them.
+    #[cfg(feature = "raptorq")]
+    RaptorQ,
+}
+
+/// Options controlling how payloads are split into UR parts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EncodeOptions {
+    pub max_fragment_length: usize,
+    /// Aim for at most this many parts, growing the fragment length as needed
+    /// within [`MIN_FRAGMENT_LENGTH`]..=[`MAX_QR_FRAGMENT_LENGTH`]. Overrides
+    /// `max_fragment_length`.
+    pub max_parts: Option<usize>,
+    pub flavor: UrFlavor,
+    /// Wrap the payload in CBOR tag 24 (encoded CBOR data item), as some
+    /// BC-UR registry types expect. Decoding accepts either form.
+    pub tag_embedded_cbor: bool,
+    pub fountain: FountainParams,
+    pub mode: EncodingMode,
+}
+
+impl EncodeOptions {
+    /// Options aiming for at most `max_parts` parts.
+    pub fn with_max_parts(max_parts: usize) -> Self {
+        EncodeOptions {
+            max_parts: Some(max_parts),
+            ..EncodeOptions::default()
+        }
+    }
+
+    /// Checks that the options can produce parts that fit in a QR code.
+    ///
+    /// With `max_parts` set, the fragment length depends on the message, so
+    /// [`EncodeOptions::validate_for`] is the complete check.
+    pub fn validate(&self) -> Result<(), QuantusUrError> {
+        self.validate_for(0)
+    }
+
+    /// Checks the options for a CBOR message of `message_len` bytes,
+    /// including the fragment length [`EncodeOptions::fragment_length_for`]
+    /// picks for it.
+    pub fn validate_for(&self, message_len: usize) -> Result<(), QuantusUrError> {
+        if self.max_parts == Some(0) {
+            return Err(QuantusUrError::InvalidConfiguration(
+                "max_parts must be at least 1".to_string(),
+            ));
+        }
+        let fragment_length = self.fragment_length_for(message_len);
+        if !(MIN_FRAGMENT_LENGTH..=MAX_QR_FRAGMENT_LENGTH).contains(&fragment_length) {
+            return Err(QuantusUrError::InvalidConfiguration(format!(
+                "fragment length {} outside {}..={}",
+                fragment_length, MIN_FRAGMENT_LENGTH, MAX_QR_FRAGMENT_LENGTH
+            )));
+        }
+        self.fountain.validate()
+    }
+
+    /// Fragment length used for a CBOR message of `message_len` bytes.
+    pub fn fragment_length_for(&self, message_len: usize) -> usize {
+        match self.max_parts {
+            Some(max_parts) => {
+                let max_parts = max_parts.max(1);
+                ((message_len + max_parts - 1) / max_parts)
+                    .clamp(MIN_FRAGMENT_LENGTH, MAX_QR_FRAGMENT_LENGTH)
+            }
+            None => self.max_fragment_length,
+        }
+    }
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        EncodeOptions {
+            max_fragment_length: MAX_FRAGMENT_LENGTH,
+            max_parts: None,
+            flavor: UrFlavor::default(),
+            tag_embedded_cbor: false,
+            fountain: FountainParams::default(),
+            mode: EncodingMode::default(),
+        }
+    }
+}
+
+/// Options controlling how a decode session accepts parts.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DecodeOptions {
+    /// Hard memory budget for the in-flight message, in bytes.
+    pub memory_limit: Option<usize>,
+    pub flavor: UrFlavor,
+    /// Reject trailing bytes, non-minimal lengths and unexpected major types
+    /// in the CBOR envelope instead of decoding it leniently.
+    pub strict_cbor: bool,
+    /// Semantic tags accepted in front of the payload byte string, besides
+    /// tag 24 which is always accepted.
+    pub allowed_tags: Vec<u64>,
+    /// Skip malformed and wrong-type scans instead of returning an error,
+    /// counting them in [`crate::UrDecoder::skipped_parts`]. Memory limit
+    /// violations are still reported.
+    pub skip_invalid_parts: bool,
+    /// Consecutive duplicate parts after which a session reports
+    /// [`crate::DecodeStatus::Stalled`]. Defaults to
+    /// [`crate::DEFAULT_STALL_THRESHOLD`].
+    pub stall_threshold: Option<usize>,
+    /// Time after the first scan passed to [`crate::UrDecoder::receive_at`]
+    /// at which the session times out, in the caller's time unit
+    /// (milliseconds with [`crate::UrDecoder::receive_with_clock`]).
+    pub max_scan_duration: Option<u64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decode_bytes, encode_bytes_with_options};
+
+    #[test]
+    fn test_fragment_length_for_max_parts() {
+        let options = EncodeOptions::with_max_parts(4);
+        assert_eq!(options.fragment_length_for(1000), 250);
+        assert_eq!(options.fragment_length_for(1001), 251);
+        assert_eq!(options.fragment_length_for(100_000), MAX_QR_FRAGMENT_LENGTH);
+        assert_eq!(
+            EncodeOptions::with_max_parts(10).fragment_length_for(30),
+            MIN_FRAGMENT_LENGTH
+        );
+        assert_eq!(
+            EncodeOptions::default().fragment_length_for(1000),
+            MAX_FRAGMENT_LENGTH
+        );
+    }
+
+    #[test]
+    fn test_random_fountain_seed() {
+        let mut rng = crate::rng::SeededRandom::new(3);
+        let params = FountainParams::default()
+            .with_random_seed(&mut rng)
+            .expect("RNG failed");
+        assert!(params.validate().is_ok());
+        let again = FountainParams::default()
+            .with_random_seed(&mut crate::rng::SeededRandom::new(3))
+            .expect("RNG failed");
+        assert_eq!(params, again);
+    }
+
+    #[test]
+    fn test_fragment_length_bounds() {
+        for max_fragment_length in [0, MIN_FRAGMENT_LENGTH - 1, MAX_QR_FRAGMENT_LENGTH + 1] {
+            let options = EncodeOptions {
+                max_fragment_length,
+                ..EncodeOptions::default()
+            };
+            assert!(matches!(
+                encode_bytes_with_options(&[0u8; 500], &options),
+                Err(QuantusUrError::InvalidConfiguration(_))
+            ));
+        }
+        assert!(EncodeOptions::with_max_parts(0).validate().is_err());
+        assert!(EncodeOptions::default().validate().is_ok());
+        let options = EncodeOptions::with_max_parts(3);
+        for message_len in [1, 30, 6000] {
+            assert!(options.validate_for(message_len).is_ok());
+        }
+        let options = EncodeOptions {
+            fountain: FountainParams {
+                seed: MAX_FOUNTAIN_SEED + 1,
+                mixed_window: None,
+            },
+            ..EncodeOptions::with_max_parts(3)
+        };
+        assert!(options.validate_for(6000).is_err());
+    }
+
+    #[test]
+    fn test_max_parts_encoding() {
+        let payload: alloc::vec::Vec<u8> = (0..3000u32).map(|i| (i % 251) as u8).collect();
+        for max_parts in [2, 3, 7] {
+            let options = EncodeOptions::with_max_parts(max_parts);
+            let parts = encode_bytes_with_options(&payload, &options).expect("Encoding failed");
+            assert!(parts.len() <= max_parts);
+            assert_eq!(decode_bytes(&parts).expect("Decoding failed"), payload);
+        }
+    }
+}
@@ -0,0 +1,338 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+use minicbor::Decoder;
+use ur::bytewords::{self, Style};
+
+use crate::cbor::cbor_error;
+use crate::checksum::crc32;
+use crate::QuantusUrError;
+
+/// Largest fragment count accepted in a part header. Far above anything an
+/// animated QR code can carry, it bounds the work and memory a forged header
+/// can demand of the fountain decoder.
+pub const MAX_SEQUENCE_COUNT: u32 = 1 << 16;
+
+/// Fountain header carried by every part of a multi-part UR.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct PartHeader {
+    pub sequence: u32,
+    pub sequence_count: u32,
+    pub message_length: usize,
+    pub checksum: u32,
+    pub fragment_length: usize,
+}
+
+/// A part string split into its UR type, optional fountain header and decoded body.
+///
+/// For single-part URs the body is the CBOR message itself; for multi-part URs
+/// it is the CBOR-encoded fountain part. Bodies are normally minimal bytewords
+/// as required by BCR-2020-005, but dash-separated URI-style bodies are
+/// accepted too; `minimal_body` records which one was received.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct ParsedPart {
+    pub ur_type: String,
+    pub header: Option<PartHeader>,
+    pub body: Vec<u8>,
+    pub minimal_body: bool,
+}
+
+impl ParsedPart {
+    /// Lowercase part string with a minimal bytewords body.
+    pub fn canonical_text(&self) -> String {
+        let body = bytewords::encode(&self.body, Style::Minimal);
+        match &self.header {
+            Some(h) => format!(
+                "ur:{}/{}-{}/{}",
+                self.ur_type, h.sequence, h.sequence_count, body
+            ),
+            None => format!("ur:{}/{}", self.ur_type, body),
+        }
+    }
+}
+
+/// Strips surrounding whitespace, control characters and byte order marks
+/// that some camera libraries leave around scanned text.
+pub(crate) fn sanitize_part(part: &str) -> &str {
+    part.trim_matches(|c: char| c.is_whitespace() || c.is_control() || c == '\u{feff}')
+}
+
+fn ur_error(msg: &str) -> QuantusUrError {
+    QuantusUrError::UrError(msg.to_string())
+}
+
+fn is_valid_ur_type(ur_type: &str) -> bool {
+    !ur_type.is_empty()
+        && ur_type
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+}
+
+fn parse_sequence(sequence: &str) -> Result<(u32, u32), QuantusUrError> {
+    let (index, count) = sequence
+        .split_once('-')
+        .ok_or_else(|| ur_error("Invalid sequence component"))?;
+    let index = index
+        .parse()
+        .map_err(|_| ur_error("Invalid sequence number"))?;
+    let count = count
+        .parse()
+        .map_err(|_| ur_error("Invalid sequence count"))?;
+    if index == 0 || count == 0 {
+        return Err(ur_error("Invalid sequence component"));
+    }
+    Ok((index, count))
+}
+
+fn parse_fountain_part(body: &[u8]) -> Result<PartHeader, QuantusUrError> {
+    let mut d = Decoder::new(body);
+    if d.array().map_err(cbor_error)? != Some(5) {
+        return Err(QuantusUrError::CborError(
+            "Malformed fountain part".to_string(),
+        ));
+    }
+    let sequence = d.u32().map_err(cbor_error)?;
+    let sequence_count = d.u32().map_err(cbor_error)?;
+    let message_length = d.u32().map_err(cbor_error)? as usize;
+    let checksum = d.u32().map_err(cbor_error)?;
+    let fragment_length = d.bytes().map_err(cbor_error)?.len();
+    if sequence_count > MAX_SEQUENCE_COUNT {
+        return Err(QuantusUrError::UrError(format!(
+            "Fragment count {} above the supported {}",
+            sequence_count, MAX_SEQUENCE_COUNT
+        )));
+    }
+    // The fragments must be able to hold the whole message
+    if fragment_length == 0
+        || message_length == 0
+        || message_length as u64 > u64::from(sequence_count) * fragment_length as u64
+    {
+        return Err(QuantusUrError::CborError(
+            "Fountain part header out of range".to_string(),
+        ));
+    }
+    Ok(PartHeader {
+        sequence,
+        sequence_count,
+        message_length,
+        checksum,
+        fragment_length,
+    })
+}
+
+pub(crate) fn parse_part(part: &str) -> Result<ParsedPart, QuantusUrError> {
+    let lower = part.to_lowercase();
+    let rest = lower
+        .strip_prefix("ur:")
+        .ok_or_else(|| ur_error("Invalid scheme"))?;
+    let segments: Vec<&str> = rest.split('/').collect();
+    let (ur_type, sequence, body) = match segments.as_slice() {
+        [ur_type, body] => (*ur_type, None, *body),
+        [ur_type, sequence, body] => (*ur_type, Some(parse_sequence(sequence)?), *body),
+        _ => return Err(ur_error("Invalid UR path")),
+    };
+    if !is_valid_ur_type(ur_type) {
+        return Err(ur_error("Invalid UR type"));
+    }
+
+    let minimal_body = !body.contains('-');
+    let style = if minimal_body {
+        Style::Minimal
+    } else {
+        Style::Uri
+    };
+    let body = bytewords::decode(body, style).map_err(|e| {
+        // Report transport corruption distinctly from malformed text
+        match crate::bytewords::verify_part_checksum(part) {
+            Err(mismatch @ QuantusUrError::PartChecksumMismatch { .. }) => mismatch,
+            _ => QuantusUrError::BytewordsError(e.to_string()),
+        }
+    })?;
+    let header = match sequence {
+        None => None,
+        Some((index, count)) => {
+            let header = parse_fountain_part(&body)?;
+            if header.sequence != index || header.sequence_count != count {
+                return Err(ur_error("Sequence component does not match part header"));
+            }
+            Some(header)
+        }
+    };
+
+    Ok(ParsedPart {
+        ur_type: ur_type.to_string(),
+        header,
+        body,
+        minimal_body,
+    })
+}
+
+/// A validated UR part string.
+///
+/// Parsing checks the scheme, type, sequence component and bytewords body, so
+/// arbitrary text cannot end up in a part list by accident. The part is
+/// displayed in uppercase, the form used for QR alphanumeric mode.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UrPart {
+    text: String,
+    parsed: ParsedPart,
+}
+
+impl UrPart {
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn ur_type(&self) -> &str {
+        &self.parsed.ur_type
+    }
+
+    pub fn is_multi_part(&self) -> bool {
+        self.parsed.header.is_some()
+    }
+
+    /// 1-based fountain sequence number, for multi-part URs.
+    pub fn sequence(&self) -> Option<u32> {
+        self.parsed.header.map(|h| h.sequence)
+    }
+
+    /// Number of fragments in the message, for multi-part URs.
+    pub fn sequence_count(&self) -> Option<u32> {
+        self.parsed.header.map(|h| h.sequence_count)
+    }
+
+    /// Length of the CBOR message being transported, for multi-part URs.
+    pub fn message_length(&self) -> Option<usize> {
+        self.parsed.header.map(|h| h.message_length)
+    }
+
+    /// CRC32 checksum of the whole message, for multi-part URs.
+    pub fn message_checksum(&self) -> Option<u32> {
+        self.parsed.header.map(|h| h.checksum)
+    }
+
+    /// CRC32 of the part body, as carried in its bytewords trailer. Unlike
+    /// [`UrPart::message_checksum`] it differs for every part.
+    pub fn part_checksum(&self) -> u32 {
+        crc32(&self.parsed.body)
+    }
+
+    pub(crate) fn parsed(&self) -> &ParsedPart {
+        &self.parsed
+    }
+}
+
+impl FromStr for UrPart {
+    type Err = QuantusUrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = sanitize_part(s);
+        let parsed = parse_part(s)?;
+        Ok(UrPart {
+            text: s.to_uppercase(),
+            parsed,
+        })
+    }
+}
+
+impl fmt::Display for UrPart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl AsRef<str> for UrPart {
+    fn as_ref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl From<UrPart> for String {
+    fn from(part: UrPart) -> Self {
+        part.text
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for UrPart {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.text)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for UrPart {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encode_bytes;
+
+    #[test]
+    fn test_parse_single_part() {
+        let parts = encode_bytes(b"Hello, Quantus!").expect("Encoding failed");
+        let parsed = parse_part(&parts[0]).expect("Parsing failed");
+        assert_eq!(parsed.ur_type, "quantus-sign-request");
+        assert!(parsed.header.is_none());
+    }
+
+    #[test]
+    fn test_parse_multi_part_header() {
+        let payload: Vec<u8> = (0..250).map(|i| i as u8).collect();
+        let parts = encode_bytes(&payload).expect("Encoding failed");
+        let parsed = parse_part(&parts[1]).expect("Parsing failed");
+        let header = parsed.header.expect("Missing header");
+        assert_eq!(header.sequence, 2);
+        assert_eq!(header.sequence_count as usize, parts.len());
+        assert!(header.message_length > payload.len());
+    }
+
+    #[test]
+    fn test_ur_part_roundtrip() {
+        let payload: Vec<u8> = (0..250).map(|i| i as u8).collect();
+        let parts = encode_bytes(&payload).expect("Encoding failed");
+        let part: UrPart = parts[0].to_lowercase().parse().expect("Parsing failed");
+        assert_eq!(part.to_string(), parts[0]);
+        assert_eq!(part.ur_type(), "quantus-sign-request");
+        assert_eq!(part.sequence(), Some(1));
+        assert_eq!(part.sequence_count(), Some(parts.len() as u32));
+        assert!("hello world".parse::<UrPart>().is_err());
+    }
+
+    #[test]
+    fn test_uri_style_body_accepted() {
+        let parts = encode_bytes(b"Hello, Quantus!").expect("Encoding failed");
+        let minimal = parse_part(&parts[0]).expect("Parsing failed");
+        assert!(minimal.minimal_body);
+
+        let uri_body = bytewords::encode(&minimal.body, Style::Uri);
+        let uri =
+            parse_part(&format!("ur:quantus-sign-request/{}", uri_body)).expect("Parsing failed");
+        assert!(!uri.minimal_body);
+        assert_eq!(uri.body, minimal.body);
+        assert_eq!(uri.canonical_text(), parts[0].to_lowercase());
+    }
+
+    #[test]
+    fn test_sanitize_part() {
+        let parts = encode_bytes(b"Hello, Quantus!").expect("Encoding failed");
+        let noisy = format!("\u{feff} {}\r\n\0", parts[0]);
+        assert_eq!(sanitize_part(&noisy), parts[0]);
+        let part: UrPart = noisy.parse().expect("Parsing failed");
+        assert_eq!(part.as_str(), parts[0]);
+    }
+
+    #[test]
+    fn test_parse_rejects_garbage() {
+        assert!(parse_part("not-a-valid-ur").is_err());
+        assert!(parse_part("ur:Bad_Type/lpadaxcf").is_err());
+        assert!(parse_part("ur:quantus-sign-request/0-3/lpadaxcf").is_err());
+    }
+}
@@ -0,0 +1,230 @@
+//! Passphrase-protected messages for backups and exports, where the QR image
+//! itself may be photographed, printed or stored.
+//!
+//! The payload is encrypted with XChaCha20-Poly1305 under a key derived from
+//! the passphrase with Argon2id. Messages use their own UR type and carry the
+//! CBOR array `[version, salt, memory_kib, iterations, parallelism, nonce,
+//! ciphertext]`; everything before the ciphertext is authenticated too, so the
+//! KDF parameters cannot be lowered without the passphrase failing.
+//!
+//! Salts and nonces come from a [`RandomSource`]; the functions without one
+//! use [`crate::rng::OsRandom`] and need the `getrandom` feature.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use minicbor::{Decoder, Encoder};
+
+use crate::cbor::{cbor_error, expect_ur_type};
+use crate::rng::{random_array, RandomSource};
+use crate::{decode_message, encode_message, QuantusUrError};
+
+pub const ENCRYPTED_UR_TYPE: &str = "quantus-encrypted";
+
+pub(crate) const VERSION: u8 = 1;
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 24;
+const KEY_LENGTH: usize = 32;
+
+/// Largest Argon2 memory cost accepted when decrypting, so a crafted message
+/// cannot make the decoder allocate without bound.
+pub const MAX_KDF_MEMORY_KIB: u32 = 256 * 1024;
+const MAX_KDF_ITERATIONS: u32 = 64;
+const MAX_KDF_PARALLELISM: u32 = 16;
+
+/// Argon2id cost parameters, stored in the message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    /// OWASP's recommended minimum for Argon2id: 19 MiB, 2 passes.
+    fn default() -> Self {
+        KdfParams {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+impl KdfParams {
+    fn derive_key(
+        &self,
+        passphrase: &str,
+        salt: &[u8],
+    ) -> Result<[u8; KEY_LENGTH], QuantusUrError> {
+        let params = Params::new(
+            self.memory_kib,
+            self.iterations,
+            self.parallelism,
+            Some(KEY_LENGTH),
+        )
+        .map_err(|e| QuantusUrError::InvalidConfiguration(e.to_string()))?;
+        let mut key = [0u8; KEY_LENGTH];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| QuantusUrError::InvalidConfiguration(e.to_string()))?;
+        Ok(key)
+    }
+}
+
+/// Encrypts `payload` under `passphrase` with the default KDF parameters.
+#[cfg(feature = "getrandom")]
+pub fn encode_with_passphrase(
+    payload: &[u8],
+    passphrase: &str,
+) -> Result<Vec<String>, QuantusUrError> {
+    encode_with_passphrase_params(payload, passphrase, &KdfParams::default())
+}
+
+/// Like [`encode_with_passphrase`], with explicit KDF parameters.
+#[cfg(feature = "getrandom")]
+pub fn encode_with_passphrase_params(
+    payload: &[u8],
+    passphrase: &str,
+    params: &KdfParams,
+) -> Result<Vec<String>, QuantusUrError> {
+    encode_with_passphrase_rng(payload, passphrase, params, &mut crate::rng::OsRandom)
+}
+
+/// Like [`encode_with_passphrase_params`], drawing the salt and nonce from
+/// `rng`.
+pub fn encode_with_passphrase_rng(
+    payload: &[u8],
+    passphrase: &str,
+    params: &KdfParams,
+    rng: &mut impl RandomSource,
+) -> Result<Vec<String>, QuantusUrError> {
+    let salt: [u8; SALT_LENGTH] = random_array(rng)?;
+    let nonce: [u8; NONCE_LENGTH] = random_array(rng)?;
+    let key = params.derive_key(passphrase, &salt)?;
+
+    let mut e = Encoder::new(Vec::new());
+    e.array(7)
+        .and_then(|e| e.u8(VERSION))
+        .and_then(|e| e.bytes(&salt))
+        .and_then(|e| e.u32(params.memory_kib))
+        .and_then(|e| e.u32(params.iterations))
+        .and_then(|e| e.u32(params.parallelism))
+        .and_then(|e| e.bytes(&nonce))
+        .map_err(cbor_error)?;
+    let ciphertext = XChaCha20Poly1305::new(&key.into())
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: payload,
+                aad: e.writer(),
+            },
+        )
+        .map_err(|_| QuantusUrError::InvalidConfiguration("encryption failed".to_string()))?;
+    e.bytes(&ciphertext).map_err(cbor_error)?;
+    encode_message(e.into_writer(), ENCRYPTED_UR_TYPE)
+}
+
+/// Decrypts a message made by [`encode_with_passphrase`]. A wrong passphrase
+/// and a tampered message both return [`QuantusUrError::DecryptionFailed`].
+pub fn decode_with_passphrase(
+    ur_parts: &[String],
+    passphrase: &str,
+) -> Result<Vec<u8>, QuantusUrError> {
+    let (ur_type, cbor) = decode_message(ur_parts)?;
+    expect_ur_type(&ur_type, ENCRYPTED_UR_TYPE)?;
+    let mut d = Decoder::new(&cbor);
+    if d.array().map_err(cbor_error)? != Some(7) {
+        return Err(QuantusUrError::CborError(
+            "malformed encrypted message".to_string(),
+        ));
+    }
+    let version = d.u8().map_err(cbor_error)?;
+    if version != VERSION {
+        return Err(QuantusUrError::CborError(alloc::format!(
+            "unsupported encrypted message version {}",
+            version
+        )));
+    }
+    let salt = d.bytes().map_err(cbor_error)?;
+    let params = KdfParams {
+        memory_kib: d.u32().map_err(cbor_error)?,
+        iterations: d.u32().map_err(cbor_error)?,
+        parallelism: d.u32().map_err(cbor_error)?,
+    };
+    if params.memory_kib > MAX_KDF_MEMORY_KIB
+        || params.iterations > MAX_KDF_ITERATIONS
+        || params.parallelism > MAX_KDF_PARALLELISM
+    {
+        return Err(QuantusUrError::InvalidConfiguration(
+            "KDF parameters above the supported maximum".to_string(),
+        ));
+    }
+    let nonce = d.bytes().map_err(cbor_error)?;
+    if nonce.len() != NONCE_LENGTH {
+        return Err(QuantusUrError::CborError(
+            "invalid nonce length".to_string(),
+        ));
+    }
+    let header_end = d.position();
+    let ciphertext = d.bytes().map_err(cbor_error)?;
+
+    let key = params.derive_key(passphrase, salt)?;
+    XChaCha20Poly1305::new(&key.into())
+        .decrypt(
+            XNonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: &cbor[..header_end],
+            },
+        )
+        .map_err(|_| QuantusUrError::DecryptionFailed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::SeededRandom;
+
+    // Cheap parameters keep the tests fast
+    const PARAMS: KdfParams = KdfParams {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    #[test]
+    fn test_passphrase_roundtrip() {
+        let payload: Vec<u8> = (0..600u32).map(|i| (i % 251) as u8).collect();
+        let mut rng = SeededRandom::new(1);
+        let parts = encode_with_passphrase_rng(&payload, "correct horse", &PARAMS, &mut rng)
+            .expect("Encoding failed");
+        assert!(parts[0].starts_with("UR:QUANTUS-ENCRYPTED/"));
+        assert_eq!(
+            decode_with_passphrase(&parts, "correct horse").expect("Decryption failed"),
+            payload
+        );
+        assert_eq!(
+            decode_with_passphrase(&parts, "battery staple")
+                .unwrap_err()
+                .kind(),
+            "decryption_failed"
+        );
+    }
+
+    #[test]
+    fn test_salt_and_nonce_are_fresh() {
+        let encode = |rng: &mut SeededRandom| {
+            encode_with_passphrase_rng(b"seed", "pass", &PARAMS, rng).expect("Encoding failed")
+        };
+        let mut rng = SeededRandom::new(2);
+        let first = encode(&mut rng);
+        assert_ne!(first, encode(&mut rng));
+        assert_eq!(first, encode(&mut SeededRandom::new(2)));
+        let plain = crate::encode_bytes(b"seed").expect("Encoding failed");
+        assert!(decode_with_passphrase(&plain, "pass").is_err());
+    }
+}
@@ -0,0 +1,248 @@
+//! Outstanding requests awaiting their response, with expiry.
+//!
+//! Wallets that show several requests, or keep a request open across
+//! screens, insert each request when encoding it and resolve scanned
+//! responses against the store, which matches them by request hash.
+//! Entries older than the store's TTL are evicted and no longer resolve.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::clock::Clock;
+use crate::response::SignResponse;
+use crate::transfer::{blake2_256, Hash};
+use crate::{encode_bytes, QuantusUrError};
+
+struct Pending<T> {
+    payload: Vec<u8>,
+    context: T,
+    expires_at: u64,
+}
+
+/// A response matched to its request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolved<T> {
+    pub payload: Vec<u8>,
+    /// What the application stored with the request.
+    pub context: T,
+    pub response: SignResponse,
+}
+
+/// Requests awaiting responses, keyed by the hash of their payload.
+///
+/// `T` is application data kept with each request, e.g. a transaction id.
+/// Times come from a [`Clock`], in milliseconds.
+pub struct PendingRequests<T = ()> {
+    ttl: u64,
+    entries: BTreeMap<Hash, Pending<T>>,
+}
+
+impl<T> PendingRequests<T> {
+    /// Store whose requests expire `ttl` milliseconds after insertion.
+    pub fn new(ttl: u64) -> Self {
+        PendingRequests {
+            ttl,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Records an outstanding request, replacing any pending request with
+    /// the same payload. Returns the hash responses will carry.
+    pub fn insert(&mut self, payload: &[u8], context: T, clock: &impl Clock) -> Hash {
+        let hash = blake2_256(payload);
+        self.entries.insert(
+            hash,
+            Pending {
+                payload: payload.to_vec(),
+                context,
+                expires_at: clock.now().saturating_add(self.ttl),
+            },
+        );
+        hash
+    }
+
+    /// Encodes the request and records it.
+    pub fn encode(
+        &mut self,
+        payload: &[u8],
+        context: T,
+        clock: &impl Clock,
+    ) -> Result<Vec<String>, QuantusUrError> {
+        let parts = encode_bytes(payload)?;
+        self.insert(payload, context, clock);
+        Ok(parts)
+    }
+
+    /// Removes and returns the request `response` answers.
+    ///
+    /// Fails with [`QuantusUrError::SessionExpired`] if the request expired
+    /// (it is evicted) and [`QuantusUrError::ResponseMismatch`] if no
+    /// request is pending for it.
+    pub fn resolve(
+        &mut self,
+        response: SignResponse,
+        clock: &impl Clock,
+    ) -> Result<Resolved<T>, QuantusUrError> {
+        self.resolve_verified(response, clock, |_, _| Ok(()))
+    }
+
+    /// Like [`PendingRequests::resolve`], but the request is only removed
+    /// once `verify` accepts the response for the request payload, e.g. with
+    /// `SignResponse::verify` (`ml-dsa` feature). A response with a bad
+    /// signature fails with the error of `verify` and leaves the request
+    /// pending, so it cannot cancel the genuine response.
+    pub fn resolve_verified(
+        &mut self,
+        response: SignResponse,
+        clock: &impl Clock,
+        verify: impl FnOnce(&[u8], &SignResponse) -> Result<(), QuantusUrError>,
+    ) -> Result<Resolved<T>, QuantusUrError> {
+        let hash = response.request_hash;
+        let pending = self
+            .entries
+            .get(&hash)
+            .ok_or(QuantusUrError::ResponseMismatch)?;
+        if clock.now() >= pending.expires_at {
+            self.entries.remove(&hash);
+            return Err(QuantusUrError::SessionExpired);
+        }
+        if !response.matches_request(&pending.payload) {
+            return Err(QuantusUrError::ResponseMismatch);
+        }
+        verify(&pending.payload, &response)?;
+        let pending = self
+            .entries
+            .remove(&hash)
+            .ok_or(QuantusUrError::ResponseMismatch)?;
+        Ok(Resolved {
+            payload: pending.payload,
+            context: pending.context,
+            response,
+        })
+    }
+
+    /// Decodes a scanned response and resolves it.
+    pub fn resolve_parts(
+        &mut self,
+        ur_parts: &[String],
+        clock: &impl Clock,
+    ) -> Result<Resolved<T>, QuantusUrError> {
+        self.resolve(SignResponse::from_parts(ur_parts)?, clock)
+    }
+
+    /// Drops a request, e.g. when the user cancels it.
+    pub fn cancel(&mut self, request_hash: &Hash) -> Option<T> {
+        self.entries
+            .remove(request_hash)
+            .map(|pending| pending.context)
+    }
+
+    /// Removes expired requests and returns their contexts.
+    pub fn evict_expired(&mut self, clock: &impl Clock) -> Vec<T> {
+        let now = clock.now();
+        let expired: Vec<Hash> = self
+            .entries
+            .iter()
+            .filter(|(_, pending)| now >= pending.expires_at)
+            .map(|(hash, _)| *hash)
+            .collect();
+        expired
+            .iter()
+            .filter_map(|hash| self.entries.remove(hash))
+            .map(|pending| pending.context)
+            .collect()
+    }
+
+    pub fn contains(&self, request_hash: &Hash) -> bool {
+        self.entries.contains_key(request_hash)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    #[test]
+    fn test_resolve_matches_request() {
+        let clock = ManualClock::new(0);
+        let mut pending = PendingRequests::new(60_000);
+        pending
+            .encode(b"transfer 1", "tx-1", &clock)
+            .expect("Encoding failed");
+        pending.insert(b"transfer 2", "tx-2", &clock);
+
+        let response = SignResponse::for_request(b"transfer 2", alloc::vec![9; 64]);
+        let parts = response.to_parts().expect("Encoding failed");
+        let resolved = pending
+            .resolve_parts(&parts, &clock)
+            .expect("Resolve failed");
+        assert_eq!(resolved.context, "tx-2");
+        assert_eq!(resolved.payload, b"transfer 2");
+        assert_eq!(pending.len(), 1);
+        assert!(matches!(
+            pending.resolve(response, &clock),
+            Err(QuantusUrError::ResponseMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_rejected_response_keeps_request() {
+        let clock = ManualClock::new(0);
+        let mut pending = PendingRequests::new(60_000);
+        pending.insert(b"transfer 1", "tx-1", &clock);
+        let genuine = alloc::vec![9; 64];
+        let verify = |_: &[u8], response: &SignResponse| {
+            if response.signature == genuine {
+                Ok(())
+            } else {
+                Err(QuantusUrError::SignatureVerificationFailed(
+                    "Unexpected signature".into(),
+                ))
+            }
+        };
+
+        let forged = SignResponse::for_request(b"transfer 1", alloc::vec![1; 64]);
+        assert!(matches!(
+            pending.resolve_verified(forged, &clock, verify),
+            Err(QuantusUrError::SignatureVerificationFailed(_))
+        ));
+        assert!(pending.contains(&blake2_256(b"transfer 1")));
+
+        let response = SignResponse::for_request(b"transfer 1", genuine.clone());
+        let resolved = pending
+            .resolve_verified(response, &clock, verify)
+            .expect("Resolve failed");
+        assert_eq!(resolved.context, "tx-1");
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn test_expired_requests_evicted() {
+        let clock = ManualClock::new(1_000);
+        let mut pending = PendingRequests::new(500);
+        pending.insert(b"old", 1, &clock);
+        clock.advance(300);
+        pending.insert(b"new", 2, &clock);
+        clock.advance(200);
+        assert_eq!(pending.evict_expired(&clock), [1]);
+        assert!(pending.contains(&blake2_256(b"new")));
+
+        clock.advance(300);
+        let response = SignResponse::for_request(b"new", alloc::vec![1; 64]);
+        assert!(matches!(
+            pending.resolve(response, &clock),
+            Err(QuantusUrError::SessionExpired)
+        ));
+        assert!(pending.is_empty());
+    }
+}
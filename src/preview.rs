@@ -0,0 +1,250 @@
+//! Best-effort, human-readable previews of SCALE-encoded calls, so signer
+//! firmware can show what is being signed instead of raw hex.
+//!
+//! Calls are looked up in a [`CallRegistry`] that the caller fills from the
+//! runtime metadata of the chain, e.g. at build time. Decoding stops at the
+//! first argument that cannot be read; the preview then lists the arguments
+//! decoded so far and is marked incomplete.
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+
+use crate::classify::{scale_compact, scale_extrinsic};
+use crate::PayloadKind;
+
+/// SCALE type of a call argument.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ArgType {
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    /// Compact-encoded unsigned integer, e.g. a balance.
+    Compact,
+    /// 32-byte account id.
+    AccountId,
+    /// `MultiAddress`; only the `Id` variant is decoded.
+    MultiAddress,
+    /// 32-byte hash.
+    H256,
+    /// Length-prefixed bytes, shown as text when printable.
+    Bytes,
+}
+
+/// Names and argument types of one call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CallInfo {
+    pub pallet: String,
+    pub call: String,
+    pub args: Vec<(String, ArgType)>,
+}
+
+/// Calls known to the previewer, keyed by pallet and call index.
+#[derive(Debug, Clone, Default)]
+pub struct CallRegistry {
+    calls: BTreeMap<(u8, u8), CallInfo>,
+}
+
+impl CallRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(
+        &mut self,
+        pallet_index: u8,
+        pallet: &str,
+        call_index: u8,
+        call: &str,
+        args: &[(&str, ArgType)],
+    ) {
+        self.calls.insert(
+            (pallet_index, call_index),
+            CallInfo {
+                pallet: pallet.to_string(),
+                call: call.to_string(),
+                args: args
+                    .iter()
+                    .map(|(name, ty)| (name.to_string(), *ty))
+                    .collect(),
+            },
+        );
+    }
+
+    pub fn get(&self, pallet_index: u8, call_index: u8) -> Option<&CallInfo> {
+        self.calls.get(&(pallet_index, call_index))
+    }
+}
+
+/// Decoded call with its arguments formatted for display.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CallPreview {
+    pub pallet: String,
+    pub call: String,
+    pub args: Vec<(String, String)>,
+    /// Whether every argument was decoded.
+    pub complete: bool,
+}
+
+impl fmt::Display for CallPreview {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}(", self.pallet, self.call)?;
+        for (i, (name, value)) in self.args.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}: {}", name, value)?;
+        }
+        if !self.complete {
+            f.write_str(if self.args.is_empty() { "…" } else { ", …" })?;
+        }
+        f.write_str(")")
+    }
+}
+
+fn hex_string(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(2 + bytes.len() * 2);
+    out.push_str("0x");
+    for byte in bytes {
+        let _ = write!(out, "{:02x}", byte);
+    }
+    out
+}
+
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if data.len() < len {
+        return None;
+    }
+    let (head, rest) = data.split_at(len);
+    *data = rest;
+    Some(head)
+}
+
+fn uint(data: &mut &[u8], len: usize) -> Option<String> {
+    let mut bytes = [0u8; 16];
+    bytes[..len].copy_from_slice(take(data, len)?);
+    Some(u128::from_le_bytes(bytes).to_string())
+}
+
+fn decode_arg(data: &mut &[u8], ty: ArgType) -> Option<String> {
+    match ty {
+        ArgType::Bool => match take(data, 1)?[0] {
+            0 => Some("false".to_string()),
+            1 => Some("true".to_string()),
+            _ => None,
+        },
+        ArgType::U8 => uint(data, 1),
+        ArgType::U16 => uint(data, 2),
+        ArgType::U32 => uint(data, 4),
+        ArgType::U64 => uint(data, 8),
+        ArgType::U128 => uint(data, 16),
+        ArgType::Compact => {
+            let (value, len) = scale_compact(data)?;
+            take(data, len)?;
+            Some(value.to_string())
+        }
+        ArgType::AccountId | ArgType::H256 => take(data, 32).map(hex_string),
+        ArgType::MultiAddress => match take(data, 1)?[0] {
+            0 => take(data, 32).map(hex_string),
+            _ => None,
+        },
+        ArgType::Bytes => {
+            let (len, prefix) = scale_compact(data)?;
+            take(data, prefix)?;
+            let bytes = take(data, usize::try_from(len).ok()?)?;
+            match core::str::from_utf8(bytes) {
+                Ok(text) if !text.chars().any(char::is_control) => Some(format!("{:?}", text)),
+                _ => Some(hex_string(bytes)),
+            }
+        }
+    }
+}
+
+/// Previews the call in a signing payload or unsigned extrinsic. Returns
+/// `None` if the call is not in the registry.
+pub fn preview_call(payload: &[u8], registry: &CallRegistry) -> Option<CallPreview> {
+    let mut data = payload;
+    if let Some(PayloadKind::ScaleExtrinsic { signed: false, .. }) = scale_extrinsic(payload) {
+        let (_, prefix) = scale_compact(payload)?;
+        data = &payload[prefix + 1..];
+    }
+    let index = take(&mut data, 2)?;
+    let info = registry.get(index[0], index[1])?;
+
+    let mut args = Vec::with_capacity(info.args.len());
+    let mut complete = true;
+    for (name, ty) in &info.args {
+        match decode_arg(&mut data, *ty) {
+            Some(value) => args.push((name.clone(), value)),
+            None => {
+                complete = false;
+                break;
+            }
+        }
+    }
+    Some(CallPreview {
+        pallet: info.pallet.clone(),
+        call: info.call.clone(),
+        args,
+        complete,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn registry() -> CallRegistry {
+        let mut registry = CallRegistry::new();
+        registry.register(
+            5,
+            "Balances",
+            0,
+            "transfer_allow_death",
+            &[("dest", ArgType::MultiAddress), ("value", ArgType::Compact)],
+        );
+        registry
+    }
+
+    fn transfer_call() -> Vec<u8> {
+        let mut call = vec![5, 0, 0];
+        call.extend_from_slice(&[0x11; 32]);
+        // Compact 1_000_000: four-byte mode
+        call.extend_from_slice(&((1_000_000u32 << 2) | 0b10).to_le_bytes());
+        call
+    }
+
+    #[test]
+    fn test_preview_transfer() {
+        let mut payload = transfer_call();
+        // Signed extensions and additional data follow the call in a signing payload
+        payload.extend_from_slice(&[0u8; 40]);
+        let preview = preview_call(&payload, &registry()).expect("No preview");
+        assert!(preview.complete);
+        assert_eq!(
+            preview.args[1],
+            ("value".to_string(), "1000000".to_string())
+        );
+        assert!(preview
+            .to_string()
+            .starts_with("Balances.transfer_allow_death(dest: 0x1111"));
+    }
+
+    #[test]
+    fn test_truncated_and_unknown() {
+        let call = transfer_call();
+        let preview = preview_call(&call[..20], &registry()).expect("No preview");
+        assert!(!preview.complete);
+        assert!(preview.args.is_empty());
+        assert!(preview_call(&[9, 9], &registry()).is_none());
+    }
+}
@@ -0,0 +1,134 @@
+//! Raw QR module matrices for UR parts, so GUI toolkits can render frames
+//! directly without a second QR dependency and encoding pass.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use qrcode::{Color, EcLevel, QrCode};
+
+pub use crate::capacity::ErrorCorrection;
+use crate::{encode_bytes, PartWriter, QuantusUrError};
+
+impl From<ErrorCorrection> for EcLevel {
+    fn from(level: ErrorCorrection) -> Self {
+        match level {
+            ErrorCorrection::Low => EcLevel::L,
+            ErrorCorrection::Medium => EcLevel::M,
+            ErrorCorrection::Quartile => EcLevel::Q,
+            ErrorCorrection::High => EcLevel::H,
+        }
+    }
+}
+
+/// Square grid of QR modules, `true` for dark. Does not include a quiet zone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QrMatrix {
+    width: usize,
+    modules: Vec<bool>,
+}
+
+impl QrMatrix {
+    /// Number of modules along each side.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Whether the module at column `x`, row `y` is dark.
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.width && self.modules[y * self.width + x]
+    }
+
+    /// Modules in row-major order.
+    pub fn modules(&self) -> &[bool] {
+        &self.modules
+    }
+
+    /// Rows of the matrix, top to bottom.
+    pub fn rows(&self) -> impl Iterator<Item = &[bool]> {
+        self.modules.chunks(self.width)
+    }
+}
+
+/// Encodes a single UR part string into a QR matrix.
+pub fn part_matrix(part: &str, ec: ErrorCorrection) -> Result<QrMatrix, QuantusUrError> {
+    // Alphanumeric mode needs uppercase; Keystone-flavored parts already are
+    let code = if part.bytes().any(|b| b.is_ascii_lowercase()) {
+        QrCode::with_error_correction_level(part.to_uppercase(), ec.into())
+    } else {
+        QrCode::with_error_correction_level(part, ec.into())
+    }
+    .map_err(|e| QuantusUrError::QrError(e.to_string()))?;
+    Ok(QrMatrix {
+        width: code.width(),
+        modules: code
+            .to_colors()
+            .into_iter()
+            .map(|c| c == Color::Dark)
+            .collect(),
+    })
+}
+
+/// [`PartWriter`] rendering each part straight into a QR matrix, for
+/// display loops driven by [`crate::UrEncoder::write_next_part`].
+#[derive(Debug, Clone, Default)]
+pub struct QrPartWriter {
+    ec: ErrorCorrection,
+    matrix: Option<QrMatrix>,
+}
+
+impl QrPartWriter {
+    pub fn new(ec: ErrorCorrection) -> Self {
+        QrPartWriter { ec, matrix: None }
+    }
+
+    /// Matrix of the most recently written part.
+    pub fn matrix(&self) -> Option<&QrMatrix> {
+        self.matrix.as_ref()
+    }
+}
+
+impl PartWriter for QrPartWriter {
+    fn write_part(&mut self, part: &str) -> Result<(), QuantusUrError> {
+        self.matrix = Some(part_matrix(part, self.ec)?);
+        Ok(())
+    }
+}
+
+/// Encodes a payload and returns one QR matrix per UR part.
+pub fn encode_bytes_matrices(
+    payload: &[u8],
+    ec: ErrorCorrection,
+) -> Result<Vec<QrMatrix>, QuantusUrError> {
+    encode_bytes(payload)?
+        .iter()
+        .map(|part| part_matrix(part, ec))
+        .collect()
+}
+
+/// Returns one QR matrix per part of an already encoded part list.
+pub fn part_matrices(
+    ur_parts: &[String],
+    ec: ErrorCorrection,
+) -> Result<Vec<QrMatrix>, QuantusUrError> {
+    ur_parts.iter().map(|part| part_matrix(part, ec)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matrix_per_part() {
+        let payload: Vec<u8> = (0..250).map(|i| i as u8).collect();
+        let parts = encode_bytes(&payload).expect("Encoding failed");
+        let matrices =
+            encode_bytes_matrices(&payload, ErrorCorrection::Low).expect("QR encoding failed");
+        assert_eq!(matrices.len(), parts.len());
+        for matrix in &matrices {
+            assert_eq!(matrix.modules().len(), matrix.width() * matrix.width());
+            assert_eq!(matrix.rows().count(), matrix.width());
+            // Top-left finder pattern corner is always dark
+            assert!(matrix.is_dark(0, 0));
+        }
+    }
+}
@@ -0,0 +1,266 @@
+//! RaptorQ ([RFC 6330](https://www.rfc-editor.org/rfc/rfc6330)) fragmenting,
+//! selected per message with [`crate::EncodingMode::RaptorQ`].
+//!
+//! The BC-UR fountain mixes fragments by XOR, so the last few missing
+//! fragments of a large message can take many extra scans. RaptorQ decodes
+//! from almost exactly as many packets as there are source symbols, whichever
+//! they are, which matters for very large payloads such as metadata proofs or
+//! firmware.
+//!
+//! Parts read `URQ:<TYPE>/<N>-<K>/<BODY>`, with `N` the 1-based packet number
+//! and `K` the number of source symbols. The minimal bytewords body holds the
+//! 12-byte object transmission information followed by the serialized
+//! packet, so a session can start from any part. The encoded object is the
+//! same CBOR message a UR would carry.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use raptorq::{Decoder, Encoder, EncodingPacket, ObjectTransmissionInformation};
+use ur::bytewords::{self, Style};
+
+use crate::part::MAX_SEQUENCE_COUNT;
+use crate::QuantusUrError;
+
+/// Scheme of RaptorQ parts, in the uppercase form used for QR codes.
+pub const RAPTORQ_SCHEME: &str = "URQ:";
+
+const OTI_LENGTH: usize = 12;
+/// Source block number and encoding symbol id in front of each symbol.
+const PACKET_HEADER: usize = 4;
+
+fn raptor_error(msg: &str) -> QuantusUrError {
+    QuantusUrError::UrError(format!("RaptorQ: {}", msg))
+}
+
+/// Whether `part` uses the RaptorQ scheme.
+pub(crate) fn is_raptor_part(part: &str) -> bool {
+    part.get(..RAPTORQ_SCHEME.len())
+        .map_or(false, |scheme| scheme.eq_ignore_ascii_case(RAPTORQ_SCHEME))
+}
+
+/// Encoder side: packets of one message, generated on demand.
+pub(crate) struct RaptorSource {
+    ur_type: String,
+    oti: [u8; OTI_LENGTH],
+    encoder: Encoder,
+    source: Vec<EncodingPacket>,
+}
+
+impl RaptorSource {
+    pub(crate) fn new(
+        message: &[u8],
+        ur_type: &str,
+        symbol_length: usize,
+    ) -> Result<Self, QuantusUrError> {
+        // Symbols are a multiple of RaptorQ's 8-byte alignment
+        let symbol_length = u16::try_from(symbol_length.max(8))
+            .map_err(|_| raptor_error("symbol length above 65535"))?;
+        let encoder = Encoder::with_defaults(message, symbol_length);
+        let source = encoder.get_encoded_packets(0);
+        if source.is_empty() || source.len() > MAX_SEQUENCE_COUNT as usize {
+            return Err(raptor_error("unsupported number of source symbols"));
+        }
+        Ok(RaptorSource {
+            ur_type: ur_type.to_string(),
+            oti: encoder.get_config().serialize(),
+            encoder,
+            source,
+        })
+    }
+
+    /// Number of source symbols, the minimal set of parts.
+    pub(crate) fn source_count(&self) -> usize {
+        self.source.len()
+    }
+
+    /// Part for the 0-based packet `index`: the source packets first, then
+    /// repair packets taken round-robin from each source block.
+    pub(crate) fn part(&self, index: usize) -> Result<String, QuantusUrError> {
+        let packet = match self.source.get(index) {
+            Some(packet) => packet.clone(),
+            None => {
+                let blocks = self.encoder.get_block_encoders();
+                let repair = index - self.source.len();
+                let block = &blocks[repair % blocks.len()];
+                let id = u32::try_from(repair / blocks.len())
+                    .map_err(|_| raptor_error("repair symbol id out of range"))?;
+                block
+                    .repair_packets(id, 1)
+                    .pop()
+                    .ok_or_else(|| raptor_error("no repair packet generated"))?
+            }
+        };
+        let mut body = self.oti.to_vec();
+        body.extend_from_slice(&packet.serialize());
+        Ok(format!(
+            "{}{}/{}-{}/{}",
+            RAPTORQ_SCHEME,
+            self.ur_type,
+            index + 1,
+            self.source.len(),
+            bytewords::encode(&body, Style::Minimal)
+        ))
+    }
+}
+
+/// A parsed RaptorQ part.
+pub(crate) struct RaptorPart {
+    pub ur_type: String,
+    pub sequence: u32,
+    pub source_count: u32,
+    oti: [u8; OTI_LENGTH],
+    packet: Vec<u8>,
+}
+
+pub(crate) fn parse_raptor_part(part: &str) -> Result<RaptorPart, QuantusUrError> {
+    let lower = part.to_lowercase();
+    let rest = lower
+        .get(RAPTORQ_SCHEME.len()..)
+        .filter(|_| is_raptor_part(part))
+        .ok_or_else(|| raptor_error("invalid scheme"))?;
+    let segments: Vec<&str> = rest.split('/').collect();
+    let [ur_type, sequence, body] = segments.as_slice() else {
+        return Err(raptor_error("invalid path"));
+    };
+    let (sequence, source_count) = sequence
+        .split_once('-')
+        .and_then(|(n, k)| Some((n.parse::<u32>().ok()?, k.parse::<u32>().ok()?)))
+        .filter(|&(n, k)| n > 0 && k > 0 && k <= MAX_SEQUENCE_COUNT)
+        .ok_or_else(|| raptor_error("invalid sequence"))?;
+    let body = bytewords::decode(body, Style::Minimal)
+        .map_err(|e| QuantusUrError::BytewordsError(e.to_string()))?;
+    if body.len() <= OTI_LENGTH + PACKET_HEADER {
+        return Err(raptor_error("body too short"));
+    }
+    let (oti, packet) = body.split_at(OTI_LENGTH);
+    Ok(RaptorPart {
+        ur_type: ur_type.to_string(),
+        sequence,
+        source_count,
+        oti: oti.try_into().map_err(|_| raptor_error("body too short"))?,
+        packet: packet.to_vec(),
+    })
+}
+
+/// Decoder side of a RaptorQ session.
+pub(crate) struct RaptorSession {
+    oti: [u8; OTI_LENGTH],
+    config: ObjectTransmissionInformation,
+    source_count: u32,
+    decoder: Decoder,
+    message: Option<Vec<u8>>,
+}
+
+impl RaptorSession {
+    /// Opens a session from its first part, refusing transmission parameters
+    /// this crate's encoder would not produce, since the decoder trusts them
+    /// for its allocations.
+    pub(crate) fn open(
+        part: &RaptorPart,
+        memory_limit: Option<usize>,
+    ) -> Result<Self, QuantusUrError> {
+        let config = ObjectTransmissionInformation::deserialize(&part.oti);
+        let symbol_size = u64::from(config.symbol_size());
+        let transfer_length = config.transfer_length();
+        // Bounds the transfer length before raptorq sees it
+        if symbol_size == 0
+            || transfer_length == 0
+            || (transfer_length + symbol_size - 1) / symbol_size != u64::from(part.source_count)
+        {
+            return Err(raptor_error(
+                "transfer length does not match the part count",
+            ));
+        }
+        if config
+            != ObjectTransmissionInformation::with_defaults(transfer_length, config.symbol_size())
+        {
+            return Err(raptor_error("unsupported transmission parameters"));
+        }
+        let required = usize::try_from(transfer_length).unwrap_or(usize::MAX);
+        if let Some(limit) = memory_limit {
+            if required > limit {
+                return Err(QuantusUrError::MemoryLimitExceeded { limit, required });
+            }
+        }
+        Ok(RaptorSession {
+            oti: part.oti,
+            config,
+            source_count: part.source_count,
+            decoder: Decoder::new(config),
+            message: None,
+        })
+    }
+
+    pub(crate) fn source_count(&self) -> u32 {
+        self.source_count
+    }
+
+    /// Transfer length plus the symbols received so far.
+    pub(crate) fn memory_usage(&self, received: usize) -> usize {
+        (self.config.transfer_length() as usize)
+            .saturating_add(received.saturating_mul(usize::from(self.config.symbol_size())))
+    }
+
+    /// Adds a packet of this session, returning whether the message is complete.
+    pub(crate) fn receive(&mut self, part: RaptorPart) -> Result<bool, QuantusUrError> {
+        if part.oti != self.oti || part.source_count != self.source_count {
+            return Err(raptor_error("part belongs to another message"));
+        }
+        if part.packet.len() != PACKET_HEADER + usize::from(self.config.symbol_size())
+            || part.packet[0] >= self.config.source_blocks()
+        {
+            return Err(raptor_error("malformed packet"));
+        }
+        if self.message.is_none() {
+            self.message = self
+                .decoder
+                .decode(EncodingPacket::deserialize(&part.packet));
+        }
+        Ok(self.message.is_some())
+    }
+
+    pub(crate) fn message(&self) -> Option<&[u8]> {
+        self.message.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decode_bytes, encode_bytes_with_options, EncodeOptions, EncodingMode, UrEncoder};
+
+    fn options() -> EncodeOptions {
+        EncodeOptions {
+            mode: EncodingMode::RaptorQ,
+            ..EncodeOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_raptorq_roundtrip() {
+        let payload: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+        let parts = encode_bytes_with_options(&payload, &options()).expect("Encoding failed");
+        assert!(parts[0].starts_with("URQ:QUANTUS-SIGN-REQUEST/1-"));
+        assert_eq!(decode_bytes(&parts).expect("Decoding failed"), payload);
+    }
+
+    #[test]
+    fn test_repair_packets_replace_lost_parts() {
+        let payload: Vec<u8> = (0..3000u32).map(|i| (i % 251) as u8).collect();
+        let mut encoder = UrEncoder::with_options(&payload, &options()).expect("Encoding failed");
+        let count = encoder.fragment_count();
+        let parts: Vec<String> = (0..count + count / 3 + 2)
+            .map(|_| encoder.next_part().expect("Encoding failed"))
+            .collect();
+        // Drop every third source packet; the repair packets make up for them
+        let kept: Vec<String> = parts
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i >= count || i % 3 != 1)
+            .map(|(_, part)| part.clone())
+            .collect();
+        assert!(kept.len() > count);
+        assert_eq!(decode_bytes(&kept).expect("Decoding failed"), payload);
+    }
+}
@@ -0,0 +1,255 @@
+//! Sign responses sent back from the signer, correlated to their request by
+//! the hash of the request payload.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use minicbor::{Decoder, Encoder};
+
+use crate::cbor::{cbor_error, expect_ur_type};
+use crate::transfer::{blake2_256, Hash};
+use crate::{
+    constant_time_eq, decode_message, encode_message, QuantusUrError, UrDecoder, UrEncoder, UrPart,
+};
+
+pub const RESPONSE_UR_TYPE: &str = "quantus-sign-response";
+
+pub(crate) const RESPONSE_VERSION: u8 = 1;
+
+/// Signature over a sign request, tagged with the BLAKE2b-256 hash of the
+/// request payload it answers and optionally the signer's public key.
+///
+/// The CBOR form is `[version, request_hash, signature]`, with the public key
+/// as a fourth element when present.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SignResponse {
+    pub request_hash: Hash,
+    pub signature: Vec<u8>,
+    pub public_key: Option<Vec<u8>>,
+}
+
+impl SignResponse {
+    /// Response answering the request with the given payload.
+    pub fn for_request(request_payload: &[u8], signature: Vec<u8>) -> Self {
+        SignResponse {
+            request_hash: blake2_256(request_payload),
+            signature,
+            public_key: None,
+        }
+    }
+
+    /// Includes the signer's public key, so the signature can be checked
+    /// without looking the key up.
+    pub fn with_public_key(mut self, public_key: Vec<u8>) -> Self {
+        self.public_key = Some(public_key);
+        self
+    }
+
+    /// Whether the response answers the request with the given payload.
+    pub fn matches_request(&self, request_payload: &[u8]) -> bool {
+        constant_time_eq(&self.request_hash, &blake2_256(request_payload))
+    }
+
+    pub(crate) fn to_cbor(&self) -> Result<Vec<u8>, QuantusUrError> {
+        let mut e = Encoder::new(Vec::new());
+        let len = if self.public_key.is_some() { 4 } else { 3 };
+        e.array(len)
+            .and_then(|e| e.u8(RESPONSE_VERSION))
+            .and_then(|e| e.bytes(&self.request_hash))
+            .and_then(|e| e.bytes(&self.signature))
+            .map_err(cbor_error)?;
+        if let Some(public_key) = &self.public_key {
+            e.bytes(public_key).map_err(cbor_error)?;
+        }
+        Ok(e.into_writer())
+    }
+
+    pub(crate) fn from_cbor(cbor: &[u8]) -> Result<Self, QuantusUrError> {
+        let mut d = Decoder::new(cbor);
+        let len = d.array().map_err(cbor_error)?;
+        if !matches!(len, Some(3 | 4)) {
+            return Err(QuantusUrError::CborError(
+                "Malformed sign response".to_string(),
+            ));
+        }
+        let version = d.u8().map_err(cbor_error)?;
+        if version != RESPONSE_VERSION {
+            return Err(QuantusUrError::CborError(format!(
+                "Unsupported sign response version {}",
+                version
+            )));
+        }
+        let request_hash = d
+            .bytes()
+            .map_err(cbor_error)?
+            .try_into()
+            .map_err(|_| QuantusUrError::CborError("Expected a 32-byte hash".to_string()))?;
+        let signature = d.bytes().map_err(cbor_error)?.to_vec();
+        let public_key = match len {
+            Some(4) => Some(d.bytes().map_err(cbor_error)?.to_vec()),
+            _ => None,
+        };
+        Ok(SignResponse {
+            request_hash,
+            signature,
+            public_key,
+        })
+    }
+
+    /// Minimal set of UR parts carrying the response.
+    pub fn to_parts(&self) -> Result<Vec<String>, QuantusUrError> {
+        encode_message(self.to_cbor()?, RESPONSE_UR_TYPE)
+    }
+
+    /// Looping encoder for the response.
+    pub fn encoder(&self) -> Result<UrEncoder, QuantusUrError> {
+        UrEncoder::from_cbor(self.to_cbor()?, RESPONSE_UR_TYPE)
+    }
+
+    pub fn from_parts(ur_parts: &[String]) -> Result<Self, QuantusUrError> {
+        let (ur_type, cbor) = decode_message(ur_parts)?;
+        expect_ur_type(&ur_type, RESPONSE_UR_TYPE)?;
+        Self::from_cbor(&cbor)
+    }
+
+    /// Response reassembled by a generic decode session, for callers that
+    /// scan any message type and branch on [`UrDecoder::ur_type`].
+    pub fn from_decoder(decoder: &UrDecoder) -> Result<Self, QuantusUrError> {
+        expect_ur_type(decoder.ur_type().unwrap_or_default(), RESPONSE_UR_TYPE)?;
+        Self::from_cbor(&decoder.cbor_message()?)
+    }
+}
+
+/// Wallet-side decode session for the response to one request.
+///
+/// Accepts only `quantus-sign-response` parts, reassembles multi-part
+/// responses (large post-quantum signatures rarely fit one QR code) and
+/// yields the response once it is complete and answers the request.
+pub struct ResponseCollector {
+    request_hash: Hash,
+    decoder: UrDecoder,
+}
+
+impl ResponseCollector {
+    pub fn for_request(request_payload: &[u8]) -> Self {
+        Self::for_request_hash(blake2_256(request_payload))
+    }
+
+    pub fn for_request_hash(request_hash: Hash) -> Self {
+        ResponseCollector {
+            request_hash,
+            decoder: UrDecoder::new(),
+        }
+    }
+
+    /// The in-progress session, for progress reporting.
+    pub fn decoder(&self) -> &UrDecoder {
+        &self.decoder
+    }
+
+    /// Drops any partially scanned response.
+    pub fn reset(&mut self) {
+        self.decoder.reset();
+    }
+
+    /// Feeds a scanned part, returning the response once complete.
+    ///
+    /// A part that does not fit the response being scanned, or a complete
+    /// response to another request ([`QuantusUrError::ResponseMismatch`]),
+    /// discards the partial session so the next scan starts afresh.
+    pub fn receive(&mut self, part: &str) -> Result<Option<SignResponse>, QuantusUrError> {
+        let part: UrPart = part.parse()?;
+        if part.ur_type() != RESPONSE_UR_TYPE {
+            return Err(QuantusUrError::UrError(
+                "Scanned part is not a sign response".to_string(),
+            ));
+        }
+        if let Err(e) = self.decoder.receive_part(&part) {
+            self.reset();
+            return Err(e);
+        }
+        if !self.decoder.is_complete() {
+            return Ok(None);
+        }
+
+        let response = SignResponse::from_decoder(&self.decoder);
+        self.reset();
+        let response = response?;
+        if !constant_time_eq(&response.request_hash, &self.request_hash) {
+            return Err(QuantusUrError::ResponseMismatch);
+        }
+        Ok(Some(response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encode_bytes;
+
+    #[test]
+    fn test_response_roundtrip() {
+        let request = b"transfer 10 QUAN";
+        let response = SignResponse::for_request(request, alloc::vec![0xab; 600])
+            .with_public_key(alloc::vec![0xcd; 32]);
+        let parts = response.to_parts().expect("Encoding failed");
+        assert!(parts.len() > 1);
+        let decoded = SignResponse::from_parts(&parts).expect("Decoding failed");
+        assert_eq!(decoded, response);
+        assert!(decoded.matches_request(request));
+        assert!(!decoded.matches_request(b"transfer 99 QUAN"));
+    }
+
+    #[test]
+    fn test_from_decoder() {
+        let response = SignResponse::for_request(b"transfer 10 QUAN", alloc::vec![0xab; 600]);
+        let mut decoder = UrDecoder::new();
+        for part in response.to_parts().expect("Encoding failed") {
+            decoder.receive(&part).expect("Receive failed");
+        }
+        assert_eq!(
+            SignResponse::from_decoder(&decoder).expect("Decoding failed"),
+            response
+        );
+
+        let mut decoder = UrDecoder::new();
+        for part in encode_bytes(b"transfer 10 QUAN").expect("Encoding failed") {
+            decoder.receive(&part).expect("Receive failed");
+        }
+        assert!(SignResponse::from_decoder(&decoder).is_err());
+    }
+
+    #[test]
+    fn test_collector_multi_part() {
+        let request = b"transfer 10 QUAN";
+        // ML-DSA-87 signature size
+        let response = SignResponse::for_request(request, alloc::vec![0x5a; 4627]);
+        let parts = response.to_parts().expect("Encoding failed");
+        assert!(parts.len() > 1);
+
+        let mut collector = ResponseCollector::for_request(request);
+        let (last, rest) = parts.split_last().unwrap();
+        for part in rest {
+            assert_eq!(collector.receive(part).expect("Receive failed"), None);
+        }
+        assert_eq!(collector.decoder().received_parts(), rest.len());
+        assert_eq!(
+            collector.receive(last).expect("Receive failed"),
+            Some(response)
+        );
+
+        let mut other = ResponseCollector::for_request(b"another request");
+        let results: Vec<_> = parts.iter().map(|part| other.receive(part)).collect();
+        assert!(matches!(
+            results.last(),
+            Some(Err(QuantusUrError::ResponseMismatch))
+        ));
+    }
+
+    #[test]
+    fn test_request_parts_rejected() {
+        let parts = encode_bytes(b"not a response").expect("Encoding failed");
+        assert!(SignResponse::from_parts(&parts).is_err());
+    }
+}
@@ -0,0 +1,89 @@
+//! Source of all randomness used by the library: fountain seeds, encryption
+//! salts and nonces, and request ids.
+//!
+//! Everything that needs randomness takes a [`RandomSource`], so firmware can
+//! supply its hardware TRNG and tests a deterministic source. [`OsRandom`],
+//! built with the `getrandom` feature (enabled by `std`), is the secure
+//! default used by the functions that do not take one.
+
+use crate::QuantusUrError;
+
+/// A cryptographically secure random number generator.
+///
+/// Implementations must be suitable for keys and nonces; a source that cannot
+/// deliver (e.g. an unseeded TRNG) returns an error rather than weak bytes.
+pub trait RandomSource {
+    fn fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), QuantusUrError>;
+}
+
+impl<R: RandomSource + ?Sized> RandomSource for &mut R {
+    fn fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), QuantusUrError> {
+        (**self).fill_bytes(dest)
+    }
+}
+
+/// The operating system's generator, via `getrandom`.
+#[cfg(feature = "getrandom")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsRandom;
+
+#[cfg(feature = "getrandom")]
+impl RandomSource for OsRandom {
+    fn fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), QuantusUrError> {
+        getrandom::getrandom(dest).map_err(|e| QuantusUrError::IoError(alloc::format!("{}", e)))
+    }
+}
+
+/// Deterministic SplitMix64 stream for reproducible tests. Not secure: never
+/// use it for real messages.
+#[cfg(any(test, feature = "testing"))]
+#[derive(Debug, Clone)]
+pub struct SeededRandom(u64);
+
+#[cfg(any(test, feature = "testing"))]
+impl SeededRandom {
+    pub fn new(seed: u64) -> Self {
+        SeededRandom(seed)
+    }
+
+    /// Next value of the SplitMix64 stream.
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl RandomSource for SeededRandom {
+    fn fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), QuantusUrError> {
+        for chunk in dest.chunks_mut(8) {
+            chunk.copy_from_slice(&self.next_u64().to_le_bytes()[..chunk.len()]);
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn random_array<const N: usize>(
+    rng: &mut impl RandomSource,
+) -> Result<[u8; N], QuantusUrError> {
+    let mut bytes = [0u8; N];
+    rng.fill_bytes(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_source_is_reproducible() {
+        let mut a = SeededRandom::new(7);
+        let mut b = SeededRandom::new(7);
+        let first: [u8; 13] = random_array(&mut a).expect("RNG failed");
+        assert_eq!(first, random_array::<13>(&mut b).expect("RNG failed"));
+        assert_ne!(first, random_array::<13>(&mut a).expect("RNG failed"));
+    }
+}
@@ -0,0 +1,166 @@
+//! JSON models of the sign request and response for web wallets and backend
+//! services that build payloads without a CBOR library.
+//!
+//! Byte fields are hex strings with an optional `0x` prefix. The JSON Schema
+//! documents are published as [`SIGN_REQUEST_SCHEMA`] and
+//! [`SIGN_RESPONSE_SCHEMA`] and must be kept in sync with these types.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::response::SignResponse;
+use crate::{encode_bytes, QuantusUrError};
+
+/// JSON Schema (draft 2020-12) for [`SignRequestJson`].
+pub const SIGN_REQUEST_SCHEMA: &str = r#"{
+  "$schema": "https://json-schema.org/draft/2020-12/schema",
+  "title": "SignRequest",
+  "type": "object",
+  "properties": {
+    "payload": {
+      "description": "SCALE-encoded signing payload as hex",
+      "type": "string",
+      "pattern": "^(0x)?([0-9a-fA-F]{2})+$"
+    }
+  },
+  "required": ["payload"],
+  "additionalProperties": false
+}"#;
+
+/// JSON Schema (draft 2020-12) for [`SignResponseJson`].
+pub const SIGN_RESPONSE_SCHEMA: &str = r#"{
+  "$schema": "https://json-schema.org/draft/2020-12/schema",
+  "title": "SignResponse",
+  "type": "object",
+  "properties": {
+    "request_hash": {
+      "description": "BLAKE2b-256 hash of the request payload as hex",
+      "type": "string",
+      "pattern": "^(0x)?[0-9a-fA-F]{64}$"
+    },
+    "signature": {
+      "description": "Signature over the request payload as hex",
+      "type": "string",
+      "pattern": "^(0x)?([0-9a-fA-F]{2})+$"
+    },
+    "public_key": {
+      "description": "Signer's public key as hex",
+      "type": "string",
+      "pattern": "^(0x)?([0-9a-fA-F]{2})+$"
+    }
+  },
+  "required": ["request_hash", "signature"],
+  "additionalProperties": false
+}"#;
+
+fn schema_error(msg: String) -> QuantusUrError {
+    QuantusUrError::SchemaError(msg)
+}
+
+fn parse_hex(field: &str, value: &str) -> Result<Vec<u8>, QuantusUrError> {
+    let digits = value.strip_prefix("0x").unwrap_or(value);
+    let bytes = hex::decode(digits).map_err(|e| schema_error(format!("{}: {}", field, e)))?;
+    if bytes.is_empty() {
+        return Err(schema_error(format!("{} must not be empty", field)));
+    }
+    Ok(bytes)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SignRequestJson {
+    pub payload: String,
+}
+
+impl SignRequestJson {
+    /// Checks the request against the schema and returns the payload bytes.
+    pub fn validate(&self) -> Result<Vec<u8>, QuantusUrError> {
+        parse_hex("payload", &self.payload)
+    }
+
+    /// Validates the request and encodes it into the minimal set of parts.
+    pub fn encode(&self) -> Result<Vec<String>, QuantusUrError> {
+        encode_bytes(&self.validate()?)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SignResponseJson {
+    pub request_hash: String,
+    pub signature: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+}
+
+impl SignResponseJson {
+    /// Checks the response against the schema and converts it.
+    pub fn validate(&self) -> Result<SignResponse, QuantusUrError> {
+        let request_hash = parse_hex("request_hash", &self.request_hash)?
+            .try_into()
+            .map_err(|_| schema_error("request_hash must be 32 bytes".to_string()))?;
+        Ok(SignResponse {
+            request_hash,
+            signature: parse_hex("signature", &self.signature)?,
+            public_key: self
+                .public_key
+                .as_deref()
+                .map(|key| parse_hex("public_key", key))
+                .transpose()?,
+        })
+    }
+}
+
+impl From<&SignResponse> for SignResponseJson {
+    fn from(response: &SignResponse) -> Self {
+        SignResponseJson {
+            request_hash: format!("0x{}", hex::encode(response.request_hash)),
+            signature: format!("0x{}", hex::encode(&response.signature)),
+            public_key: response
+                .public_key
+                .as_ref()
+                .map(|key| format!("0x{}", hex::encode(key))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode_bytes;
+
+    #[test]
+    fn test_request_validation() {
+        let request = SignRequestJson {
+            payload: "0xdeadbeef".to_string(),
+        };
+        let parts = request.encode().expect("Encoding failed");
+        assert_eq!(
+            decode_bytes(&parts).expect("Decoding failed"),
+            [0xde, 0xad, 0xbe, 0xef]
+        );
+        for payload in ["", "0x", "0xabc", "zz"] {
+            let request = SignRequestJson {
+                payload: payload.to_string(),
+            };
+            assert!(matches!(
+                request.validate(),
+                Err(QuantusUrError::SchemaError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_response_roundtrip() {
+        let response = SignResponse::for_request(b"payload", alloc::vec![9; 64]);
+        let json = SignResponseJson::from(&response);
+        assert_eq!(json.validate().expect("Validation failed"), response);
+
+        let short = SignResponseJson {
+            request_hash: "0xabcd".to_string(),
+            ..json
+        };
+        assert!(short.validate().is_err());
+    }
+}
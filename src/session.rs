@@ -0,0 +1,211 @@
+//! Requester side of a complete air-gapped signing round trip.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::clock::Clock;
+use crate::response::{ResponseCollector, SignResponse};
+use crate::{QuantusUrError, UrEncoder};
+
+/// Where an [`AirGapSession`] is in the round trip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SessionState {
+    /// Showing the request; no response part scanned yet.
+    AwaitingResponse,
+    /// Some response parts scanned, message incomplete.
+    ReceivingResponse,
+    /// A response matching the request was received.
+    Completed,
+    /// The session passed its expiry before a response arrived.
+    Expired,
+}
+
+/// Drives one request/response exchange: emits the request parts, accepts
+/// scanned response parts, checks that the response answers this request and
+/// yields the typed [`SignResponse`].
+///
+/// A response for a different request is rejected with
+/// [`QuantusUrError::ResponseMismatch`] and the session keeps waiting, so the
+/// user can scan the right one.
+pub struct AirGapSession {
+    payload: Vec<u8>,
+    encoder: UrEncoder,
+    collector: ResponseCollector,
+    state: SessionState,
+    response: Option<SignResponse>,
+    expires_at: Option<u64>,
+}
+
+impl AirGapSession {
+    pub fn new(payload: &[u8]) -> Result<Self, QuantusUrError> {
+        Ok(AirGapSession {
+            payload: payload.to_vec(),
+            encoder: UrEncoder::new(payload)?,
+            collector: ResponseCollector::for_request(payload),
+            state: SessionState::AwaitingResponse,
+            response: None,
+            expires_at: None,
+        })
+    }
+
+    /// Expires the session at `expires_at`, in whatever time unit the caller
+    /// passes to [`AirGapSession::check_expiry`].
+    pub fn with_expiry(mut self, expires_at: u64) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    /// Expires the session `ttl` milliseconds after `clock`'s current time;
+    /// check it with [`AirGapSession::check_expiry_with_clock`].
+    pub fn with_ttl(self, ttl: u64, clock: &impl Clock) -> Self {
+        self.with_expiry(clock.now().saturating_add(ttl))
+    }
+
+    pub fn state(&self) -> SessionState {
+        self.state
+    }
+
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// Next request part to display.
+    pub fn next_part(&mut self) -> Result<String, QuantusUrError> {
+        self.ensure_open()?;
+        self.encoder.next_part()
+    }
+
+    /// Replays the request from its minimal set and drops any partially
+    /// scanned response, e.g. when the user reopens the signing screen.
+    pub fn restart(&mut self) -> Result<(), QuantusUrError> {
+        self.ensure_open()?;
+        self.encoder.restart_from_minimal_set()?;
+        self.collector.reset();
+        self.state = SessionState::AwaitingResponse;
+        Ok(())
+    }
+
+    /// Marks the session expired once `now` reaches the expiry. Returns
+    /// whether it is expired.
+    pub fn check_expiry(&mut self, now: u64) -> bool {
+        if self.state != SessionState::Completed && self.expires_at.map_or(false, |at| now >= at) {
+            self.state = SessionState::Expired;
+        }
+        self.state == SessionState::Expired
+    }
+
+    /// Like [`AirGapSession::check_expiry`], reading the time from `clock`.
+    pub fn check_expiry_with_clock(&mut self, clock: &impl Clock) -> bool {
+        self.check_expiry(clock.now())
+    }
+
+    /// Feeds a scanned response part.
+    pub fn receive(&mut self, part: &str) -> Result<SessionState, QuantusUrError> {
+        if self.state == SessionState::Completed {
+            return Ok(self.state);
+        }
+        self.ensure_open()?;
+        match self.collector.receive(part) {
+            Ok(Some(response)) => {
+                self.response = Some(response);
+                self.state = SessionState::Completed;
+            }
+            Ok(None) => self.state = SessionState::ReceivingResponse,
+            Err(e) => {
+                if self.collector.decoder().received_parts() == 0 {
+                    self.state = SessionState::AwaitingResponse;
+                }
+                return Err(e);
+            }
+        }
+        Ok(self.state)
+    }
+
+    /// The validated response, once completed.
+    pub fn response(&self) -> Option<&SignResponse> {
+        self.response.as_ref()
+    }
+
+    fn ensure_open(&self) -> Result<(), QuantusUrError> {
+        match self.state {
+            SessionState::Expired => Err(QuantusUrError::SessionExpired),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REQUEST: &[u8] = b"transfer 10 QUAN to qz1...";
+
+    fn scan(session: &mut AirGapSession, parts: &[String]) -> Result<SessionState, QuantusUrError> {
+        let mut state = session.state();
+        for part in parts {
+            state = session.receive(part)?;
+        }
+        Ok(state)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut session = AirGapSession::new(REQUEST).expect("Session creation failed");
+        let request_part = session.next_part().expect("Part generation failed");
+        assert_eq!(
+            crate::decode_bytes(&[request_part]).expect("Decoding failed"),
+            REQUEST
+        );
+
+        let response = SignResponse::for_request(REQUEST, alloc::vec![7; 700]);
+        let parts = response.to_parts().expect("Encoding failed");
+        assert_eq!(
+            scan(&mut session, &parts).expect("Receive failed"),
+            SessionState::Completed
+        );
+        assert_eq!(session.response(), Some(&response));
+    }
+
+    #[test]
+    fn test_wrong_response_rejected() {
+        let mut session = AirGapSession::new(REQUEST).expect("Session creation failed");
+        let wrong = SignResponse::for_request(b"another request", alloc::vec![1; 64]);
+        assert!(matches!(
+            scan(&mut session, &wrong.to_parts().unwrap()),
+            Err(QuantusUrError::ResponseMismatch)
+        ));
+        assert_eq!(session.state(), SessionState::AwaitingResponse);
+
+        let right = SignResponse::for_request(REQUEST, alloc::vec![1; 64]);
+        assert_eq!(
+            scan(&mut session, &right.to_parts().unwrap()).expect("Receive failed"),
+            SessionState::Completed
+        );
+    }
+
+    #[test]
+    fn test_expiry() {
+        let mut session = AirGapSession::new(REQUEST)
+            .expect("Session creation failed")
+            .with_expiry(100);
+        assert!(!session.check_expiry(99));
+        assert!(session.check_expiry(100));
+        assert!(matches!(
+            session.next_part(),
+            Err(QuantusUrError::SessionExpired)
+        ));
+    }
+
+    #[test]
+    fn test_ttl_with_clock() {
+        let clock = crate::clock::ManualClock::new(1_000);
+        let mut session = AirGapSession::new(REQUEST)
+            .expect("Session creation failed")
+            .with_ttl(60_000, &clock);
+        clock.advance(59_999);
+        assert!(!session.check_expiry_with_clock(&clock));
+        clock.advance(1);
+        assert!(session.check_expiry_with_clock(&clock));
+    }
+}
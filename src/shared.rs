@@ -0,0 +1,106 @@
+use std::string::{String, ToString};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::vec::Vec;
+
+use crate::{DecodeEvent, DecodeOptions, QuantusUrError, UrDecoder};
+
+/// A [`UrDecoder`] that can be fed and queried from several threads, e.g. a
+/// camera callback thread and a UI thread.
+///
+/// Clones share the same session. Every call takes an internal lock, so
+/// receives are applied one at a time in lock order and each query sees the
+/// state between two receives, never a part half-applied. Separate queries
+/// may observe different states; use [`SharedDecoder::progress`] when the
+/// received and expected counts must agree.
+#[derive(Clone, Default)]
+pub struct SharedDecoder {
+    inner: Arc<Mutex<UrDecoder>>,
+}
+
+impl SharedDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_options(options: DecodeOptions) -> Self {
+        Self::from(UrDecoder::with_options(options))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, UrDecoder> {
+        // The decoder is never left mid-update by a panic in this module, so a
+        // poisoned lock still guards a consistent session.
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn receive(&self, part: &str) -> Result<DecodeEvent, QuantusUrError> {
+        self.lock().receive_event(part)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.lock().is_complete()
+    }
+
+    /// Received and expected part counts, read together.
+    pub fn progress(&self) -> (usize, Option<usize>) {
+        let decoder = self.lock();
+        (decoder.received_parts(), decoder.expected_parts())
+    }
+
+    pub fn missing_parts(&self) -> Vec<u32> {
+        self.lock().missing_parts()
+    }
+
+    pub fn ur_type(&self) -> Option<String> {
+        self.lock().ur_type().map(ToString::to_string)
+    }
+
+    pub fn message(&self) -> Result<Vec<u8>, QuantusUrError> {
+        self.lock().message()
+    }
+
+    /// Abandons the current message for every holder of this handle.
+    pub fn reset(&self) {
+        self.lock().reset()
+    }
+}
+
+impl From<UrDecoder> for SharedDecoder {
+    fn from(decoder: UrDecoder) -> Self {
+        SharedDecoder {
+            inner: Arc::new(Mutex::new(decoder)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encode_bytes;
+    use std::thread;
+
+    #[test]
+    fn test_concurrent_receive() {
+        let payload: Vec<u8> = (0..3000u32).map(|i| (i % 251) as u8).collect();
+        let parts = encode_bytes(&payload).expect("Encoding failed");
+        let decoder = SharedDecoder::new();
+
+        let handles: Vec<_> = parts
+            .chunks(parts.len() / 2 + 1)
+            .map(|chunk| {
+                let decoder = decoder.clone();
+                let chunk = chunk.to_vec();
+                thread::spawn(move || {
+                    for part in &chunk {
+                        decoder.receive(part).expect("Receive failed");
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().expect("Thread panicked");
+        }
+
+        assert!(decoder.is_complete());
+        assert_eq!(decoder.message().expect("Decoding failed"), payload);
+    }
+}
@@ -0,0 +1,316 @@
+//! Payloads split across several sequential UR sessions.
+//!
+//! A payload larger than the per-session ceiling is cut into segments, each
+//! sent as its own `quantus-split` message the receiver scans to completion
+//! before the sender moves on. Every segment carries a continuation header
+//! `[version, transfer id, index, count, total size, data]`, where the
+//! transfer id is the payload hash, so segments are self-describing and can
+//! arrive in any order. Unlike [`crate::transfer`] there is no manifest or
+//! return channel: the receiver learns the layout from the first segment.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use minicbor::{Decoder, Encoder};
+
+use crate::cbor::{cbor_error, expect_ur_type};
+use crate::transfer::{blake2_256, Hash, MAX_CHUNK_COUNT};
+use crate::{constant_time_eq, EncodeOptions, QuantusUrError, UrDecoder, UrEncoder};
+
+pub const SPLIT_UR_TYPE: &str = "quantus-split";
+
+pub(crate) const SPLIT_VERSION: u8 = 1;
+
+struct Segment<'a> {
+    transfer_id: Hash,
+    index: u32,
+    count: u32,
+    total_size: u64,
+    data: &'a [u8],
+}
+
+impl<'a> Segment<'a> {
+    fn to_cbor(&self) -> Result<Vec<u8>, QuantusUrError> {
+        let mut e = Encoder::new(Vec::new());
+        e.array(6)
+            .and_then(|e| e.u8(SPLIT_VERSION))
+            .and_then(|e| e.bytes(&self.transfer_id))
+            .and_then(|e| e.u32(self.index))
+            .and_then(|e| e.u32(self.count))
+            .and_then(|e| e.u64(self.total_size))
+            .and_then(|e| e.bytes(self.data))
+            .map_err(cbor_error)?;
+        Ok(e.into_writer())
+    }
+
+    fn from_cbor(cbor: &'a [u8]) -> Result<Self, QuantusUrError> {
+        let mut d = Decoder::new(cbor);
+        if d.array().map_err(cbor_error)? != Some(6) {
+            return Err(QuantusUrError::CborError(
+                "Malformed split segment".to_string(),
+            ));
+        }
+        let version = d.u8().map_err(cbor_error)?;
+        if version != SPLIT_VERSION {
+            return Err(QuantusUrError::TransferError(format!(
+                "Unsupported split version {}",
+                version
+            )));
+        }
+        let transfer_id = d
+            .bytes()
+            .map_err(cbor_error)?
+            .try_into()
+            .map_err(|_| QuantusUrError::CborError("Expected a 32-byte hash".to_string()))?;
+        let index = d.u32().map_err(cbor_error)?;
+        let count = d.u32().map_err(cbor_error)?;
+        let total_size = d.u64().map_err(cbor_error)?;
+        let data = d.bytes().map_err(cbor_error)?;
+        if count == 0 || count as usize > MAX_CHUNK_COUNT || index >= count || total_size == 0 {
+            return Err(QuantusUrError::TransferError(
+                "Segment layout out of range".to_string(),
+            ));
+        }
+        Ok(Segment {
+            transfer_id,
+            index,
+            count,
+            total_size,
+            data,
+        })
+    }
+}
+
+/// Sending side: one UR session per segment, shown in order.
+pub struct SplitSender {
+    payload: Vec<u8>,
+    transfer_id: Hash,
+    max_session_bytes: usize,
+}
+
+impl SplitSender {
+    /// Splits `payload` into segments of at most `max_session_bytes`. A
+    /// payload under the ceiling still travels as a single segment.
+    pub fn new(payload: &[u8], max_session_bytes: usize) -> Result<Self, QuantusUrError> {
+        if payload.is_empty() {
+            return Err(QuantusUrError::EmptyPayload);
+        }
+        if max_session_bytes == 0 || payload.chunks(max_session_bytes).len() > MAX_CHUNK_COUNT {
+            return Err(QuantusUrError::TransferError(
+                "Session size out of range".to_string(),
+            ));
+        }
+        Ok(SplitSender {
+            payload: payload.to_vec(),
+            transfer_id: blake2_256(payload),
+            max_session_bytes,
+        })
+    }
+
+    /// The payload hash carried by every segment.
+    pub fn transfer_id(&self) -> &Hash {
+        &self.transfer_id
+    }
+
+    pub fn session_count(&self) -> usize {
+        self.payload.chunks(self.max_session_bytes).len()
+    }
+
+    /// Looping encoder for the session at `index`.
+    pub fn session_encoder(
+        &self,
+        index: usize,
+        options: &EncodeOptions,
+    ) -> Result<UrEncoder, QuantusUrError> {
+        UrEncoder::from_cbor_with_options(self.segment_cbor(index)?, SPLIT_UR_TYPE, options)
+    }
+
+    /// Minimal set of UR parts for the session at `index`.
+    pub fn session_parts(&self, index: usize) -> Result<Vec<String>, QuantusUrError> {
+        self.session_encoder(index, &EncodeOptions::default())?
+            .into_minimal_parts()
+            .collect()
+    }
+
+    fn segment_cbor(&self, index: usize) -> Result<Vec<u8>, QuantusUrError> {
+        let data = self
+            .payload
+            .chunks(self.max_session_bytes)
+            .nth(index)
+            .ok_or_else(|| {
+                QuantusUrError::TransferError(format!("No session at index {}", index))
+            })?;
+        Segment {
+            transfer_id: self.transfer_id,
+            index: index as u32,
+            count: self.session_count() as u32,
+            total_size: self.payload.len() as u64,
+            data,
+        }
+        .to_cbor()
+    }
+}
+
+/// Receiving side: feed it every scanned part, across sessions.
+///
+/// Parts go to a streaming [`UrDecoder`] that is reset whenever a session
+/// completes, so [`SplitReceiver::session_progress`] tracks the session being
+/// scanned and [`SplitReceiver::progress`] the sessions stored so far.
+#[derive(Default)]
+pub struct SplitReceiver {
+    decoder: UrDecoder,
+    transfer_id: Option<Hash>,
+    total_size: u64,
+    segments: Vec<Option<Vec<u8>>>,
+}
+
+impl SplitReceiver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Processes one scanned part and returns whether the whole payload has
+    /// been received.
+    pub fn receive(&mut self, part: &str) -> Result<bool, QuantusUrError> {
+        self.decoder.receive(part)?;
+        if self.decoder.is_complete() {
+            let result = self.store_session();
+            self.decoder.reset();
+            result?;
+        }
+        Ok(self.is_complete())
+    }
+
+    fn store_session(&mut self) -> Result<(), QuantusUrError> {
+        let ur_type = self.decoder.ur_type().unwrap_or_default();
+        expect_ur_type(ur_type, SPLIT_UR_TYPE)?;
+        let cbor = self.decoder.cbor_message()?;
+        let segment = Segment::from_cbor(&cbor)?;
+        match self.transfer_id {
+            None => {
+                self.transfer_id = Some(segment.transfer_id);
+                self.total_size = segment.total_size;
+                self.segments = vec![None; segment.count as usize];
+            }
+            Some(id)
+                if id != segment.transfer_id
+                    || self.total_size != segment.total_size
+                    || self.segments.len() != segment.count as usize =>
+            {
+                return Err(QuantusUrError::TransferError(
+                    "Session belongs to a different transfer".to_string(),
+                ));
+            }
+            Some(_) => {}
+        }
+        self.segments[segment.index as usize].get_or_insert_with(|| segment.data.to_vec());
+        Ok(())
+    }
+
+    /// Received and expected parts of the session being scanned.
+    pub fn session_progress(&self) -> (usize, usize) {
+        (
+            self.decoder.received_parts(),
+            self.decoder.expected_parts().unwrap_or(0),
+        )
+    }
+
+    /// Received and expected sessions. The count is 0 until the first
+    /// session completes.
+    pub fn progress(&self) -> (usize, usize) {
+        let received = self.segments.iter().filter(|s| s.is_some()).count();
+        (received, self.segments.len())
+    }
+
+    /// Indexes of the sessions not received yet.
+    pub fn missing_sessions(&self) -> Vec<u32> {
+        self.segments
+            .iter()
+            .enumerate()
+            .filter(|(_, segment)| segment.is_none())
+            .map(|(i, _)| i as u32)
+            .collect()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        !self.segments.is_empty() && self.segments.iter().all(Option::is_some)
+    }
+
+    /// Concatenates the segments and checks the result against the transfer id.
+    pub fn message(&self) -> Result<Vec<u8>, QuantusUrError> {
+        let transfer_id = self.transfer_id.ok_or(QuantusUrError::Incomplete)?;
+        let mut payload = Vec::new();
+        for segment in &self.segments {
+            payload.extend_from_slice(segment.as_ref().ok_or(QuantusUrError::Incomplete)?);
+        }
+        if payload.len() as u64 != self.total_size
+            || !constant_time_eq(&blake2_256(&payload), &transfer_id)
+        {
+            return Err(QuantusUrError::TransferError(
+                "Reassembled payload does not match transfer id".to_string(),
+            ));
+        }
+        Ok(payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn large_payload() -> Vec<u8> {
+        (0..2500u32).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_split_roundtrip() {
+        let payload = large_payload();
+        let sender = SplitSender::new(&payload, 1000).expect("Sender creation failed");
+        assert_eq!(sender.session_count(), 3);
+
+        let mut receiver = SplitReceiver::new();
+        for index in [2, 0, 1] {
+            let mut encoder = sender
+                .session_encoder(index, &EncodeOptions::default())
+                .expect("Encoding failed");
+            let first = encoder.next_part().expect("Encoding failed");
+            receiver.receive(&first).expect("Receive failed");
+            if index == 2 {
+                assert_eq!(receiver.session_progress().0, 1);
+            }
+            while receiver.session_progress().0 != 0 {
+                receiver
+                    .receive(&encoder.next_part().expect("Encoding failed"))
+                    .expect("Receive failed");
+            }
+            if index == 2 {
+                assert_eq!(receiver.progress(), (1, 3));
+                assert_eq!(receiver.missing_sessions(), vec![0, 1]);
+            }
+        }
+        assert!(receiver.is_complete());
+        assert_eq!(receiver.message().expect("Message failed"), payload);
+    }
+
+    #[test]
+    fn test_foreign_session_rejected() {
+        let payload = large_payload();
+        let sender = SplitSender::new(&payload, 1000).expect("Sender creation failed");
+        let other = SplitSender::new(&payload[1..], 1000).expect("Sender creation failed");
+        let mut receiver = SplitReceiver::new();
+        for part in sender.session_parts(0).expect("Encoding failed") {
+            receiver.receive(&part).expect("Receive failed");
+        }
+        let parts = other.session_parts(1).expect("Encoding failed");
+        let result = parts
+            .iter()
+            .try_for_each(|part| receiver.receive(part).map(drop));
+        assert!(matches!(result, Err(QuantusUrError::TransferError(_))));
+        assert_eq!(receiver.progress(), (1, 3));
+
+        let single = SplitSender::new(b"small", 1000).expect("Sender creation failed");
+        assert_eq!(single.session_count(), 1);
+        assert!(SplitSender::new(&[], 1000).is_err());
+    }
+}
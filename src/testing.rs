@@ -0,0 +1,336 @@
+//! Utilities for integration-testing scan loops against an unreliable camera,
+//! and a [`MockSigner`] standing in for the hardware on the other end.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::response::SignResponse;
+use crate::rng::SeededRandom;
+use crate::transfer::blake2_256;
+use crate::transport::PartSource;
+use crate::{QuantusUrError, UrDecoder, UrEncoder};
+
+/// Uniform value in `0.0..1.0`.
+fn next_f64(rng: &mut SeededRandom) -> f64 {
+    (rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Wraps an encoder and yields its parts as a lossy camera would see them:
+/// some dropped, some duplicated and, within a window, out of order.
+///
+/// The channel never ends; take as many parts as the scan loop needs.
+pub struct LossyChannel {
+    encoder: UrEncoder,
+    rng: SeededRandom,
+    drop_rate: f64,
+    duplicate_rate: f64,
+    reorder_window: usize,
+    buffer: Vec<String>,
+    dropped: usize,
+}
+
+impl LossyChannel {
+    /// A channel that delivers every part in order until configured otherwise.
+    pub fn new(encoder: UrEncoder, seed: u64) -> Self {
+        LossyChannel {
+            encoder,
+            rng: SeededRandom::new(seed),
+            drop_rate: 0.0,
+            duplicate_rate: 0.0,
+            reorder_window: 0,
+            buffer: Vec::new(),
+            dropped: 0,
+        }
+    }
+
+    /// Probability that a part is lost, clamped to `0.0..=0.99`.
+    pub fn drop_rate(mut self, rate: f64) -> Self {
+        self.drop_rate = rate.clamp(0.0, 0.99);
+        self
+    }
+
+    /// Probability that a delivered part is delivered twice.
+    pub fn duplicate_rate(mut self, rate: f64) -> Self {
+        self.duplicate_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Number of later parts a part may be overtaken by.
+    pub fn reorder_window(mut self, window: usize) -> Self {
+        self.reorder_window = window;
+        self
+    }
+
+    /// Number of parts dropped so far.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    fn fill(&mut self) -> Result<(), QuantusUrError> {
+        while self.buffer.len() <= self.reorder_window {
+            let part = self.encoder.next_part()?;
+            if next_f64(&mut self.rng) < self.drop_rate {
+                self.dropped += 1;
+                continue;
+            }
+            if next_f64(&mut self.rng) < self.duplicate_rate {
+                self.buffer.push(part.clone());
+            }
+            self.buffer.push(part);
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for LossyChannel {
+    type Item = Result<String, QuantusUrError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Err(e) = self.fill() {
+            return Some(Err(e));
+        }
+        let index = (self.rng.next_u64() % self.buffer.len() as u64) as usize;
+        Some(Ok(self.buffer.remove(index)))
+    }
+}
+
+impl PartSource for LossyChannel {
+    fn poll_part(&mut self) -> Result<Option<String>, QuantusUrError> {
+        self.next().transpose()
+    }
+}
+
+/// Signature length of [`MockSigner`] unless configured otherwise.
+pub const MOCK_SIGNATURE_LENGTH: usize = 64;
+
+/// Emulates a hardware signer: scans sign request parts and answers each
+/// completed request with sign response parts carrying a deterministic fake
+/// signature, so both directions of the flow can be tested without a device.
+///
+/// Signatures are not cryptographic; compare them against
+/// [`MockSigner::signature_for`].
+pub struct MockSigner {
+    decoder: UrDecoder,
+    public_key: Vec<u8>,
+    signature_length: usize,
+    signed: Vec<Vec<u8>>,
+}
+
+impl MockSigner {
+    /// A signer whose public key is derived from `seed`.
+    pub fn new(seed: u64) -> Self {
+        let mut rng = SeededRandom::new(seed);
+        let public_key = (0..4).flat_map(|_| rng.next_u64().to_le_bytes()).collect();
+        MockSigner {
+            decoder: UrDecoder::new(),
+            public_key,
+            signature_length: MOCK_SIGNATURE_LENGTH,
+            signed: Vec::new(),
+        }
+    }
+
+    /// Length of the fake signatures, e.g. 4627 to produce responses as large
+    /// as ML-DSA-87 ones.
+    pub fn signature_length(mut self, length: usize) -> Self {
+        self.signature_length = length;
+        self
+    }
+
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    /// Payloads signed so far, in order.
+    pub fn signed_requests(&self) -> &[Vec<u8>] {
+        &self.signed
+    }
+
+    /// The fake signature this signer produces for `payload`.
+    pub fn signature_for(&self, payload: &[u8]) -> Vec<u8> {
+        let mut input = self.public_key.clone();
+        input.extend_from_slice(payload);
+        let digest = blake2_256(&input);
+        let mut seed = [0u8; 8];
+        seed.copy_from_slice(&digest[..8]);
+        let mut rng = SeededRandom::new(u64::from_le_bytes(seed));
+        core::iter::repeat_with(|| rng.next_u64().to_le_bytes())
+            .flatten()
+            .take(self.signature_length)
+            .collect()
+    }
+
+    /// Feeds a scanned request part. Once the request is complete, returns
+    /// the response parts to display and starts scanning the next request.
+    pub fn receive(&mut self, part: &str) -> Result<Option<Vec<String>>, QuantusUrError> {
+        self.decoder.receive(part)?;
+        if !self.decoder.is_complete() {
+            return Ok(None);
+        }
+        let payload = self.decoder.message();
+        self.decoder.reset();
+        self.sign(&payload?).map(Some)
+    }
+
+    /// Signs a complete request given as all of its parts.
+    pub fn sign_parts(&mut self, ur_parts: &[String]) -> Result<Vec<String>, QuantusUrError> {
+        self.sign(&crate::decode_bytes(ur_parts)?)
+    }
+
+    fn sign(&mut self, payload: &[u8]) -> Result<Vec<String>, QuantusUrError> {
+        let response = SignResponse::for_request(payload, self.signature_for(payload))
+            .with_public_key(self.public_key.clone());
+        self.signed.push(payload.to_vec());
+        response.to_parts()
+    }
+}
+
+/// Heap allocations made on the current thread during a measured call.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AllocStats {
+    pub count: usize,
+    pub bytes: usize,
+}
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    static ALLOC_STATS: core::cell::Cell<AllocStats> = const {
+        core::cell::Cell::new(AllocStats { count: 0, bytes: 0 })
+    };
+}
+
+/// System allocator that counts allocations per thread, for enforcing
+/// allocation budgets in tests. Install it in the test binary:
+///
+/// ```ignore
+/// #[global_allocator]
+/// static ALLOCATOR: quantus_ur::testing::CountingAllocator =
+///     quantus_ur::testing::CountingAllocator;
+/// ```
+#[cfg(feature = "std")]
+pub struct CountingAllocator;
+
+#[cfg(feature = "std")]
+unsafe impl core::alloc::GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: core::alloc::Layout) -> *mut u8 {
+        record(layout.size());
+        std::alloc::System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: core::alloc::Layout) {
+        std::alloc::System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(
+        &self,
+        ptr: *mut u8,
+        layout: core::alloc::Layout,
+        new_size: usize,
+    ) -> *mut u8 {
+        record(new_size);
+        std::alloc::System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[cfg(feature = "std")]
+fn record(bytes: usize) {
+    // Ignore allocations made while the thread-local is being torn down
+    let _ = ALLOC_STATS.try_with(|stats| {
+        let mut current = stats.get();
+        current.count += 1;
+        current.bytes += bytes;
+        stats.set(current);
+    });
+}
+
+/// Runs `f` and returns the allocations it made on this thread. Counts are
+/// only recorded when [`CountingAllocator`] is the global allocator.
+#[cfg(feature = "std")]
+pub fn measure_allocations<R>(f: impl FnOnce() -> R) -> (R, AllocStats) {
+    let before = ALLOC_STATS.with(|stats| stats.get());
+    let result = f();
+    let after = ALLOC_STATS.with(|stats| stats.get());
+    (
+        result,
+        AllocStats {
+            count: after.count - before.count,
+            bytes: after.bytes - before.bytes,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::UrDecoder;
+
+    fn payload() -> Vec<u8> {
+        (0..2000u32).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn channel(seed: u64) -> LossyChannel {
+        let encoder = UrEncoder::new(&payload()).expect("Encoder creation failed");
+        LossyChannel::new(encoder, seed)
+            .drop_rate(0.3)
+            .duplicate_rate(0.2)
+            .reorder_window(3)
+    }
+
+    #[test]
+    fn test_decodes_through_lossy_channel() {
+        let mut decoder = UrDecoder::new();
+        for part in channel(7).take(1000) {
+            decoder
+                .receive(&part.expect("Part generation failed"))
+                .expect("Receive failed");
+            if decoder.is_complete() {
+                break;
+            }
+        }
+        assert_eq!(decoder.message().expect("Decoding failed"), payload());
+    }
+
+    #[test]
+    fn test_lossy_source() {
+        let mut decoder = UrDecoder::new();
+        let complete =
+            crate::transport::decode_from(channel(11), &mut decoder, 1000).expect("Receive failed");
+        assert!(complete);
+        assert_eq!(decoder.message().expect("Decoding failed"), payload());
+    }
+
+    #[test]
+    fn test_same_seed_same_stream() {
+        let first: Vec<String> = channel(42).take(30).map(Result::unwrap).collect();
+        let second: Vec<String> = channel(42).take(30).map(Result::unwrap).collect();
+        assert_eq!(first, second);
+        let other: Vec<String> = channel(43).take(30).map(Result::unwrap).collect();
+        assert_ne!(first, other);
+    }
+
+    #[test]
+    fn test_mock_signer_roundtrip() {
+        let mut signer = MockSigner::new(1).signature_length(4627);
+        let mut response_parts = None;
+        for part in channel(7).take(1000) {
+            response_parts = signer
+                .receive(&part.expect("Part generation failed"))
+                .expect("Receive failed");
+            if response_parts.is_some() {
+                break;
+            }
+        }
+        let response_parts = response_parts.expect("Request never completed");
+        assert!(response_parts.len() > 1);
+
+        let mut collector = crate::response::ResponseCollector::for_request(&payload());
+        let mut response = None;
+        for part in &response_parts {
+            response = collector.receive(part).expect("Receive failed");
+        }
+        let response = response.expect("Response incomplete");
+        assert_eq!(response.signature, signer.signature_for(&payload()));
+        assert_eq!(response.public_key.as_deref(), Some(signer.public_key()));
+        assert_eq!(signer.signed_requests(), [payload()]);
+    }
+}
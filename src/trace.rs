@@ -0,0 +1,18 @@
+//! Optional `tracing` instrumentation. Without the `tracing` feature these
+//! macros expand to nothing.
+
+/// Emits a `tracing` debug event.
+macro_rules! trace_event {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($arg)*);
+    };
+}
+
+/// Enters a `tracing` debug span until the end of the enclosing scope.
+macro_rules! trace_span {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!($($arg)*).entered();
+    };
+}
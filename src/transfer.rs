@@ -0,0 +1,535 @@
+//! Chunked transfer of payloads too large for a single UR message.
+//!
+//! The sender splits the payload into fixed-size chunks and announces them with
+//! a manifest message carrying the total size and the Merkle root over all
+//! chunks. Each chunk then travels as its own UR message together with its
+//! Merkle proof, so a blob far beyond comfortable fountain sizes can cross the
+//! air gap in stages and every chunk is verified as soon as it arrives.
+//!
+//! Transfers are resumable: when chunks are lost, the receiver answers with a
+//! missing-chunks message and the sender re-displays only those chunks.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use blake2::digest::consts::U32;
+use blake2::{Blake2b, Digest};
+use minicbor::{Decoder, Encoder};
+
+use crate::cbor::cbor_error;
+use crate::merkle::{leaf_hash, verify_proof, MerkleTree};
+use crate::{constant_time_eq, decode_message, encode_message, QuantusUrError, UrEncoder};
+
+pub const MANIFEST_UR_TYPE: &str = "quantus-transfer-manifest";
+pub const CHUNK_UR_TYPE: &str = "quantus-transfer-chunk";
+pub const MISSING_UR_TYPE: &str = "quantus-transfer-missing";
+pub const DEFAULT_CHUNK_SIZE: usize = 2048;
+pub const MAX_CHUNK_COUNT: usize = 1 << 16;
+
+pub(crate) const MANIFEST_VERSION: u8 = 2;
+
+pub type Hash = [u8; 32];
+
+pub(crate) fn blake2_256(data: &[u8]) -> Hash {
+    Blake2b::<U32>::digest(data).into()
+}
+
+fn chunk_count_for(total_size: u64, chunk_size: u32) -> u64 {
+    let chunk_size = chunk_size as u64;
+    total_size / chunk_size + (total_size % chunk_size != 0) as u64
+}
+
+fn decode_hash(d: &mut Decoder) -> Result<Hash, QuantusUrError> {
+    d.bytes()
+        .map_err(cbor_error)?
+        .try_into()
+        .map_err(|_| QuantusUrError::CborError("Expected a 32-byte hash".to_string()))
+}
+
+/// Describes a chunked transfer. The payload hash doubles as the transfer id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TransferManifest {
+    pub total_size: u64,
+    pub chunk_size: u32,
+    pub payload_hash: Hash,
+    pub merkle_root: Hash,
+}
+
+impl TransferManifest {
+    pub fn chunk_count(&self) -> usize {
+        chunk_count_for(self.total_size, self.chunk_size) as usize
+    }
+
+    fn to_cbor(&self) -> Result<Vec<u8>, QuantusUrError> {
+        let mut e = Encoder::new(Vec::new());
+        e.array(5)
+            .and_then(|e| e.u8(MANIFEST_VERSION))
+            .and_then(|e| e.u64(self.total_size))
+            .and_then(|e| e.u32(self.chunk_size))
+            .and_then(|e| e.bytes(&self.payload_hash))
+            .and_then(|e| e.bytes(&self.merkle_root))
+            .map_err(cbor_error)?;
+        Ok(e.into_writer())
+    }
+
+    fn from_cbor(cbor: &[u8]) -> Result<Self, QuantusUrError> {
+        let mut d = Decoder::new(cbor);
+        if d.array().map_err(cbor_error)? != Some(5) {
+            return Err(QuantusUrError::CborError(
+                "Malformed transfer manifest".to_string(),
+            ));
+        }
+        let version = d.u8().map_err(cbor_error)?;
+        if version != MANIFEST_VERSION {
+            return Err(QuantusUrError::TransferError(format!(
+                "Unsupported manifest version {}",
+                version
+            )));
+        }
+        let total_size = d.u64().map_err(cbor_error)?;
+        let chunk_size = d.u32().map_err(cbor_error)?;
+        let payload_hash = decode_hash(&mut d)?;
+        let merkle_root = decode_hash(&mut d)?;
+        if chunk_size == 0
+            || total_size == 0
+            || chunk_count_for(total_size, chunk_size) > MAX_CHUNK_COUNT as u64
+        {
+            return Err(QuantusUrError::TransferError(
+                "Manifest chunk layout out of range".to_string(),
+            ));
+        }
+        Ok(TransferManifest {
+            total_size,
+            chunk_size,
+            payload_hash,
+            merkle_root,
+        })
+    }
+}
+
+struct TransferChunk {
+    transfer_id: Hash,
+    index: u32,
+    data: Vec<u8>,
+    proof: Vec<Hash>,
+}
+
+impl TransferChunk {
+    fn to_cbor(&self) -> Result<Vec<u8>, QuantusUrError> {
+        let mut e = Encoder::new(Vec::new());
+        e.array(4)
+            .and_then(|e| e.bytes(&self.transfer_id))
+            .and_then(|e| e.u32(self.index))
+            .and_then(|e| e.bytes(&self.data))
+            .and_then(|e| e.array(self.proof.len() as u64))
+            .map_err(cbor_error)?;
+        for hash in &self.proof {
+            e.bytes(hash).map_err(cbor_error)?;
+        }
+        Ok(e.into_writer())
+    }
+
+    fn from_cbor(cbor: &[u8]) -> Result<Self, QuantusUrError> {
+        let mut d = Decoder::new(cbor);
+        if d.array().map_err(cbor_error)? != Some(4) {
+            return Err(QuantusUrError::CborError(
+                "Malformed transfer chunk".to_string(),
+            ));
+        }
+        let transfer_id = decode_hash(&mut d)?;
+        let index = d.u32().map_err(cbor_error)?;
+        let data = d.bytes().map_err(cbor_error)?.to_vec();
+        let count = d
+            .array()
+            .map_err(cbor_error)?
+            .ok_or_else(|| QuantusUrError::CborError("Indefinite proof array".to_string()))?;
+        let mut proof = Vec::new();
+        for _ in 0..count {
+            proof.push(decode_hash(&mut d)?);
+        }
+        Ok(TransferChunk {
+            transfer_id,
+            index,
+            data,
+            proof,
+        })
+    }
+}
+
+fn missing_to_cbor(transfer_id: &Hash, indexes: &[u32]) -> Result<Vec<u8>, QuantusUrError> {
+    let mut e = Encoder::new(Vec::new());
+    e.array(2)
+        .and_then(|e| e.bytes(transfer_id))
+        .and_then(|e| e.array(indexes.len() as u64))
+        .map_err(cbor_error)?;
+    for index in indexes {
+        e.u32(*index).map_err(cbor_error)?;
+    }
+    Ok(e.into_writer())
+}
+
+fn missing_from_cbor(cbor: &[u8]) -> Result<(Hash, Vec<u32>), QuantusUrError> {
+    let mut d = Decoder::new(cbor);
+    if d.array().map_err(cbor_error)? != Some(2) {
+        return Err(QuantusUrError::CborError(
+            "Malformed missing-chunks message".to_string(),
+        ));
+    }
+    let transfer_id = decode_hash(&mut d)?;
+    let count = d
+        .array()
+        .map_err(cbor_error)?
+        .ok_or_else(|| QuantusUrError::CborError("Indefinite index array".to_string()))?;
+    let mut indexes = Vec::new();
+    for _ in 0..count {
+        indexes.push(d.u32().map_err(cbor_error)?);
+    }
+    Ok((transfer_id, indexes))
+}
+
+/// Sending side of a chunked transfer.
+pub struct TransferSender {
+    manifest: TransferManifest,
+    payload: Vec<u8>,
+    tree: MerkleTree,
+}
+
+impl TransferSender {
+    pub fn new(payload: &[u8], chunk_size: usize) -> Result<Self, QuantusUrError> {
+        if payload.is_empty() {
+            return Err(QuantusUrError::TransferError(
+                "Cannot transfer an empty payload".to_string(),
+            ));
+        }
+        if chunk_size == 0
+            || chunk_size > u32::MAX as usize
+            || payload.chunks(chunk_size).len() > MAX_CHUNK_COUNT
+        {
+            return Err(QuantusUrError::TransferError(
+                "Chunk size out of range".to_string(),
+            ));
+        }
+        let tree = MerkleTree::new(payload.chunks(chunk_size).map(leaf_hash).collect());
+        let manifest = TransferManifest {
+            total_size: payload.len() as u64,
+            chunk_size: chunk_size as u32,
+            payload_hash: blake2_256(payload),
+            merkle_root: tree.root(),
+        };
+        Ok(TransferSender {
+            manifest,
+            payload: payload.to_vec(),
+            tree,
+        })
+    }
+
+    pub fn manifest(&self) -> &TransferManifest {
+        &self.manifest
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.manifest.chunk_count()
+    }
+
+    /// Minimal set of UR parts for the manifest message.
+    pub fn manifest_parts(&self) -> Result<Vec<String>, QuantusUrError> {
+        encode_message(self.manifest.to_cbor()?, MANIFEST_UR_TYPE)
+    }
+
+    /// Looping encoder for the manifest message.
+    pub fn manifest_encoder(&self) -> Result<UrEncoder, QuantusUrError> {
+        UrEncoder::from_cbor(self.manifest.to_cbor()?, MANIFEST_UR_TYPE)
+    }
+
+    /// Minimal set of UR parts for the chunk at `index`.
+    pub fn chunk_parts(&self, index: usize) -> Result<Vec<String>, QuantusUrError> {
+        encode_message(self.chunk_cbor(index)?, CHUNK_UR_TYPE)
+    }
+
+    /// Looping encoder for the chunk at `index`.
+    pub fn chunk_encoder(&self, index: usize) -> Result<UrEncoder, QuantusUrError> {
+        UrEncoder::from_cbor(self.chunk_cbor(index)?, CHUNK_UR_TYPE)
+    }
+
+    /// Parses a missing-chunks message from the receiver and returns the chunk
+    /// indexes to display again.
+    pub fn requested_chunks(&self, ur_parts: &[String]) -> Result<Vec<u32>, QuantusUrError> {
+        let (ur_type, cbor) = decode_message(ur_parts)?;
+        if ur_type != MISSING_UR_TYPE {
+            return Err(QuantusUrError::UrError(format!(
+                "Unexpected UR type {}",
+                ur_type
+            )));
+        }
+        let (transfer_id, indexes) = missing_from_cbor(&cbor)?;
+        if transfer_id != self.manifest.payload_hash {
+            return Err(QuantusUrError::TransferError(
+                "Request belongs to a different transfer".to_string(),
+            ));
+        }
+        if let Some(index) = indexes
+            .iter()
+            .find(|index| **index as usize >= self.chunk_count())
+        {
+            return Err(QuantusUrError::TransferError(format!(
+                "Chunk index {} out of range",
+                index
+            )));
+        }
+        Ok(indexes)
+    }
+
+    fn chunk_cbor(&self, index: usize) -> Result<Vec<u8>, QuantusUrError> {
+        let chunk_size = self.manifest.chunk_size as usize;
+        let data =
+            self.payload.chunks(chunk_size).nth(index).ok_or_else(|| {
+                QuantusUrError::TransferError(format!("No chunk at index {}", index))
+            })?;
+        TransferChunk {
+            transfer_id: self.manifest.payload_hash,
+            index: index as u32,
+            data: data.to_vec(),
+            proof: self.tree.proof(index),
+        }
+        .to_cbor()
+    }
+}
+
+/// Receiving side of a chunked transfer.
+///
+/// Feed it complete UR messages (the manifest first, then chunks in any order)
+/// and call [`TransferReceiver::finish`] once [`TransferReceiver::is_complete`]
+/// reports every chunk present.
+#[derive(Default)]
+pub struct TransferReceiver {
+    manifest: Option<TransferManifest>,
+    chunks: Vec<Option<Vec<u8>>>,
+}
+
+impl TransferReceiver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn manifest(&self) -> Option<&TransferManifest> {
+        self.manifest.as_ref()
+    }
+
+    /// Processes the parts of one complete UR message, either the manifest or a chunk.
+    pub fn receive(&mut self, ur_parts: &[String]) -> Result<(), QuantusUrError> {
+        let (ur_type, cbor) = decode_message(ur_parts)?;
+        match ur_type.as_str() {
+            MANIFEST_UR_TYPE => self.receive_manifest(TransferManifest::from_cbor(&cbor)?),
+            CHUNK_UR_TYPE => self.receive_chunk(TransferChunk::from_cbor(&cbor)?),
+            other => Err(QuantusUrError::UrError(format!(
+                "Unexpected UR type {}",
+                other
+            ))),
+        }
+    }
+
+    fn receive_manifest(&mut self, manifest: TransferManifest) -> Result<(), QuantusUrError> {
+        if let Some(current) = &self.manifest {
+            if *current != manifest {
+                return Err(QuantusUrError::TransferError(
+                    "Manifest belongs to a different transfer".to_string(),
+                ));
+            }
+            return Ok(());
+        }
+        self.chunks = vec![None; manifest.chunk_count()];
+        self.manifest = Some(manifest);
+        Ok(())
+    }
+
+    fn receive_chunk(&mut self, chunk: TransferChunk) -> Result<(), QuantusUrError> {
+        let manifest = self.manifest.as_ref().ok_or_else(|| {
+            QuantusUrError::TransferError("Manifest must be received before chunks".to_string())
+        })?;
+        if chunk.transfer_id != manifest.payload_hash {
+            return Err(QuantusUrError::TransferError(
+                "Chunk belongs to a different transfer".to_string(),
+            ));
+        }
+        let index = chunk.index as usize;
+        if index >= self.chunks.len() {
+            return Err(QuantusUrError::TransferError(format!(
+                "Chunk index {} out of range",
+                chunk.index
+            )));
+        }
+        let chunk_size = manifest.chunk_size as u64;
+        let expected_len = chunk_size.min(manifest.total_size - index as u64 * chunk_size);
+        if chunk.data.len() as u64 != expected_len
+            || !verify_proof(
+                leaf_hash(&chunk.data),
+                index,
+                self.chunks.len(),
+                &chunk.proof,
+                &manifest.merkle_root,
+            )
+        {
+            return Err(QuantusUrError::ChunkVerificationFailed(chunk.index));
+        }
+        self.chunks[index] = Some(chunk.data);
+        Ok(())
+    }
+
+    /// Indexes of the chunks not received yet. Empty until the manifest arrives.
+    pub fn missing_chunks(&self) -> Vec<u32> {
+        self.chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, chunk)| chunk.is_none())
+            .map(|(i, _)| i as u32)
+            .collect()
+    }
+
+    /// Encodes the missing-chunks message to show back to the sender, so it only
+    /// re-displays the chunks this receiver still needs.
+    pub fn missing_chunks_parts(&self) -> Result<Vec<String>, QuantusUrError> {
+        let manifest = self
+            .manifest
+            .as_ref()
+            .ok_or_else(|| QuantusUrError::TransferError("No manifest received yet".to_string()))?;
+        encode_message(
+            missing_to_cbor(&manifest.payload_hash, &self.missing_chunks())?,
+            MISSING_UR_TYPE,
+        )
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.manifest.is_some() && self.chunks.iter().all(Option::is_some)
+    }
+
+    /// Concatenates the chunks and checks the result against the manifest.
+    pub fn finish(&self) -> Result<Vec<u8>, QuantusUrError> {
+        let manifest = self.manifest.as_ref().ok_or(QuantusUrError::Incomplete)?;
+        let mut payload = Vec::new();
+        for chunk in &self.chunks {
+            payload.extend_from_slice(chunk.as_ref().ok_or(QuantusUrError::Incomplete)?);
+        }
+        if payload.len() as u64 != manifest.total_size
+            || !constant_time_eq(&blake2_256(&payload), &manifest.payload_hash)
+        {
+            return Err(QuantusUrError::TransferError(
+                "Reassembled payload does not match manifest".to_string(),
+            ));
+        }
+        Ok(payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn large_payload() -> Vec<u8> {
+        (0..5000u32).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_transfer_roundtrip() {
+        let payload = large_payload();
+        let sender = TransferSender::new(&payload, 1024).expect("Sender creation failed");
+        assert_eq!(sender.chunk_count(), 5);
+
+        let mut receiver = TransferReceiver::new();
+        receiver
+            .receive(&sender.manifest_parts().expect("Manifest encoding failed"))
+            .expect("Manifest rejected");
+        assert_eq!(receiver.missing_chunks(), vec![0, 1, 2, 3, 4]);
+
+        for index in (0..sender.chunk_count()).rev() {
+            let parts = sender.chunk_parts(index).expect("Chunk encoding failed");
+            receiver.receive(&parts).expect("Chunk rejected");
+        }
+        assert!(receiver.is_complete());
+        assert_eq!(receiver.finish().expect("Finish failed"), payload);
+    }
+
+    #[test]
+    fn test_resume_with_missing_chunks() {
+        let payload = large_payload();
+        let sender = TransferSender::new(&payload, 1024).expect("Sender creation failed");
+        let mut receiver = TransferReceiver::new();
+        receiver
+            .receive(&sender.manifest_parts().expect("Manifest encoding failed"))
+            .expect("Manifest rejected");
+
+        // Chunks 1 and 3 are dropped on the first pass
+        for index in [0, 2, 4] {
+            let parts = sender.chunk_parts(index).expect("Chunk encoding failed");
+            receiver.receive(&parts).expect("Chunk rejected");
+        }
+
+        let request = receiver
+            .missing_chunks_parts()
+            .expect("Request encoding failed");
+        let requested = sender.requested_chunks(&request).expect("Request rejected");
+        assert_eq!(requested, vec![1, 3]);
+
+        for index in requested {
+            let parts = sender
+                .chunk_parts(index as usize)
+                .expect("Chunk encoding failed");
+            receiver.receive(&parts).expect("Chunk rejected");
+        }
+        assert_eq!(receiver.finish().expect("Finish failed"), payload);
+    }
+
+    #[test]
+    fn test_corrupted_chunk_identified() {
+        let payload = large_payload();
+        let sender = TransferSender::new(&payload, 1024).expect("Sender creation failed");
+        let mut receiver = TransferReceiver::new();
+        receiver
+            .receive(&sender.manifest_parts().expect("Manifest encoding failed"))
+            .expect("Manifest rejected");
+
+        let mut data = payload[2048..3072].to_vec();
+        data[17] ^= 0xff;
+        let corrupted = TransferChunk {
+            transfer_id: sender.manifest().payload_hash,
+            index: 2,
+            data,
+            proof: sender.tree.proof(2),
+        };
+        let parts = encode_message(
+            corrupted.to_cbor().expect("Chunk encoding failed"),
+            CHUNK_UR_TYPE,
+        )
+        .expect("Chunk encoding failed");
+        assert!(matches!(
+            receiver.receive(&parts),
+            Err(QuantusUrError::ChunkVerificationFailed(2))
+        ));
+        assert!(receiver.missing_chunks().contains(&2));
+    }
+
+    #[test]
+    fn test_chunk_before_manifest_rejected() {
+        let sender = TransferSender::new(&large_payload(), 1024).expect("Sender creation failed");
+        let mut receiver = TransferReceiver::new();
+        let parts = sender.chunk_parts(0).expect("Chunk encoding failed");
+        assert!(matches!(
+            receiver.receive(&parts),
+            Err(QuantusUrError::TransferError(_))
+        ));
+    }
+
+    #[test]
+    fn test_chunk_from_other_transfer_rejected() {
+        let sender = TransferSender::new(&large_payload(), 1024).expect("Sender creation failed");
+        let other = TransferSender::new(b"another payload", 1024).expect("Sender creation failed");
+        let mut receiver = TransferReceiver::new();
+        receiver
+            .receive(&sender.manifest_parts().expect("Manifest encoding failed"))
+            .expect("Manifest rejected");
+        let parts = other.chunk_parts(0).expect("Chunk encoding failed");
+        assert!(receiver.receive(&parts).is_err());
+        assert!(!receiver.is_complete());
+    }
+}
@@ -0,0 +1,163 @@
+//! Part transports, decoupling the protocol from the screen and camera.
+//!
+//! A [`PartSink`] takes parts to show, a [`PartSource`] yields scanned parts.
+//! [`loopback`] connects the two in memory, so a whole exchange, optionally
+//! through [`crate::testing::LossyChannel`], runs inside one process.
+
+use alloc::collections::VecDeque;
+use alloc::rc::Rc;
+use alloc::string::String;
+use core::cell::RefCell;
+
+use crate::{QuantusUrError, UrDecoder, UrEncoder};
+
+/// Receives parts for display or transmission.
+pub trait PartSink {
+    fn send_part(&mut self, part: &str) -> Result<(), QuantusUrError>;
+}
+
+/// Yields scanned parts; `None` when no part is available right now.
+pub trait PartSource {
+    fn poll_part(&mut self) -> Result<Option<String>, QuantusUrError>;
+}
+
+/// An encoder is an endless source of parts.
+impl PartSource for UrEncoder {
+    fn poll_part(&mut self) -> Result<Option<String>, QuantusUrError> {
+        self.next_part().map(Some)
+    }
+}
+
+/// A decoder consumes parts as if scanning them.
+impl PartSink for UrDecoder {
+    fn send_part(&mut self, part: &str) -> Result<(), QuantusUrError> {
+        self.receive(part)
+    }
+}
+
+impl<S: PartSink + ?Sized> PartSink for &mut S {
+    fn send_part(&mut self, part: &str) -> Result<(), QuantusUrError> {
+        (**self).send_part(part)
+    }
+}
+
+impl<S: PartSource + ?Sized> PartSource for &mut S {
+    fn poll_part(&mut self) -> Result<Option<String>, QuantusUrError> {
+        (**self).poll_part()
+    }
+}
+
+/// Sending end of a [`loopback`] pair.
+pub struct LoopbackSink {
+    queue: Rc<RefCell<VecDeque<String>>>,
+}
+
+/// Receiving end of a [`loopback`] pair.
+pub struct LoopbackSource {
+    queue: Rc<RefCell<VecDeque<String>>>,
+}
+
+/// An in-memory channel delivering parts in order, without loss.
+pub fn loopback() -> (LoopbackSink, LoopbackSource) {
+    let queue = Rc::new(RefCell::new(VecDeque::new()));
+    (
+        LoopbackSink {
+            queue: queue.clone(),
+        },
+        LoopbackSource { queue },
+    )
+}
+
+impl PartSink for LoopbackSink {
+    fn send_part(&mut self, part: &str) -> Result<(), QuantusUrError> {
+        self.queue.borrow_mut().push_back(part.into());
+        Ok(())
+    }
+}
+
+impl PartSource for LoopbackSource {
+    fn poll_part(&mut self) -> Result<Option<String>, QuantusUrError> {
+        Ok(self.queue.borrow_mut().pop_front())
+    }
+}
+
+impl LoopbackSource {
+    /// Number of parts sent but not polled yet.
+    pub fn pending(&self) -> usize {
+        self.queue.borrow().len()
+    }
+}
+
+/// Moves up to `max_parts` parts from `source` to `sink`, stopping early when
+/// the source has none. Returns the number of parts moved.
+pub fn pump(
+    mut source: impl PartSource,
+    mut sink: impl PartSink,
+    max_parts: usize,
+) -> Result<usize, QuantusUrError> {
+    for moved in 0..max_parts {
+        match source.poll_part()? {
+            Some(part) => sink.send_part(&part)?,
+            None => return Ok(moved),
+        }
+    }
+    Ok(max_parts)
+}
+
+/// Feeds `decoder` from `source` until the message is complete, the source
+/// runs dry or `max_parts` were scanned. Returns whether it completed.
+pub fn decode_from(
+    mut source: impl PartSource,
+    decoder: &mut UrDecoder,
+    max_parts: usize,
+) -> Result<bool, QuantusUrError> {
+    for _ in 0..max_parts {
+        if decoder.is_complete() {
+            break;
+        }
+        match source.poll_part()? {
+            Some(part) => decoder.receive(&part)?,
+            None => break,
+        }
+    }
+    Ok(decoder.is_complete())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    fn payload() -> Vec<u8> {
+        (0..1500u32).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_encoder_to_decoder() {
+        let mut encoder = UrEncoder::new(&payload()).expect("Encoding failed");
+        let count = encoder.fragment_count();
+        let mut decoder = UrDecoder::new();
+        assert_eq!(
+            pump(&mut encoder, &mut decoder, count).expect("Pump failed"),
+            count
+        );
+        assert_eq!(decoder.message().expect("Decoding failed"), payload());
+    }
+
+    #[test]
+    fn test_loopback_pair() {
+        let (mut sink, mut source) = loopback();
+        let encoder = UrEncoder::new(&payload()).expect("Encoding failed");
+        let count = encoder.fragment_count();
+        for part in encoder.into_minimal_parts() {
+            sink.send_part(&part.expect("Encoding failed"))
+                .expect("Send failed");
+        }
+        assert_eq!(source.pending(), count);
+
+        let mut decoder = UrDecoder::new();
+        assert!(decode_from(&mut source, &mut decoder, usize::MAX).expect("Decoding failed"));
+        assert_eq!(source.pending(), 0);
+        assert_eq!(decoder.message().expect("Decoding failed"), payload());
+    }
+}
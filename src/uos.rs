@@ -0,0 +1,334 @@
+//! Polkadot Vault (formerly Parity Signer) UOS payloads, as an alternative
+//! transport to existing substrate air-gap signers.
+//!
+//! A sign request is `0x53`, a crypto byte, a command byte, the signer's
+//! public key, the payload and the genesis hash. It is split into binary
+//! frames of `0x00`, the frame count and the frame index (both big-endian
+//! `u16`) followed by up to [`FRAME_SIZE`] bytes, each shown as a byte-mode QR
+//! code. The signer answers with a single QR code holding the crypto byte
+//! followed by the signature.
+
+use alloc::format;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use crate::response::SignResponse;
+use crate::transfer::Hash;
+use crate::QuantusUrError;
+
+/// Payload bytes per frame, as used by polkadot-js.
+pub const FRAME_SIZE: usize = 1024;
+
+const SUBSTRATE_ID: u8 = 0x53;
+const MULTIPART: u8 = 0x00;
+const FRAME_HEADER: usize = 5;
+
+fn uos_error(msg: &str) -> QuantusUrError {
+    QuantusUrError::UrError(format!("UOS: {}", msg))
+}
+
+/// Key type of the signing account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UosCrypto {
+    Ed25519,
+    Sr25519,
+    Ecdsa,
+}
+
+impl UosCrypto {
+    fn from_byte(byte: u8) -> Result<Self, QuantusUrError> {
+        match byte {
+            0x00 => Ok(UosCrypto::Ed25519),
+            0x01 => Ok(UosCrypto::Sr25519),
+            0x02 => Ok(UosCrypto::Ecdsa),
+            _ => Err(uos_error("unknown crypto type")),
+        }
+    }
+
+    fn byte(self) -> u8 {
+        match self {
+            UosCrypto::Ed25519 => 0x00,
+            UosCrypto::Sr25519 => 0x01,
+            UosCrypto::Ecdsa => 0x02,
+        }
+    }
+
+    fn public_key_len(self) -> usize {
+        match self {
+            UosCrypto::Ecdsa => 33,
+            _ => 32,
+        }
+    }
+
+    fn signature_len(self) -> usize {
+        match self {
+            UosCrypto::Ecdsa => 65,
+            _ => 64,
+        }
+    }
+}
+
+/// What the signer is asked to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UosCommand {
+    SignTransaction,
+    SignTransactionHash,
+    SignImmortalTransaction,
+    SignMessage,
+}
+
+impl UosCommand {
+    fn from_byte(byte: u8) -> Result<Self, QuantusUrError> {
+        match byte {
+            0x00 => Ok(UosCommand::SignTransaction),
+            0x01 => Ok(UosCommand::SignTransactionHash),
+            0x02 => Ok(UosCommand::SignImmortalTransaction),
+            0x03 => Ok(UosCommand::SignMessage),
+            _ => Err(uos_error("unknown command")),
+        }
+    }
+
+    fn byte(self) -> u8 {
+        match self {
+            UosCommand::SignTransaction => 0x00,
+            UosCommand::SignTransactionHash => 0x01,
+            UosCommand::SignImmortalTransaction => 0x02,
+            UosCommand::SignMessage => 0x03,
+        }
+    }
+}
+
+/// A substrate sign request in UOS form.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UosRequest {
+    pub crypto: UosCrypto,
+    pub command: UosCommand,
+    pub public_key: Vec<u8>,
+    pub payload: Vec<u8>,
+    pub genesis_hash: Hash,
+}
+
+impl UosRequest {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, QuantusUrError> {
+        if self.public_key.len() != self.crypto.public_key_len() {
+            return Err(uos_error("public key length does not match crypto type"));
+        }
+        let mut bytes = Vec::with_capacity(3 + self.public_key.len() + self.payload.len() + 32);
+        bytes.extend_from_slice(&[SUBSTRATE_ID, self.crypto.byte(), self.command.byte()]);
+        bytes.extend_from_slice(&self.public_key);
+        bytes.extend_from_slice(&self.payload);
+        bytes.extend_from_slice(&self.genesis_hash);
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, QuantusUrError> {
+        if bytes.len() < 3 {
+            return Err(uos_error("payload too short"));
+        }
+        let (header, rest) = bytes.split_at(3);
+        if header[0] != SUBSTRATE_ID {
+            return Err(uos_error("not a substrate payload"));
+        }
+        let crypto = UosCrypto::from_byte(header[1])?;
+        let command = UosCommand::from_byte(header[2])?;
+        let key_len = crypto.public_key_len();
+        if rest.len() < key_len + 32 {
+            return Err(uos_error("payload too short"));
+        }
+        let (public_key, rest) = rest.split_at(key_len);
+        let (payload, genesis_hash) = rest.split_at(rest.len() - 32);
+        Ok(UosRequest {
+            crypto,
+            command,
+            public_key: public_key.to_vec(),
+            payload: payload.to_vec(),
+            genesis_hash: genesis_hash
+                .try_into()
+                .map_err(|_| uos_error("payload too short"))?,
+        })
+    }
+
+    /// Binary frames to show as byte-mode QR codes.
+    pub fn to_frames(&self) -> Result<Vec<Vec<u8>>, QuantusUrError> {
+        encode_frames(&self.to_bytes()?)
+    }
+}
+
+/// Splits `data` into multipart frames of at most [`FRAME_SIZE`] data bytes.
+pub fn encode_frames(data: &[u8]) -> Result<Vec<Vec<u8>>, QuantusUrError> {
+    let count = u16::try_from((data.len() + FRAME_SIZE - 1) / FRAME_SIZE)
+        .map_err(|_| uos_error("payload needs more than 65535 frames"))?;
+    Ok(data
+        .chunks(FRAME_SIZE)
+        .zip(0u16..)
+        .map(|(chunk, index)| {
+            let mut frame = Vec::with_capacity(FRAME_HEADER + chunk.len());
+            frame.push(MULTIPART);
+            frame.extend_from_slice(&count.to_be_bytes());
+            frame.extend_from_slice(&index.to_be_bytes());
+            frame.extend_from_slice(chunk);
+            frame
+        })
+        .collect())
+}
+
+/// Collects scanned multipart frames in any order.
+#[derive(Debug, Default)]
+pub struct UosDecoder {
+    frames: Vec<Option<Vec<u8>>>,
+}
+
+impl UosDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds one scanned frame. Returns whether the payload is complete.
+    pub fn receive(&mut self, frame: &[u8]) -> Result<bool, QuantusUrError> {
+        if frame.len() < FRAME_HEADER {
+            return Err(uos_error("frame too short"));
+        }
+        let (header, data) = frame.split_at(FRAME_HEADER);
+        if header[0] != MULTIPART {
+            return Err(uos_error("not a multipart frame"));
+        }
+        let count = u16::from_be_bytes([header[1], header[2]]) as usize;
+        let index = u16::from_be_bytes([header[3], header[4]]) as usize;
+        if count == 0 || index >= count {
+            return Err(uos_error("frame index out of range"));
+        }
+        if self.frames.is_empty() {
+            self.frames = alloc::vec![None; count];
+        } else if self.frames.len() != count {
+            return Err(QuantusUrError::InconsistentSequence {
+                expected: self.frames.len() as u32,
+                actual: count as u32,
+            });
+        }
+        self.frames[index].get_or_insert_with(|| data.to_vec());
+        Ok(self.is_complete())
+    }
+
+    pub fn is_complete(&self) -> bool {
+        !self.frames.is_empty() && self.frames.iter().all(Option::is_some)
+    }
+
+    /// Received and expected frame counts.
+    pub fn progress(&self) -> (usize, usize) {
+        let received = self.frames.iter().filter(|f| f.is_some()).count();
+        (received, self.frames.len())
+    }
+
+    /// The reassembled payload, once complete.
+    pub fn message(&self) -> Result<Vec<u8>, QuantusUrError> {
+        if !self.is_complete() {
+            return Err(QuantusUrError::Incomplete);
+        }
+        Ok(self.frames.iter().flatten().flatten().copied().collect())
+    }
+}
+
+/// Signature QR code returned by the signer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UosSignature {
+    pub crypto: UosCrypto,
+    pub signature: Vec<u8>,
+}
+
+impl UosSignature {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, QuantusUrError> {
+        let (&crypto, signature) = bytes
+            .split_first()
+            .ok_or_else(|| uos_error("empty signature"))?;
+        let crypto = UosCrypto::from_byte(crypto)?;
+        if signature.len() != crypto.signature_len() {
+            return Err(QuantusUrError::UrError(format!(
+                "UOS: expected a {} byte signature, got {}",
+                crypto.signature_len(),
+                signature.len()
+            )));
+        }
+        Ok(UosSignature {
+            crypto,
+            signature: signature.to_vec(),
+        })
+    }
+
+    /// Signatures are usually shown as `0x`-prefixed hex text.
+    pub fn from_hex(text: &str) -> Result<Self, QuantusUrError> {
+        let text = text.trim();
+        let digits = text.strip_prefix("0x").unwrap_or(text);
+        if digits.len() % 2 != 0 || !digits.is_ascii() {
+            return Err(uos_error("invalid signature hex"));
+        }
+        let bytes = (0..digits.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&digits[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|e| uos_error(&e.to_string()))?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// The signature as a response to `request`, for code built around
+    /// [`SignResponse`].
+    pub fn into_response(self, request: &UosRequest) -> SignResponse {
+        SignResponse::for_request(&request.payload, self.signature)
+            .with_public_key(request.public_key.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(payload_len: usize) -> UosRequest {
+        UosRequest {
+            crypto: UosCrypto::Sr25519,
+            command: UosCommand::SignTransaction,
+            public_key: alloc::vec![0xd4; 32],
+            payload: (0..payload_len).map(|i| (i % 251) as u8).collect(),
+            genesis_hash: [0x91; 32],
+        }
+    }
+
+    #[test]
+    fn test_frames_roundtrip_out_of_order() {
+        let request = request(2500);
+        let frames = request.to_frames().expect("Encoding failed");
+        assert_eq!(frames.len(), 3);
+        assert_eq!(&frames[0][..5], &[0x00, 0x00, 0x03, 0x00, 0x00]);
+        assert_eq!(&frames[0][5..8], &[0x53, 0x01, 0x00]);
+
+        let mut decoder = UosDecoder::new();
+        assert!(!decoder.receive(&frames[2]).expect("Receive failed"));
+        assert!(!decoder.receive(&frames[0]).expect("Receive failed"));
+        assert!(!decoder.receive(&frames[0]).expect("Receive failed"));
+        assert!(decoder.receive(&frames[1]).expect("Receive failed"));
+        let bytes = decoder.message().expect("Decoding failed");
+        assert_eq!(
+            UosRequest::from_bytes(&bytes).expect("Parsing failed"),
+            request
+        );
+
+        assert!(UosDecoder::new()
+            .receive(&[0x00, 0x00, 0x01, 0x00, 0x01])
+            .is_err());
+    }
+
+    #[test]
+    fn test_signature() {
+        let mut text = "0x01".to_string();
+        text.extend(core::iter::repeat("ab").take(64));
+        let signature = UosSignature::from_hex(&text).expect("Parsing failed");
+        assert_eq!(signature.crypto, UosCrypto::Sr25519);
+
+        let request = request(10);
+        let response = signature.into_response(&request);
+        assert!(response.matches_request(&request.payload));
+        assert!(UosSignature::from_hex("0x01abab").is_err());
+    }
+}
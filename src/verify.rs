@@ -0,0 +1,88 @@
+//! ML-DSA-87 (Dilithium) verification of sign responses, so wallets can
+//! check a response right after scanning it.
+
+use alloc::string::ToString;
+
+use ml_dsa::{EncodedSignature, EncodedVerifyingKey, MlDsa87, Signature, VerifyingKey};
+
+use crate::response::SignResponse;
+use crate::QuantusUrError;
+
+fn failed(msg: &str) -> QuantusUrError {
+    QuantusUrError::SignatureVerificationFailed(msg.to_string())
+}
+
+/// Checks an ML-DSA-87 signature over `message` with an encoded public key.
+pub fn verify_ml_dsa(
+    public_key: &[u8],
+    message: &[u8],
+    signature: &[u8],
+) -> Result<(), QuantusUrError> {
+    let key = EncodedVerifyingKey::<MlDsa87>::try_from(public_key)
+        .map_err(|_| failed("Public key has the wrong length for ML-DSA-87"))?;
+    let key = VerifyingKey::<MlDsa87>::decode(&key);
+    let signature = EncodedSignature::<MlDsa87>::try_from(signature)
+        .map_err(|_| failed("Signature has the wrong length for ML-DSA-87"))?;
+    let signature =
+        Signature::<MlDsa87>::decode(&signature).ok_or_else(|| failed("Malformed signature"))?;
+    if key.verify_with_context(message, &[], &signature) {
+        Ok(())
+    } else {
+        Err(failed("Signature does not verify"))
+    }
+}
+
+impl SignResponse {
+    /// Checks that the response answers the request with the given payload
+    /// and that its signature verifies against the included public key.
+    pub fn verify(&self, request_payload: &[u8]) -> Result<(), QuantusUrError> {
+        if !self.matches_request(request_payload) {
+            return Err(QuantusUrError::ResponseMismatch);
+        }
+        let public_key = self
+            .public_key
+            .as_deref()
+            .ok_or_else(|| failed("Response carries no public key"))?;
+        verify_ml_dsa(public_key, request_payload, &self.signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ml_dsa::{KeyGen, B32};
+
+    const REQUEST: &[u8] = b"transfer 10 QUAN";
+
+    fn signed_response(message: &[u8]) -> SignResponse {
+        let keypair = MlDsa87::key_gen_internal(&B32::from([7u8; 32]));
+        let signature = keypair
+            .signing_key()
+            .sign_deterministic(message, &[])
+            .expect("Signing failed");
+        SignResponse::for_request(REQUEST, signature.encode().to_vec())
+            .with_public_key(keypair.verifying_key().encode().to_vec())
+    }
+
+    #[test]
+    fn test_valid_signature() {
+        signed_response(REQUEST)
+            .verify(REQUEST)
+            .expect("Verification failed");
+    }
+
+    #[test]
+    fn test_invalid_signatures_rejected() {
+        assert!(matches!(
+            signed_response(b"something else").verify(REQUEST),
+            Err(QuantusUrError::SignatureVerificationFailed(_))
+        ));
+        assert!(matches!(
+            signed_response(REQUEST).verify(b"another request"),
+            Err(QuantusUrError::ResponseMismatch)
+        ));
+        let mut response = signed_response(REQUEST);
+        response.public_key = None;
+        assert!(response.verify(REQUEST).is_err());
+    }
+}
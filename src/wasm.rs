@@ -0,0 +1,264 @@
+//! `wasm-bindgen` exports for web wallets.
+//!
+//! With the `wasm-camera` feature, [`CameraScanner`] also hooks a camera
+//! `MediaStream` to a `<video>` element, detects QR codes with the browser's
+//! `BarcodeDetector` where available and drives a streaming decoder, so a web
+//! wallet only needs a render loop calling [`CameraScanner::scan_frame`].
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use wasm_bindgen::prelude::*;
+
+use crate::{DecodeOptions, QuantusUrError, UrDecoder};
+
+fn js_error(e: QuantusUrError) -> JsError {
+    JsError::new(&e.to_string())
+}
+
+#[wasm_bindgen(js_name = encodeBytes)]
+pub fn encode_bytes(payload: &[u8]) -> Result<Vec<String>, JsError> {
+    crate::encode_bytes(payload).map_err(js_error)
+}
+
+#[wasm_bindgen(js_name = decodeBytes)]
+pub fn decode_bytes(parts: Vec<String>) -> Result<Vec<u8>, JsError> {
+    crate::decode_bytes(&parts).map_err(js_error)
+}
+
+/// Scan progress after a part or frame.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScanProgress {
+    pub received: u32,
+    /// Zero until the first part has been received.
+    pub expected: u32,
+    pub complete: bool,
+}
+
+impl ScanProgress {
+    fn of(decoder: &UrDecoder) -> Self {
+        ScanProgress {
+            received: decoder.received_parts() as u32,
+            expected: decoder.expected_parts().unwrap_or(0) as u32,
+            complete: decoder.is_complete(),
+        }
+    }
+}
+
+/// Streaming decoder for parts scanned by JavaScript code.
+#[wasm_bindgen]
+pub struct WasmUrDecoder {
+    inner: UrDecoder,
+}
+
+impl Default for WasmUrDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen]
+impl WasmUrDecoder {
+    /// A lenient decoder: QR codes that are not UR parts are skipped.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        WasmUrDecoder {
+            inner: lenient_decoder(),
+        }
+    }
+
+    pub fn receive(&mut self, part: &str) -> Result<ScanProgress, JsError> {
+        self.inner.receive(part).map_err(js_error)?;
+        Ok(ScanProgress::of(&self.inner))
+    }
+
+    pub fn message(&self) -> Result<Vec<u8>, JsError> {
+        self.inner.message().map_err(js_error)
+    }
+
+    pub fn reset(&mut self) {
+        self.inner.reset();
+    }
+}
+
+fn lenient_decoder() -> UrDecoder {
+    UrDecoder::with_options(DecodeOptions {
+        skip_invalid_parts: true,
+        ..DecodeOptions::default()
+    })
+}
+
+#[cfg(feature = "wasm-camera")]
+pub use camera::CameraScanner;
+
+#[cfg(feature = "wasm-camera")]
+mod camera {
+    use alloc::rc::Rc;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use core::cell::RefCell;
+    use js_sys::{Array, Function, Object, Promise, Reflect};
+    use wasm_bindgen::prelude::*;
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen_futures::{future_to_promise, JsFuture};
+    use web_sys::{HtmlVideoElement, MediaStream, MediaStreamConstraints, MediaStreamTrack};
+
+    use super::{js_error, lenient_decoder, ScanProgress};
+    use crate::UrDecoder;
+
+    fn object(entries: &[(&str, JsValue)]) -> Result<Object, JsValue> {
+        let object = Object::new();
+        for (key, value) in entries {
+            Reflect::set(&object, &JsValue::from_str(key), value)?;
+        }
+        Ok(object)
+    }
+
+    /// `new BarcodeDetector({ formats: ["qr_code"] })`, or `None` when the
+    /// browser has no `BarcodeDetector`.
+    fn qr_detector() -> Result<Option<JsValue>, JsValue> {
+        let constructor = Reflect::get(&js_sys::global(), &JsValue::from_str("BarcodeDetector"))?;
+        if constructor.is_undefined() {
+            return Ok(None);
+        }
+        let formats = Array::of1(&JsValue::from_str("qr_code"));
+        let options = object(&[("formats", formats.into())])?;
+        Reflect::construct(
+            constructor.unchecked_ref::<Function>(),
+            &Array::of1(&options),
+        )
+        .map(Some)
+    }
+
+    /// Camera-driven scan session.
+    ///
+    /// Call [`CameraScanner::scan_frame`] from a `requestAnimationFrame`
+    /// loop until it reports completion. Without `BarcodeDetector`, run a
+    /// JavaScript QR library on the video instead and pass its results to
+    /// [`CameraScanner::receive_text`].
+    #[wasm_bindgen]
+    pub struct CameraScanner {
+        video: HtmlVideoElement,
+        stream: MediaStream,
+        detector: Option<JsValue>,
+        decoder: Rc<RefCell<UrDecoder>>,
+    }
+
+    #[wasm_bindgen]
+    impl CameraScanner {
+        /// Opens the rear camera, plays it in `video` and prepares the QR
+        /// detector.
+        pub async fn start(video: HtmlVideoElement) -> Result<CameraScanner, JsValue> {
+            let window = web_sys::window().ok_or_else(|| JsValue::from_str("No window"))?;
+            let camera = object(&[("facingMode", JsValue::from_str("environment"))])?;
+            let constraints = object(&[("audio", JsValue::FALSE), ("video", camera.into())])?;
+            let request = window
+                .navigator()
+                .media_devices()?
+                .get_user_media_with_constraints(
+                    constraints.unchecked_ref::<MediaStreamConstraints>(),
+                )?;
+            let stream: MediaStream = JsFuture::from(request).await?.dyn_into()?;
+
+            video.set_attribute("playsinline", "")?;
+            video.set_muted(true);
+            video.set_src_object(Some(&stream));
+            JsFuture::from(video.play()?).await?;
+            Ok(CameraScanner {
+                video,
+                stream,
+                detector: qr_detector()?,
+                decoder: Rc::new(RefCell::new(lenient_decoder())),
+            })
+        }
+
+        /// Whether frames are scanned natively with `BarcodeDetector`.
+        #[wasm_bindgen(getter = hasDetector)]
+        pub fn has_detector(&self) -> bool {
+            self.detector.is_some()
+        }
+
+        /// Detects the QR codes in the current video frame and feeds them to
+        /// the decoder. Resolves to the [`ScanProgress`] afterwards.
+        #[wasm_bindgen(js_name = scanFrame)]
+        pub fn scan_frame(&self) -> Promise {
+            let video = self.video.clone();
+            let detector = self.detector.clone();
+            let decoder = self.decoder.clone();
+            future_to_promise(async move {
+                let detector = detector
+                    .ok_or_else(|| JsValue::from_str("BarcodeDetector is not available"))?;
+                let detect: Function =
+                    Reflect::get(&detector, &JsValue::from_str("detect"))?.dyn_into()?;
+                let detection = Promise::resolve(&detect.call1(&detector, &video)?);
+                let codes: Array = JsFuture::from(detection).await?.dyn_into()?;
+                let texts: Vec<String> = codes
+                    .iter()
+                    .filter_map(|code| {
+                        Reflect::get(&code, &JsValue::from_str("rawValue"))
+                            .ok()?
+                            .as_string()
+                    })
+                    .collect();
+                let mut decoder = decoder.borrow_mut();
+                for text in texts {
+                    if decoder.is_complete() {
+                        break;
+                    }
+                    decoder.receive(&text).map_err(js_error)?;
+                }
+                Ok(ScanProgress::of(&decoder).into())
+            })
+        }
+
+        /// Feeds text decoded from a QR code by other means.
+        #[wasm_bindgen(js_name = receiveText)]
+        pub fn receive_text(&self, text: &str) -> Result<ScanProgress, JsError> {
+            let mut decoder = self.decoder.borrow_mut();
+            decoder.receive(text).map_err(js_error)?;
+            Ok(ScanProgress::of(&decoder))
+        }
+
+        pub fn message(&self) -> Result<Vec<u8>, JsError> {
+            self.decoder.borrow().message().map_err(js_error)
+        }
+
+        /// Starts a new scan session on the same camera.
+        pub fn reset(&self) {
+            self.decoder.borrow_mut().reset();
+        }
+
+        /// Stops the camera and detaches it from the video element.
+        pub fn stop(&self) {
+            for track in self.stream.get_tracks().iter() {
+                track.unchecked_into::<MediaStreamTrack>().stop();
+            }
+            self.video.set_src_object(None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decoder_skips_foreign_codes() {
+        let parts = crate::encode_bytes(&[0x42; 600]).expect("Encoding failed");
+        let mut decoder = WasmUrDecoder::new();
+        assert!(decoder.receive("https://example.com").is_ok());
+        let mut progress = None;
+        for part in &parts {
+            let Ok(next) = decoder.receive(part) else {
+                panic!("Receive failed");
+            };
+            progress = Some(next);
+        }
+        let progress = progress.expect("No parts");
+        assert!(progress.complete);
+        assert_eq!(progress.expected as usize, parts.len());
+        assert!(decoder
+            .message()
+            .is_ok_and(|message| message == [0x42; 600]));
+    }
+}
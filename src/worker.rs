@@ -0,0 +1,88 @@
+//! Background decode thread for non-async GUI apps: the camera callback sends
+//! scanned strings on one channel and the UI reads events from another.
+
+use std::string::String;
+use std::sync::mpsc::{Receiver, Sender};
+use std::thread::{self, JoinHandle};
+use std::vec::Vec;
+
+use crate::{DecodeEvent, QuantusUrError, UrDecoder};
+
+/// Event sent by the decode worker for every scanned string.
+#[derive(Debug)]
+pub enum WorkerEvent {
+    Progress {
+        received: usize,
+        expected: usize,
+    },
+    Duplicate {
+        sequence: u32,
+    },
+    /// The scan was skipped in lenient mode.
+    Skipped,
+    /// The scan was rejected; the session continues.
+    Rejected(QuantusUrError),
+    /// The decoded payload. The worker exits after sending it.
+    Complete(Vec<u8>),
+}
+
+/// Spawns a thread feeding every string received on `scans` to `decoder` and
+/// reporting the outcome on `events`.
+///
+/// The thread exits once the message is complete, the `scans` sender is
+/// dropped or the `events` receiver is dropped. The decoder is returned from
+/// the join handle so an unfinished session can be inspected or resumed.
+pub fn spawn_decode_worker(
+    mut decoder: UrDecoder,
+    scans: Receiver<String>,
+    events: Sender<WorkerEvent>,
+) -> JoinHandle<UrDecoder> {
+    thread::spawn(move || {
+        for scan in scans {
+            let event = match decoder.receive_event(&scan) {
+                Ok(DecodeEvent::Progress { received, expected }) => {
+                    WorkerEvent::Progress { received, expected }
+                }
+                Ok(DecodeEvent::Duplicate { sequence }) => WorkerEvent::Duplicate { sequence },
+                Ok(DecodeEvent::Skipped) => WorkerEvent::Skipped,
+                Ok(DecodeEvent::Complete) => match decoder.message() {
+                    Ok(payload) => WorkerEvent::Complete(payload),
+                    Err(e) => WorkerEvent::Rejected(e),
+                },
+                Err(e) => WorkerEvent::Rejected(e),
+            };
+            let done = matches!(event, WorkerEvent::Complete(_));
+            if events.send(event).is_err() || done {
+                break;
+            }
+        }
+        decoder
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encode_bytes;
+    use std::string::ToString;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn test_worker_decodes() {
+        let payload: Vec<u8> = (0..1500u32).map(|i| (i % 251) as u8).collect();
+        let parts = encode_bytes(&payload).expect("Encoding failed");
+        let (scan_tx, scan_rx) = channel();
+        let (event_tx, event_rx) = channel();
+        let handle = spawn_decode_worker(UrDecoder::new(), scan_rx, event_tx);
+
+        scan_tx.send("garbage".to_string()).unwrap();
+        for part in &parts {
+            scan_tx.send(part.clone()).unwrap();
+        }
+
+        assert!(matches!(event_rx.recv(), Ok(WorkerEvent::Rejected(_))));
+        let last = event_rx.iter().last().expect("No events");
+        assert!(matches!(last, WorkerEvent::Complete(ref p) if *p == payload));
+        assert!(handle.join().expect("Worker panicked").is_complete());
+    }
+}
@@ -0,0 +1,84 @@
+//! Allocation budgets, enforced with `CountingAllocator`.
+//!
+//! The allocator is installed here rather than in the library's unit tests,
+//! so only this test binary pays for the per-allocation bookkeeping. Run with
+//! `cargo test --features testing,std --test alloc_budget`.
+//!
+//! Budgets scale with the number of parts and the payload size and leave
+//! headroom over what the code needs today, so they fail on a change in
+//! complexity (per-part message copies, quadratic buffering) rather than on
+//! small tweaks.
+
+use quantus_ur::testing::{measure_allocations, AllocStats, CountingAllocator};
+use quantus_ur::UrDecoder;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Allocations per part of the minimal set, when encoding or decoding.
+const ALLOCS_PER_PART: usize = 96;
+/// Allocations for setting up an encoder or decoder session.
+const ALLOCS_PER_SESSION: usize = 256;
+/// Bytes allocated per payload byte, counting every part string and buffer
+/// growth on the way.
+const BYTES_PER_PAYLOAD_BYTE: usize = 64;
+
+fn payload() -> Vec<u8> {
+    (0..2000u32).map(|i| (i % 251) as u8).collect()
+}
+
+fn assert_within_budget(stats: AllocStats, parts: usize, payload_len: usize) {
+    let max_count = ALLOCS_PER_SESSION + parts * ALLOCS_PER_PART;
+    let max_bytes = payload_len * BYTES_PER_PAYLOAD_BYTE;
+    assert!(
+        stats.count <= max_count,
+        "{} allocations, budget {}",
+        stats.count,
+        max_count
+    );
+    assert!(
+        stats.bytes <= max_bytes,
+        "{} bytes allocated, budget {}",
+        stats.bytes,
+        max_bytes
+    );
+}
+
+#[test]
+fn test_single_part_allocation_free() {
+    let mut buf = [0u8; 256];
+    let (part, stats) =
+        measure_allocations(|| quantus_ur::encode_single_part_into(b"signature", &mut buf).is_ok());
+    assert!(part);
+    assert_eq!(stats, AllocStats::default());
+}
+
+#[test]
+fn test_encode_budget() {
+    let payload = payload();
+    let (parts, stats) = measure_allocations(|| quantus_ur::encode_bytes(&payload));
+    let parts = parts.expect("Encoding failed");
+    assert!(parts.len() > 1);
+    assert!(stats.count > 0 && stats.bytes >= payload.len());
+    assert_within_budget(stats, parts.len(), payload.len());
+}
+
+#[test]
+fn test_decode_budget() {
+    let payload = payload();
+    let parts = quantus_ur::encode_bytes(&payload).expect("Encoding failed");
+
+    let (decoded, stats) = measure_allocations(|| quantus_ur::decode_bytes(&parts));
+    assert_eq!(decoded.expect("Decoding failed"), payload);
+    assert_within_budget(stats, parts.len(), payload.len());
+
+    let (decoded, stats) = measure_allocations(|| {
+        let mut decoder = UrDecoder::new();
+        for part in &parts {
+            decoder.receive(part)?;
+        }
+        decoder.message()
+    });
+    assert_eq!(decoded.expect("Decoding failed"), payload);
+    assert_within_budget(stats, parts.len(), payload.len());
+}